@@ -40,6 +40,55 @@ macro_rules! fixed_type_tag {
     };
 }
 
+/// An entry in block content. The `type` decides which it is, so a malformed inline node is
+/// reported rather than read as an unknown block.
+enum BlockOrInline {
+    Block(AdfBlockNode),
+    Inline(AdfNode),
+}
+
+impl<'de> Deserialize<'de> for BlockOrInline {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let is_inline = value
+            .get("type")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|type_| type_.parse::<AdfNode>().ok())
+            .is_some_and(|node| node != AdfNode::Unknown);
+        let item = if is_inline {
+            serde_json::from_value(value).map(Self::Inline)
+        } else {
+            serde_json::from_value(value).map(Self::Block)
+        };
+        item.map_err(serde::de::Error::custom)
+    }
+}
+
+/// Deserializes block content, wrapping any runs of inline nodes in paragraphs. ADF keeps block
+/// and inline content apart, but lenient producers sometimes put text directly in a document,
+/// cell or other container.
+fn deserialize_block_content<'de, D>(deserializer: D) -> Result<Vec<AdfBlockNode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut blocks = vec![];
+    let mut inline = vec![];
+    for item in Vec::<BlockOrInline>::deserialize(deserializer)? {
+        match item {
+            BlockOrInline::Block(block) => {
+                blocks.extend(wrap_inline_in_paragraphs(std::mem::take(&mut inline)));
+                blocks.push(block);
+            }
+            BlockOrInline::Inline(node) => inline.push(node),
+        }
+    }
+    blocks.extend(wrap_inline_in_paragraphs(inline));
+    Ok(blocks)
+}
+
 fixed_type_tag!(TableHeaderType, "tableHeader");
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -48,6 +97,7 @@ pub struct TableHeader {
     type_: TableHeaderType,
     #[serde(skip_serializing_if = "Option::is_none")]
     attrs: Option<TableCellAttrs>,
+    #[serde(deserialize_with = "deserialize_block_content")]
     content: Vec<AdfBlockNode>,
 }
 
@@ -74,6 +124,7 @@ pub struct TableCell {
     type_: TableCellType,
     #[serde(skip_serializing_if = "Option::is_none")]
     attrs: Option<TableCellAttrs>,
+    #[serde(deserialize_with = "deserialize_block_content")]
     content: Vec<AdfBlockNode>,
 }
 
@@ -116,6 +167,13 @@ impl TableRowEntry {
             attrs,
         })
     }
+
+    pub fn content_mut(&mut self) -> &mut Vec<AdfBlockNode> {
+        match self {
+            Self::TableHeader(header) => &mut header.content,
+            Self::TableCell(cell) => &mut cell.content,
+        }
+    }
 }
 
 fixed_type_tag!(TableRowType, "tableRow");
@@ -139,6 +197,10 @@ impl TableRow {
         &self.content
     }
 
+    pub fn content_mut(&mut self) -> &mut Vec<TableRowEntry> {
+        &mut self.content
+    }
+
     pub fn unwrap(self) -> Vec<TableRowEntry> {
         self.content
     }
@@ -216,6 +278,7 @@ fixed_type_tag!(ListItemType, "listItem");
 pub struct ListItem {
     #[serde(rename = "type")]
     type_: ListItemType,
+    #[serde(deserialize_with = "deserialize_block_content")]
     content: Vec<AdfBlockNode>,
 }
 
@@ -262,6 +325,11 @@ pub enum AdfNode {
     Status {
         attrs: StatusAttrs,
     },
+    MediaInline {
+        attrs: MediaAttrs,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        marks: Option<Vec<MediaMark>>,
+    },
     #[serde(other)]
     Unknown,
 }
@@ -271,10 +339,12 @@ pub enum AdfNode {
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum AdfBlockNode {
     Doc {
+        #[serde(deserialize_with = "deserialize_block_content")]
         content: Vec<AdfBlockNode>,
         version: i32,
     },
     Blockquote {
+        #[serde(deserialize_with = "deserialize_block_content")]
         content: Vec<AdfBlockNode>,
     },
     BulletList {
@@ -287,11 +357,15 @@ pub enum AdfBlockNode {
         content: Option<Vec<AdfNode>>,
     },
     Expand {
+        #[serde(deserialize_with = "deserialize_block_content")]
         content: Vec<AdfBlockNode>,
+        #[serde(default)]
         attrs: ExpandAttrs,
     },
     NestedExpand {
+        #[serde(default)]
         attrs: NestedAttrs,
+        #[serde(deserialize_with = "deserialize_block_content")]
         content: Vec<AdfBlockNode>,
     },
     Paragraph {
@@ -306,12 +380,14 @@ pub enum AdfBlockNode {
     },
     Panel {
         attrs: PanelAttrs,
+        #[serde(deserialize_with = "deserialize_block_content")]
         content: Vec<AdfBlockNode>,
     },
     MediaGroup {
         content: Vec<MediaNode>,
     },
     MediaSingle {
+        #[serde(default)]
         attrs: MediaSingleAttrs,
         content: Vec<MediaNode>,
     },
@@ -330,24 +406,153 @@ pub enum AdfBlockNode {
         attrs: BlockCardAttrs,
     },
     TaskList {
+        #[serde(default)]
         attrs: LocalId,
         content: Vec<TaskItem>,
     },
     DecisionList {
         content: Vec<DecisionItem>,
+        #[serde(default)]
         attrs: LocalId,
     },
     #[serde(other)]
     Unknown,
 }
 
+impl Default for AdfBlockNode {
+    fn default() -> Self {
+        Self::EMPTY_DOC
+    }
+}
+
 impl AdfBlockNode {
+    /// A document with no content.
+    pub const EMPTY_DOC: Self = Self::Doc {
+        content: Vec::new(),
+        version: 1,
+    };
+
     pub fn unwrap_doc(&mut self) -> Vec<AdfBlockNode> {
         if let Self::Doc { content, .. } = self {
             return content.clone();
         }
         Vec::new()
     }
+
+    /// Serializes the node with each text node's marks in the order the Jira API returns them,
+    /// so documents stored through the API don't show spurious diffs. Editor-only attrs outside
+    /// the ADF schema, like an expand's `__expanded`, are left out.
+    pub fn to_api_json(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        shape_for_api(&mut value);
+        Ok(value)
+    }
+
+    /// Wraps inline content in a paragraph, using `content: None` when there is none.
+    pub fn paragraph_from(inline: Vec<AdfNode>) -> Self {
+        let content = if inline.is_empty() {
+            None
+        } else {
+            Some(inline)
+        };
+        Self::Paragraph { content }
+    }
+
+    /// Calls `f` with each list of blocks directly inside this node: the content of a container,
+    /// of each list item, and of each table cell and header.
+    pub fn for_each_child_blocks_mut(&mut self, mut f: impl FnMut(&mut Vec<AdfBlockNode>)) {
+        match self {
+            Self::Doc { content, .. }
+            | Self::Blockquote { content }
+            | Self::Expand { content, .. }
+            | Self::NestedExpand { content, .. }
+            | Self::Panel { content, .. } => f(content),
+            Self::BulletList { content } | Self::OrderedList { content, .. } => {
+                content.iter_mut().for_each(|item| f(&mut item.content));
+            }
+            Self::Table { content, .. } => {
+                content
+                    .iter_mut()
+                    .flat_map(|row| row.content.iter_mut())
+                    .for_each(|entry| f(entry.content_mut()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Calls `f` with each list of inline nodes directly inside this node: the content of a
+    /// paragraph, heading or code block, and of each task and decision item.
+    pub fn for_each_child_inline_mut(&mut self, mut f: impl FnMut(&mut Vec<AdfNode>)) {
+        match self {
+            Self::Paragraph { content }
+            | Self::Heading { content, .. }
+            | Self::CodeBlock { content, .. } => {
+                if let Some(inline) = content {
+                    f(inline);
+                }
+            }
+            Self::TaskList { content, .. } => {
+                content.iter_mut().for_each(|item| f(&mut item.content));
+            }
+            Self::DecisionList { content, .. } => {
+                content.iter_mut().for_each(|item| f(&mut item.content));
+            }
+            _ => {}
+        }
+    }
+
+    /// Calls `f` on this node and then on every block inside it, parents before their children.
+    pub fn visit_blocks_mut(&mut self, f: &mut impl FnMut(&mut AdfBlockNode)) {
+        f(self);
+        self.for_each_child_blocks_mut(|blocks| {
+            blocks
+                .iter_mut()
+                .for_each(|child| child.visit_blocks_mut(f));
+        });
+    }
+
+    /// Rewrites every empty paragraph in the tree to the canonical `content: None` form.
+    ///
+    /// `Paragraph { content: Some(vec![]) }` and `Paragraph { content: None }` mean the same
+    /// thing, but serialize differently. Normalizing keeps the JSON output deterministic.
+    pub fn normalize_empty_paragraphs(&mut self) {
+        self.visit_blocks_mut(&mut |node| {
+            if let Self::Paragraph { content } = node
+                && content.as_ref().is_some_and(Vec::is_empty)
+            {
+                *content = None;
+            }
+        });
+    }
+
+    /// Replaces every paragraph holding nothing but an inline card with a block card for the
+    /// same URL, as Atlassian's editors do with a link on its own line. Only paragraphs directly
+    /// in the document or in a table cell are replaced, as those are where ADF allows block cards.
+    pub fn promote_standalone_cards(&mut self) {
+        self.visit_blocks_mut(&mut |node| {
+            if matches!(node, Self::Doc { .. } | Self::Table { .. }) {
+                node.for_each_child_blocks_mut(|blocks| {
+                    blocks.iter_mut().for_each(Self::promote_standalone_card);
+                });
+            }
+        });
+    }
+
+    fn promote_standalone_card(&mut self) {
+        if let Self::Paragraph {
+            content: Some(nodes),
+        } = self
+            && let [AdfNode::InlineCard { attrs }] = nodes.as_slice()
+            && let Some(url) = &attrs.url
+        {
+            *self = Self::BlockCard {
+                attrs: BlockCardAttrs {
+                    datasource: None,
+                    url: url.clone(),
+                },
+            };
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Default)]
@@ -404,7 +609,7 @@ impl AdfMark {
             },
             AdfMark::TextColor { color } => {
                 if let Some(text_color) = TextColor::from_hex_string(color) {
-                    return Some(format!("{{color:{}}}", text_color.to_string()));
+                    return Some(format!("{{color:{}}}", text_color));
                 }
                 return None;
             }
@@ -413,6 +618,107 @@ impl AdfMark {
             }
         })
     }
+
+    /// The CSS color of a text or background color mark. Palette names such as `bold_blue` or
+    /// `red` are resolved to their hex value; anything else, hex included, is kept as-is.
+    pub fn canonical_color(&self) -> Option<String> {
+        let (AdfMark::TextColor { color } | AdfMark::BackgroundColor { color }) = self else {
+            return None;
+        };
+        Some(match color.parse::<TextColor>() {
+            Ok(palette_color) => palette_color.as_hex_string(),
+            _ => color.clone(),
+        })
+    }
+}
+
+/// Serializes ADF as compact JSON, ready to send to the Jira API.
+pub fn to_json(adf: &AdfBlockNode) -> serde_json::Result<String> {
+    serde_json::to_string(adf)
+}
+
+/// Serializes ADF as indented JSON, for reading.
+pub fn to_json_pretty(adf: &AdfBlockNode) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(adf)
+}
+
+/// Groups loose inline content into paragraphs.
+///
+/// Two consecutive hard breaks end a paragraph, mirroring how `<br><br>` is parsed. Paragraphs
+/// left empty by the split are dropped.
+pub fn wrap_inline_in_paragraphs(inline: Vec<AdfNode>) -> Vec<AdfBlockNode> {
+    let mut paragraphs = vec![];
+    let mut current: Vec<AdfNode> = vec![];
+    for node in inline {
+        if node == AdfNode::HardBreak && current.last() == Some(&AdfNode::HardBreak) {
+            current.pop();
+            if !current.is_empty() {
+                paragraphs.push(AdfBlockNode::paragraph_from(std::mem::take(&mut current)));
+            }
+            continue;
+        }
+        current.push(node);
+    }
+    if !current.is_empty() {
+        paragraphs.push(AdfBlockNode::paragraph_from(current));
+    }
+    paragraphs
+}
+
+/// The position of a mark in the canonical mark order, lowest first.
+///
+/// This is the order Atlassian's editor schema declares the marks in. ProseMirror keeps a node's
+/// marks in schema order, so it is also the order the Jira API returns them in.
+pub fn canonical_rank(mark: &AdfMark) -> u8 {
+    match mark {
+        AdfMark::Link(_) => 0,
+        AdfMark::Em => 1,
+        AdfMark::Strong => 2,
+        AdfMark::TextColor { .. } => 3,
+        AdfMark::BackgroundColor { .. } => 4,
+        AdfMark::Strike => 5,
+        AdfMark::Subsup { .. } => 6,
+        AdfMark::Underline => 7,
+        AdfMark::Code => 8,
+    }
+}
+
+/// Sorts marks into the canonical order given by [`canonical_rank`].
+///
+/// The sort is stable, so marks of the same kind keep their relative order.
+pub fn sort_marks(marks: &mut [AdfMark]) {
+    marks.sort_by_key(canonical_rank);
+}
+
+/// Moves a link ahead of a code mark, so the link wraps the code.
+///
+/// Code excludes every other mark except a link, as in Atlassian's schema, so `<a><code>` and
+/// `<code><a>` both give linked code text. Whatever order a document lists the two marks in,
+/// the link is rendered around the code, since a link inside a Markdown code span is literal.
+pub fn order_code_link(marks: &mut [AdfMark]) {
+    if marks.contains(&AdfMark::Code) {
+        marks.sort_by_key(|mark| !matches!(mark, AdfMark::Link(_)));
+    }
+}
+
+/// Sorts every `marks` array in serialized ADF by [`canonical_rank`]. Marks this crate doesn't
+/// know are kept after the rest.
+fn shape_for_api(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            if let Some(serde_json::Value::Object(attrs)) = object.get_mut("attrs") {
+                attrs.remove("__expanded");
+            }
+            if let Some(serde_json::Value::Array(marks)) = object.get_mut("marks") {
+                marks.sort_by_key(|mark| {
+                    AdfMark::deserialize(mark).map_or(u8::MAX, |mark| canonical_rank(&mark))
+                });
+            }
+            object.values_mut().for_each(shape_for_api);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(shape_for_api),
+        _ => {}
+    }
 }
 
 pub enum ParseNextResponse {
@@ -530,13 +836,42 @@ pub enum Subsup {
 
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Default)]
 pub struct HeadingAttrs {
+    #[serde(deserialize_with = "deserialize_heading_level")]
     pub level: u8, // Heading level (1 to 6)
+    /// Attributes this crate doesn't model, such as those of collapsible Confluence headings,
+    /// kept so that they survive deserializing and serializing again. HTML doesn't carry them.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Clamps a heading level into the valid 1 to 6 range, warning when it was outside it.
+pub fn clamp_heading_level(level: u8) -> u8 {
+    let clamped = level.clamp(1, 6);
+    if clamped != level {
+        tracing::warn!(level, clamped, "Heading level out of range");
+    }
+    clamped
+}
+
+fn deserialize_heading_level<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    u8::deserialize(deserializer).map(clamp_heading_level)
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Default)]
 pub struct ExpandAttrs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    /// Whether the expand starts open. ADF has no attribute for this, so it is carried in the
+    /// editor's `__expanded` attr, which Atlassian strips when storing the document.
+    #[serde(
+        rename = "__expanded",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub expanded: bool,
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Default)]
@@ -545,6 +880,44 @@ pub struct CodeBlockAttrs {
     pub language: Option<String>, // Optional programming language
 }
 
+/// Common aliases for code block languages, paired with the name they normalize to.
+const LANGUAGE_ALIASES: [(&str, &str); 24] = [
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("mjs", "javascript"),
+    ("node", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("py", "python"),
+    ("py3", "python"),
+    ("python3", "python"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("golang", "go"),
+    ("yml", "yaml"),
+    ("md", "markdown"),
+    ("cs", "csharp"),
+    ("c#", "csharp"),
+    ("c++", "cpp"),
+    ("cc", "cpp"),
+    ("kt", "kotlin"),
+    ("ps1", "powershell"),
+    ("htm", "html"),
+];
+
+/// Normalizes a code block language to a canonical lowercase name, so that aliases such as
+/// `js` and `javascript` highlight the same way. Unknown languages are only lowercased.
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language, |(_, canonical)| canonical.to_string())
+}
+
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Default)]
 pub struct OrderedListAttrs {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -561,6 +934,8 @@ pub struct DateAttrs {
 pub struct EmojiAttrs {
     pub short_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
 }
 
@@ -598,6 +973,7 @@ pub struct MediaNode {
 pub struct MediaAttrs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub alt: Option<String>,
+    #[serde(default)]
     pub collection: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub height: Option<u32>,
@@ -649,7 +1025,15 @@ pub struct MentionAttrs {
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
 pub struct NestedAttrs {
+    #[serde(default)]
     pub title: String,
+    /// Whether the expand starts open, as with [`ExpandAttrs::expanded`].
+    #[serde(
+        rename = "__expanded",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub expanded: bool,
 }
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
@@ -693,8 +1077,16 @@ pub struct TableCellAttrs {
 }
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct MediaSingleAttrs {
+    #[serde(default)]
     pub layout: String,
+    /// Kept as a JSON number because percentage widths are often fractional.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<serde_json::Number>,
+    /// `percentage` or `pixel`; a missing type means a percentage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width_type: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
@@ -708,7 +1100,9 @@ pub enum TaskItemState {
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskItemAttrs {
+    #[serde(default)]
     pub local_id: String,
+    #[serde(default)]
     pub state: TaskItemState,
 }
 
@@ -716,20 +1110,25 @@ fixed_type_tag!(DecisionItemState, "DECIDED");
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct DecisionItemAttrs {
+    #[serde(default)]
     pub state: DecisionItemState,
+    #[serde(default)]
     pub local_id: String,
 }
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct LocalId {
+    #[serde(default)]
     pub local_id: String,
 }
 
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockCardAttrs {
-    pub datasource: DataSourceAttrs,
+    /// Absent for a plain URL card, which shows the linked page rather than a query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datasource: Option<DataSourceAttrs>,
     pub url: String,
 }
 
@@ -765,3 +1164,397 @@ pub struct TableViewProperties {
 pub struct TableColumn {
     pub key: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_inline_in_paragraphs() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let paragraphs = wrap_inline_in_paragraphs(vec![
+            text("one"),
+            AdfNode::HardBreak,
+            text("still one"),
+            AdfNode::HardBreak,
+            AdfNode::HardBreak,
+            text("two"),
+            AdfNode::HardBreak,
+            AdfNode::HardBreak,
+        ]);
+        assert_eq!(
+            paragraphs,
+            vec![
+                AdfBlockNode::paragraph_from(vec![
+                    text("one"),
+                    AdfNode::HardBreak,
+                    text("still one"),
+                ]),
+                AdfBlockNode::paragraph_from(vec![text("two")]),
+            ]
+        );
+        assert_eq!(
+            AdfBlockNode::paragraph_from(vec![]),
+            AdfBlockNode::Paragraph { content: None }
+        );
+        assert!(wrap_inline_in_paragraphs(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_minimal_attrs_deserialize() {
+        let mention: AdfNode = serde_json::from_value(serde_json::json!({
+            "type": "mention",
+            "attrs": { "id": "user-1" },
+        }))
+        .unwrap();
+        assert_eq!(
+            mention,
+            AdfNode::Mention {
+                attrs: MentionAttrs {
+                    id: "user-1".into(),
+                    ..Default::default()
+                },
+            }
+        );
+
+        let doc: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                {
+                    "type": "mediaSingle",
+                    "content": [{
+                        "type": "media",
+                        "attrs": { "id": "media-1", "type": "file" },
+                    }],
+                },
+                { "type": "expand", "content": [] },
+                { "type": "nestedExpand", "content": [] },
+                {
+                    "type": "taskList",
+                    "content": [{ "type": "taskItem", "content": [], "attrs": {} }],
+                },
+                {
+                    "type": "decisionList",
+                    "content": [{ "type": "decisionItem", "content": [], "attrs": {} }],
+                },
+            ],
+        }))
+        .unwrap();
+        let AdfBlockNode::Doc { content, .. } = doc else {
+            panic!("Expected a document");
+        };
+        assert_eq!(content.len(), 5);
+        assert!(!content.contains(&AdfBlockNode::Unknown));
+    }
+
+    #[test]
+    fn test_to_api_json_mark_order() {
+        // A paragraph as the Jira API returns it, marks in schema order
+        let payload = serde_json::json!({
+            "type": "paragraph",
+            "content": [
+                {
+                    "type": "text",
+                    "text": "styled",
+                    "marks": [
+                        { "type": "link", "attrs": { "href": "https://example.com" } },
+                        { "type": "em" },
+                        { "type": "strong" },
+                        { "type": "textColor", "attrs": { "color": "#ff5630" } },
+                        { "type": "strike" },
+                        { "type": "underline" },
+                    ],
+                },
+                {
+                    "type": "text",
+                    "text": "linked code",
+                    "marks": [
+                        { "type": "link", "attrs": { "href": "https://example.com" } },
+                        { "type": "code" },
+                    ],
+                },
+            ],
+        });
+        let paragraph: AdfBlockNode = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(paragraph.to_api_json().unwrap(), payload);
+
+        // The canonical order is the API's order, so sorting leaves the marks alone
+        let AdfBlockNode::Paragraph {
+            content: Some(inline),
+        } = &paragraph
+        else {
+            panic!("Expected a paragraph");
+        };
+        for node in inline {
+            let AdfNode::Text {
+                marks: Some(marks), ..
+            } = node
+            else {
+                panic!("Expected marked text");
+            };
+            let mut sorted = marks.clone();
+            sort_marks(&mut sorted);
+            assert_eq!(&sorted, marks);
+        }
+
+        // Marks built in any other order are serialized in the API's order
+        let shuffled = AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: "styled".into(),
+                marks: Some(vec![
+                    AdfMark::Underline,
+                    AdfMark::Strong,
+                    AdfMark::TextColor {
+                        color: "#ff5630".into(),
+                    },
+                    AdfMark::Strike,
+                    AdfMark::Em,
+                    AdfMark::Link(LinkMark {
+                        href: "https://example.com".into(),
+                        ..Default::default()
+                    }),
+                ]),
+            }]),
+        };
+        assert_eq!(
+            shuffled.to_api_json().unwrap()["content"][0],
+            payload["content"][0]
+        );
+    }
+
+    #[test]
+    fn test_to_api_json_skips_expanded() {
+        let expand = AdfBlockNode::Expand {
+            attrs: ExpandAttrs {
+                title: Some("Details".into()),
+                expanded: true,
+            },
+            content: vec![AdfBlockNode::NestedExpand {
+                attrs: NestedAttrs {
+                    title: "More".into(),
+                    expanded: true,
+                },
+                content: vec![AdfBlockNode::paragraph_from(vec![])],
+            }],
+        };
+        assert_eq!(
+            serde_json::to_value(&expand).unwrap()["attrs"]["__expanded"],
+            true
+        );
+        let api = expand.to_api_json().unwrap();
+        assert_eq!(api["attrs"], serde_json::json!({ "title": "Details" }));
+        assert_eq!(
+            api["content"][0]["attrs"],
+            serde_json::json!({ "title": "More" })
+        );
+    }
+
+    #[test]
+    fn test_sort_marks() {
+        let mut marks = vec![
+            AdfMark::BackgroundColor {
+                color: "#ffffff".into(),
+            },
+            AdfMark::Underline,
+            AdfMark::Em,
+            AdfMark::Link(LinkMark {
+                href: "https://example.com".into(),
+                ..Default::default()
+            }),
+            AdfMark::Strong,
+        ];
+        sort_marks(&mut marks);
+        assert_eq!(
+            marks,
+            vec![
+                AdfMark::Link(LinkMark {
+                    href: "https://example.com".into(),
+                    ..Default::default()
+                }),
+                AdfMark::Em,
+                AdfMark::Strong,
+                AdfMark::BackgroundColor {
+                    color: "#ffffff".into(),
+                },
+                AdfMark::Underline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heading_level_clamped() {
+        for (level, expected) in [(0, 1), (3, 3), (9, 6)] {
+            let heading: AdfBlockNode = serde_json::from_value(serde_json::json!({
+                "type": "heading",
+                "attrs": { "level": level },
+                "content": [{ "type": "text", "text": "Title" }],
+            }))
+            .unwrap();
+            assert!(
+                matches!(heading, AdfBlockNode::Heading { attrs: HeadingAttrs { level, .. }, .. } if level == expected),
+                "Level {level} parsed as {heading:?}"
+            );
+        }
+
+        // Levels built in code are clamped when rendered
+        let html = crate::adf_to_html::adf_to_html(
+            vec![AdfBlockNode::Heading {
+                attrs: HeadingAttrs {
+                    level: 0,
+                    ..Default::default()
+                },
+                content: None,
+            }],
+            "",
+        );
+        assert!(html.contains("<h1>"), "{html}");
+    }
+
+    #[test]
+    fn test_default_is_empty_doc() {
+        assert_eq!(AdfBlockNode::default(), AdfBlockNode::EMPTY_DOC);
+        assert_eq!(
+            serde_json::to_value(AdfBlockNode::default()).unwrap(),
+            serde_json::json!({ "type": "doc", "version": 1, "content": [] })
+        );
+    }
+
+    #[test]
+    fn test_stray_inline_nodes_wrapped_in_paragraphs() {
+        let adf: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                { "type": "text", "text": "Loose " },
+                { "type": "text", "text": "text", "marks": [{ "type": "strong" }] },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Proper" }] },
+                { "type": "hardBreak" },
+                {
+                    "type": "panel",
+                    "attrs": { "panelType": "info" },
+                    "content": [{ "type": "text", "text": "In a panel" }],
+                },
+            ],
+        }))
+        .unwrap();
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![
+                    AdfBlockNode::paragraph_from(vec![
+                        text("Loose "),
+                        AdfNode::Text {
+                            text: "text".into(),
+                            marks: Some(vec![AdfMark::Strong]),
+                        },
+                    ]),
+                    AdfBlockNode::paragraph_from(vec![text("Proper")]),
+                    AdfBlockNode::paragraph_from(vec![AdfNode::HardBreak]),
+                    AdfBlockNode::Panel {
+                        attrs: PanelAttrs {
+                            panel_type: "info".into(),
+                        },
+                        content: vec![AdfBlockNode::paragraph_from(vec![text("In a panel")])],
+                    },
+                ],
+                version: 1,
+            }
+        );
+
+        // Malformed inline nodes are an error, not an unknown block
+        let error = serde_json::from_value::<AdfBlockNode>(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{ "type": "text", "marks": [] }],
+        }))
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("missing field `text`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Rule],
+            version: 1,
+        };
+        assert_eq!(
+            to_json(&adf).unwrap(),
+            r#"{"type":"doc","content":[{"type":"rule"}],"version":1}"#
+        );
+        let pretty = to_json_pretty(&adf).unwrap();
+        assert!(pretty.contains("\n  \"type\": \"doc\","), "{pretty}");
+        assert_eq!(serde_json::from_str::<AdfBlockNode>(&pretty).unwrap(), adf);
+    }
+
+    #[test]
+    fn test_canonical_color() {
+        let text_color = |color: &str| AdfMark::TextColor {
+            color: color.into(),
+        };
+        assert_eq!(
+            text_color("bold_blue").canonical_color().as_deref(),
+            Some("#0747a6")
+        );
+        assert_eq!(
+            text_color("#0747a6").canonical_color().as_deref(),
+            Some("#0747a6")
+        );
+        assert_eq!(
+            text_color("rebeccapurple").canonical_color().as_deref(),
+            Some("rebeccapurple")
+        );
+        assert_eq!(
+            text_color("red").canonical_color().as_deref(),
+            Some("#ff5630")
+        );
+        assert_eq!(
+            AdfMark::BackgroundColor {
+                color: "subtle_yellow".into()
+            }
+            .canonical_color()
+            .as_deref(),
+            Some("#fff0b3")
+        );
+        assert_eq!(AdfMark::Strong.canonical_color(), None);
+    }
+
+    #[test]
+    fn test_heading_extra_attrs_roundtrip() {
+        let json = serde_json::json!({
+            "type": "heading",
+            "attrs": { "level": 2, "localId": "h-1", "collapsible": { "collapsed": true } },
+            "content": [{ "type": "text", "text": "Details" }],
+        });
+        let heading: AdfBlockNode = serde_json::from_value(json.clone()).unwrap();
+        let AdfBlockNode::Heading { attrs, .. } = &heading else {
+            panic!("Expected a heading, got {heading:?}");
+        };
+        assert_eq!(attrs.level, 2);
+        assert_eq!(attrs.extra["localId"], "h-1");
+        assert_eq!(serde_json::to_value(&heading).unwrap(), json);
+
+        // Headings without extra attributes serialize as before
+        let heading = AdfBlockNode::Heading {
+            attrs: HeadingAttrs {
+                level: 1,
+                ..Default::default()
+            },
+            content: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&heading).unwrap(),
+            serde_json::json!({ "type": "heading", "attrs": { "level": 1 } })
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::mem::discriminant;
+
+use super::adf_types::{AdfBlockNode, AdfMark, AdfNode, sort_marks};
+use super::retain::NodeKind;
+
+/// A repair made by [`validate_and_fix`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FixApplied {
+    /// An empty table cell or header was given an empty paragraph, as ADF requires content.
+    EmptyCellFilled,
+    /// Marks were dropped from code text, which may only also carry a link.
+    CodeMarksDropped { dropped: Vec<AdfMark> },
+    /// A heading level outside 1 to 6 was clamped into range.
+    HeadingLevelClamped { from: u8, to: u8 },
+    /// A list with no items was removed.
+    EmptyListRemoved(NodeKind),
+    /// A blockquote, panel, expand or list item with no content was given an empty paragraph,
+    /// as ADF requires content. Holds the kind of the container, or of the list for a list item.
+    EmptyContentFilled(NodeKind),
+    /// A text node's marks were deduplicated, keeping the first of each type, or sorted into
+    /// the canonical order.
+    MarksNormalized,
+}
+
+/// Repairs the common ways a document breaks the ADF schema, so that Jira accepts it, and
+/// reports each repair made. An empty result means the document was left as it was.
+///
+/// Empty table cells, list items and containers get an empty paragraph, lists with no items are
+/// removed, heading levels are clamped, and text marks are deduplicated and sorted with
+/// [`sort_marks`]. Text marked as code keeps only the code mark and a link.
+pub fn validate_and_fix(adf: &mut AdfBlockNode) -> Vec<FixApplied> {
+    let mut fixes = vec![];
+    fix_block(adf, &mut fixes);
+    fixes
+}
+
+fn fix_blocks(nodes: &mut Vec<AdfBlockNode>, fixes: &mut Vec<FixApplied>) {
+    for node in nodes.iter_mut() {
+        fix_block(node, fixes);
+    }
+    nodes.retain(|node| {
+        let empty = match node {
+            AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } => {
+                content.is_empty()
+            }
+            AdfBlockNode::TaskList { content, .. } => content.is_empty(),
+            AdfBlockNode::DecisionList { content, .. } => content.is_empty(),
+            _ => false,
+        };
+        if empty {
+            let kind = NodeKind::of_block(node).expect("Lists have a kind");
+            fixes.push(FixApplied::EmptyListRemoved(kind));
+        }
+        !empty
+    });
+}
+
+fn fix_block(node: &mut AdfBlockNode, fixes: &mut Vec<FixApplied>) {
+    if let AdfBlockNode::Heading { attrs, .. } = node {
+        let level = attrs.level.clamp(1, 6);
+        if level != attrs.level {
+            fixes.push(FixApplied::HeadingLevelClamped {
+                from: attrs.level,
+                to: level,
+            });
+            attrs.level = level;
+        }
+    }
+    node.for_each_child_blocks_mut(|blocks| fix_blocks(blocks, fixes));
+    node.for_each_child_inline_mut(|inline| fix_inline(inline, fixes));
+
+    // ADF requires content in everything but the document, which may have just been removed
+    if let Some(kind) = NodeKind::of_block(node).filter(|kind| *kind != NodeKind::Doc) {
+        node.for_each_child_blocks_mut(|blocks| {
+            if blocks.is_empty() {
+                fixes.push(match kind {
+                    NodeKind::Table => FixApplied::EmptyCellFilled,
+                    kind => FixApplied::EmptyContentFilled(kind),
+                });
+                blocks.push(AdfBlockNode::Paragraph { content: None });
+            }
+        });
+    }
+}
+
+fn fix_inline(nodes: &mut [AdfNode], fixes: &mut Vec<FixApplied>) {
+    for node in nodes {
+        if let AdfNode::Text {
+            marks: Some(marks), ..
+        } = node
+        {
+            fix_marks(marks, fixes);
+        }
+    }
+}
+
+fn fix_marks(marks: &mut Vec<AdfMark>, fixes: &mut Vec<FixApplied>) {
+    if marks.contains(&AdfMark::Code) {
+        let (kept, dropped) = std::mem::take(marks)
+            .into_iter()
+            .partition(|mark| matches!(mark, AdfMark::Code | AdfMark::Link(_)));
+        *marks = kept;
+        if !dropped.is_empty() {
+            fixes.push(FixApplied::CodeMarksDropped { dropped });
+        }
+    }
+
+    let mut normalized: Vec<AdfMark> = Vec::with_capacity(marks.len());
+    for mark in marks.iter() {
+        if !normalized
+            .iter()
+            .any(|kept| discriminant(kept) == discriminant(mark))
+        {
+            normalized.push(mark.clone());
+        }
+    }
+    sort_marks(&mut normalized);
+    if normalized != *marks {
+        *marks = normalized;
+        fixes.push(FixApplied::MarksNormalized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adf::adf_types::{HeadingAttrs, LinkMark, ListItem, TableRow, TableRowEntry};
+
+    #[test]
+    fn test_validate_and_fix() {
+        let text = |text: &str, marks: Option<Vec<AdfMark>>| AdfNode::Text {
+            text: text.into(),
+            marks,
+        };
+        let link = AdfMark::Link(LinkMark {
+            href: "https://example.com".into(),
+            ..Default::default()
+        });
+        let mut adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 9,
+                        ..Default::default()
+                    },
+                    content: Some(vec![text("Title", None)]),
+                },
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![
+                        text(
+                            "code",
+                            Some(vec![AdfMark::Strong, AdfMark::Code, link.clone()]),
+                        ),
+                        text(
+                            "bold",
+                            Some(vec![AdfMark::Em, AdfMark::Strong, AdfMark::Em]),
+                        ),
+                    ]),
+                },
+                AdfBlockNode::BulletList { content: vec![] },
+                AdfBlockNode::Table {
+                    attrs: None,
+                    content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                        vec![AdfBlockNode::OrderedList {
+                            attrs: None,
+                            content: vec![],
+                        }],
+                        None,
+                    )])],
+                },
+            ],
+            version: 1,
+        };
+
+        let fixes = validate_and_fix(&mut adf);
+        assert_eq!(
+            fixes,
+            vec![
+                FixApplied::HeadingLevelClamped { from: 9, to: 6 },
+                FixApplied::CodeMarksDropped {
+                    dropped: vec![AdfMark::Strong],
+                },
+                FixApplied::MarksNormalized,
+                FixApplied::MarksNormalized,
+                FixApplied::EmptyListRemoved(NodeKind::OrderedList),
+                FixApplied::EmptyCellFilled,
+                FixApplied::EmptyListRemoved(NodeKind::BulletList),
+            ]
+        );
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![
+                    AdfBlockNode::Heading {
+                        attrs: HeadingAttrs {
+                            level: 6,
+                            ..Default::default()
+                        },
+                        content: Some(vec![text("Title", None)]),
+                    },
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![
+                            text("code", Some(vec![link, AdfMark::Code])),
+                            text("bold", Some(vec![AdfMark::Em, AdfMark::Strong])),
+                        ]),
+                    },
+                    AdfBlockNode::Table {
+                        attrs: None,
+                        content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                            vec![AdfBlockNode::Paragraph { content: None }],
+                            None,
+                        )])],
+                    },
+                ],
+                version: 1,
+            }
+        );
+
+        // A valid document is left alone
+        assert!(validate_and_fix(&mut adf).is_empty());
+    }
+
+    #[test]
+    fn test_validate_and_fix_fills_emptied_containers() {
+        // `- - ` is a list item holding nothing but an empty list
+        let mut adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![AdfBlockNode::BulletList {
+                        content: vec![],
+                    }])],
+                },
+                AdfBlockNode::Blockquote {
+                    content: vec![AdfBlockNode::TaskList {
+                        attrs: Default::default(),
+                        content: vec![],
+                    }],
+                },
+            ],
+            version: 1,
+        };
+
+        let fixes = validate_and_fix(&mut adf);
+        assert_eq!(
+            fixes,
+            vec![
+                FixApplied::EmptyListRemoved(NodeKind::BulletList),
+                FixApplied::EmptyContentFilled(NodeKind::BulletList),
+                FixApplied::EmptyListRemoved(NodeKind::TaskList),
+                FixApplied::EmptyContentFilled(NodeKind::Blockquote),
+            ]
+        );
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![
+                    AdfBlockNode::BulletList {
+                        content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                            content: None
+                        }])],
+                    },
+                    AdfBlockNode::Blockquote {
+                        content: vec![AdfBlockNode::Paragraph { content: None }],
+                    },
+                ],
+                version: 1,
+            }
+        );
+        assert!(validate_and_fix(&mut adf).is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+
+use super::adf_types::AdfBlockNode;
+
+/// The ADF schema version Jira accepts, and the only one Atlassian has published so far.
+pub const ADF_VERSION: i32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// The document or the target is at a version with no known schema.
+    UnsupportedVersion(i32),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported ADF version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// The schema differences between one version and the one before it.
+pub struct Migration {
+    /// The version this migration upgrades to.
+    pub version: i32,
+    /// Applied to every block node when moving up to `version`.
+    pub upgrade: fn(&mut AdfBlockNode),
+    /// Applied to every block node when moving back down from `version`.
+    pub downgrade: fn(&mut AdfBlockNode),
+}
+
+/// Migrations between the published ADF versions. Version 1 is the only one, so there are none
+/// yet; newer attributes such as `mediaSingle`'s `width` and `widthType` are part of version 1.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Rewrites the document for the schema of the `target` version and sets `Doc.version` to match.
+///
+/// Only [`ADF_VERSION`] is known, so this currently checks the versions and leaves the content
+/// alone. Use [`migrate_with`] to apply migrations of your own.
+pub fn migrate_to_version(adf: &mut AdfBlockNode, target: i32) -> Result<(), MigrationError> {
+    migrate_with(adf, target, MIGRATIONS)
+}
+
+/// Like [`migrate_to_version`], with the given migrations in place of the built-in ones.
+///
+/// The migrations are ordered by version, each moving a document from `version - 1` to
+/// `version`, so the known versions run from [`ADF_VERSION`] to the last of them. Migrations are
+/// applied one version at a time, in either direction. A root node other than a document has no
+/// version of its own and is taken to be at the newest known version.
+pub fn migrate_with(
+    adf: &mut AdfBlockNode,
+    target: i32,
+    migrations: &[Migration],
+) -> Result<(), MigrationError> {
+    let latest = migrations
+        .last()
+        .map_or(ADF_VERSION, |migration| migration.version);
+    let current = match adf {
+        AdfBlockNode::Doc { version, .. } => *version,
+        _ => latest,
+    };
+    for version in [current, target] {
+        if !(ADF_VERSION..=latest).contains(&version) {
+            return Err(MigrationError::UnsupportedVersion(version));
+        }
+    }
+
+    if current < target {
+        for migration in migrations
+            .iter()
+            .filter(|migration| migration.version > current && migration.version <= target)
+        {
+            adf.visit_blocks_mut(&mut |node| (migration.upgrade)(node));
+        }
+    } else {
+        for migration in migrations
+            .iter()
+            .rev()
+            .filter(|migration| migration.version > target && migration.version <= current)
+        {
+            adf.visit_blocks_mut(&mut |node| (migration.downgrade)(node));
+        }
+    }
+
+    if let AdfBlockNode::Doc { version, .. } = adf {
+        *version = target;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adf::adf_types::MediaSingleAttrs;
+
+    fn doc(version: i32, width: Option<serde_json::Number>) -> AdfBlockNode {
+        AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Blockquote {
+                content: vec![AdfBlockNode::MediaSingle {
+                    attrs: MediaSingleAttrs {
+                        layout: "center".into(),
+                        width_type: width.as_ref().map(|_| "percentage".into()),
+                        width,
+                    },
+                    content: vec![],
+                }],
+            }],
+            version,
+        }
+    }
+
+    #[test]
+    fn test_migrate_to_version() {
+        let width = serde_json::Number::from_f64(66.67);
+
+        // Version 1 documents keep their width
+        let mut adf = doc(1, width.clone());
+        migrate_to_version(&mut adf, ADF_VERSION).unwrap();
+        assert_eq!(adf, doc(1, width.clone()));
+        assert_eq!(
+            migrate_to_version(&mut adf, 2),
+            Err(MigrationError::UnsupportedVersion(2))
+        );
+        let mut adf = doc(0, None);
+        assert_eq!(
+            migrate_to_version(&mut adf, 1),
+            Err(MigrationError::UnsupportedVersion(0))
+        );
+
+        // A hypothetical version 2 that introduced the width, to exercise the migrations
+        let migrations = [Migration {
+            version: 2,
+            upgrade: |_| {},
+            downgrade: |node| {
+                if let AdfBlockNode::MediaSingle { attrs, .. } = node {
+                    attrs.width = None;
+                    attrs.width_type = None;
+                }
+            },
+        }];
+        let mut adf = doc(2, width.clone());
+        migrate_with(&mut adf, 1, &migrations).unwrap();
+        assert_eq!(adf, doc(1, None));
+        migrate_with(&mut adf, 2, &migrations).unwrap();
+        assert_eq!(adf, doc(2, None));
+        assert_eq!(
+            migrate_with(&mut adf, 3, &migrations),
+            Err(MigrationError::UnsupportedVersion(3))
+        );
+    }
+}
@@ -1 +1,5 @@
 pub mod adf_types;
+pub mod fix;
+pub mod migrate;
+pub mod retain;
+pub mod stats;
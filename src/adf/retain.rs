@@ -0,0 +1,390 @@
+use std::collections::HashSet;
+
+use strum_macros::{Display, EnumIter};
+
+use super::adf_types::{AdfBlockNode, AdfNode, TableRow, TableRowEntry};
+
+/// The ADF node types, as named by their `type` field.
+///
+/// List items, table rows and cells, and media entries have no kind of their own: they are kept
+/// or dropped together with the node that contains them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, EnumIter)]
+#[strum(serialize_all = "camelCase")]
+pub enum NodeKind {
+    // Block nodes
+    Doc,
+    Blockquote,
+    BulletList,
+    CodeBlock,
+    Expand,
+    NestedExpand,
+    Paragraph,
+    Rule,
+    Heading,
+    Panel,
+    MediaGroup,
+    MediaSingle,
+    Table,
+    OrderedList,
+    BlockCard,
+    TaskList,
+    DecisionList,
+    // Inline nodes
+    HardBreak,
+    Text,
+    Date,
+    InlineCard,
+    Emoji,
+    Mention,
+    Status,
+    MediaInline,
+}
+
+impl NodeKind {
+    pub fn of_block(node: &AdfBlockNode) -> Option<Self> {
+        Some(match node {
+            AdfBlockNode::Doc { .. } => Self::Doc,
+            AdfBlockNode::Blockquote { .. } => Self::Blockquote,
+            AdfBlockNode::BulletList { .. } => Self::BulletList,
+            AdfBlockNode::CodeBlock { .. } => Self::CodeBlock,
+            AdfBlockNode::Expand { .. } => Self::Expand,
+            AdfBlockNode::NestedExpand { .. } => Self::NestedExpand,
+            AdfBlockNode::Paragraph { .. } => Self::Paragraph,
+            AdfBlockNode::Rule => Self::Rule,
+            AdfBlockNode::Heading { .. } => Self::Heading,
+            AdfBlockNode::Panel { .. } => Self::Panel,
+            AdfBlockNode::MediaGroup { .. } => Self::MediaGroup,
+            AdfBlockNode::MediaSingle { .. } => Self::MediaSingle,
+            AdfBlockNode::Table { .. } => Self::Table,
+            AdfBlockNode::OrderedList { .. } => Self::OrderedList,
+            AdfBlockNode::BlockCard { .. } => Self::BlockCard,
+            AdfBlockNode::TaskList { .. } => Self::TaskList,
+            AdfBlockNode::DecisionList { .. } => Self::DecisionList,
+            AdfBlockNode::Unknown => return None,
+        })
+    }
+
+    pub fn of_inline(node: &AdfNode) -> Option<Self> {
+        Some(match node {
+            AdfNode::HardBreak => Self::HardBreak,
+            AdfNode::Text { .. } => Self::Text,
+            AdfNode::Date { .. } => Self::Date,
+            AdfNode::InlineCard { .. } => Self::InlineCard,
+            AdfNode::Emoji { .. } => Self::Emoji,
+            AdfNode::Mention { .. } => Self::Mention,
+            AdfNode::Status { .. } => Self::Status,
+            AdfNode::MediaInline { .. } => Self::MediaInline,
+            AdfNode::Unknown => return None,
+        })
+    }
+}
+
+/// Reduces a document to the allowed node types.
+///
+/// Disallowed containers (blockquotes, panels, expands, lists and tables) are unwrapped so their
+/// allowed content survives. Headings, code blocks, tasks and decisions become paragraphs of their
+/// text, and inline nodes such as emoji, mentions and statuses become plain text. Anything without
+/// a text form, or whose replacement isn't allowed either, is dropped. Unknown nodes are always
+/// dropped, as are containers, list items and tables left empty by the rest; the emptied cells of
+/// a table that is kept get an empty paragraph. The root node itself is kept regardless of its
+/// kind.
+pub fn retain_node_types(adf: &mut AdfBlockNode, allowed: &HashSet<NodeKind>) {
+    retain_children(adf, allowed);
+}
+
+fn retain_blocks(nodes: Vec<AdfBlockNode>, allowed: &HashSet<NodeKind>) -> Vec<AdfBlockNode> {
+    let mut retained = vec![];
+    for mut node in nodes {
+        match NodeKind::of_block(&node) {
+            Some(kind) if allowed.contains(&kind) => {
+                let had_content = has_content(&node);
+                retain_children(&mut node, allowed);
+                // ADF requires content in these, so ones emptied here are dropped
+                if !had_content || has_content(&node) {
+                    retained.push(node);
+                }
+            }
+            Some(_) => retained.extend(replace_block(node, allowed)),
+            None => {}
+        }
+    }
+    retained
+}
+
+fn retain_children(node: &mut AdfBlockNode, allowed: &HashSet<NodeKind>) {
+    node.for_each_child_blocks_mut(|blocks| {
+        *blocks = retain_blocks(std::mem::take(blocks), allowed);
+    });
+    node.for_each_child_inline_mut(|inline| {
+        *inline = retain_inline(std::mem::take(inline), allowed);
+    });
+    if let AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } = node {
+        content.retain(|item| !item.content().is_empty());
+    }
+    // A kept table's emptied cells still need content, as in `validate_and_fix`
+    if let AdfBlockNode::Table { content, .. } = node {
+        content
+            .iter_mut()
+            .flat_map(|row| row.content_mut())
+            .map(TableRowEntry::content_mut)
+            .filter(|cell| cell.is_empty())
+            .for_each(|cell| cell.push(AdfBlockNode::Paragraph { content: None }));
+    }
+}
+
+/// Whether a node that must hold blocks has any. Other nodes always count as having content.
+fn has_content(node: &AdfBlockNode) -> bool {
+    match node {
+        AdfBlockNode::Blockquote { content }
+        | AdfBlockNode::Expand { content, .. }
+        | AdfBlockNode::NestedExpand { content, .. }
+        | AdfBlockNode::Panel { content, .. } => !content.is_empty(),
+        AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } => {
+            content.iter().any(|item| !item.content().is_empty())
+        }
+        AdfBlockNode::Table { content, .. } => {
+            content
+                .iter()
+                .flat_map(|row| row.content())
+                .any(|entry| match entry {
+                    TableRowEntry::TableHeader(header) => !header.content().is_empty(),
+                    TableRowEntry::TableCell(cell) => !cell.content().is_empty(),
+                })
+        }
+        _ => true,
+    }
+}
+
+/// Produces the allowed replacement for a disallowed block node.
+fn replace_block(node: AdfBlockNode, allowed: &HashSet<NodeKind>) -> Vec<AdfBlockNode> {
+    match node {
+        AdfBlockNode::Doc { content, .. }
+        | AdfBlockNode::Blockquote { content }
+        | AdfBlockNode::Expand { content, .. }
+        | AdfBlockNode::NestedExpand { content, .. }
+        | AdfBlockNode::Panel { content, .. } => retain_blocks(content, allowed),
+        AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } => content
+            .into_iter()
+            .flat_map(|item| retain_blocks(item.unwrap(), allowed))
+            .collect(),
+        AdfBlockNode::Table { content, .. } => content
+            .into_iter()
+            .flat_map(TableRow::unwrap)
+            .flat_map(|entry| match entry {
+                TableRowEntry::TableHeader(header) => retain_blocks(header.unwrap().0, allowed),
+                TableRowEntry::TableCell(cell) => retain_blocks(cell.unwrap().0, allowed),
+            })
+            .collect(),
+        AdfBlockNode::Heading { content, .. } | AdfBlockNode::CodeBlock { content, .. } => {
+            text_paragraph(content.unwrap_or_default(), allowed)
+        }
+        AdfBlockNode::TaskList { content, .. } => content
+            .into_iter()
+            .flat_map(|item| text_paragraph(item.unwrap().0, allowed))
+            .collect(),
+        AdfBlockNode::DecisionList { content, .. } => content
+            .into_iter()
+            .flat_map(|item| text_paragraph(item.unwrap().0, allowed))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn text_paragraph(inline: Vec<AdfNode>, allowed: &HashSet<NodeKind>) -> Vec<AdfBlockNode> {
+    let inline = retain_inline(inline, allowed);
+    if inline.is_empty() || !allowed.contains(&NodeKind::Paragraph) {
+        return vec![];
+    }
+    vec![AdfBlockNode::paragraph_from(inline)]
+}
+
+fn retain_inline(nodes: Vec<AdfNode>, allowed: &HashSet<NodeKind>) -> Vec<AdfNode> {
+    nodes
+        .into_iter()
+        .filter_map(|node| match NodeKind::of_inline(&node) {
+            Some(kind) if allowed.contains(&kind) => Some(node),
+            Some(_) => replace_inline(node, allowed),
+            None => None,
+        })
+        .collect()
+}
+
+/// Produces the plain text replacement for a disallowed inline node.
+fn replace_inline(node: AdfNode, allowed: &HashSet<NodeKind>) -> Option<AdfNode> {
+    if !allowed.contains(&NodeKind::Text) {
+        return None;
+    }
+    let text = match node {
+        AdfNode::HardBreak => " ".to_string(),
+        AdfNode::Emoji { attrs } => attrs.text.unwrap_or(attrs.short_name),
+        AdfNode::Mention { attrs } => attrs.text?,
+        AdfNode::Status { attrs } => attrs.text,
+        AdfNode::InlineCard { attrs } => attrs.url?,
+        AdfNode::MediaInline { attrs, .. } => attrs.alt?,
+        _ => return None,
+    };
+    Some(AdfNode::Text { text, marks: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adf::adf_types::{AdfMark, EmojiAttrs, HeadingAttrs, ListItem, PanelAttrs};
+
+    #[test]
+    fn test_retain_node_types() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let mut adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 1,
+                        ..Default::default()
+                    },
+                    content: Some(vec![text("Title")]),
+                },
+                AdfBlockNode::Panel {
+                    attrs: PanelAttrs {
+                        panel_type: "info".into(),
+                    },
+                    content: vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![
+                            AdfNode::Text {
+                                text: "Bold ".into(),
+                                marks: Some(vec![AdfMark::Strong]),
+                            },
+                            AdfNode::Emoji {
+                                attrs: EmojiAttrs {
+                                    id: None,
+                                    short_name: ":smile:".into(),
+                                    text: None,
+                                },
+                            },
+                        ]),
+                    }],
+                },
+                AdfBlockNode::Rule,
+                AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![text("Item")]),
+                    }])],
+                },
+            ],
+            version: 1,
+        };
+        let allowed = HashSet::from([NodeKind::Paragraph, NodeKind::BulletList, NodeKind::Text]);
+        retain_node_types(&mut adf, &allowed);
+
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![text("Title")]),
+                    },
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![
+                            AdfNode::Text {
+                                text: "Bold ".into(),
+                                marks: Some(vec![AdfMark::Strong]),
+                            },
+                            text(":smile:"),
+                        ]),
+                    },
+                    AdfBlockNode::BulletList {
+                        content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                            content: Some(vec![text("Item")]),
+                        }])],
+                    },
+                ],
+                version: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_retain_prunes_emptied_containers() {
+        let rule_item = || ListItem::new(vec![AdfBlockNode::Rule]);
+        let mut adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::BulletList {
+                    content: vec![
+                        rule_item(),
+                        ListItem::new(vec![AdfBlockNode::Paragraph {
+                            content: Some(vec![AdfNode::Text {
+                                text: "Kept".into(),
+                                marks: None,
+                            }]),
+                        }]),
+                    ],
+                },
+                AdfBlockNode::BulletList {
+                    content: vec![rule_item(), rule_item()],
+                },
+                AdfBlockNode::Blockquote {
+                    content: vec![AdfBlockNode::Rule],
+                },
+            ],
+            version: 1,
+        };
+        let allowed = HashSet::from([
+            NodeKind::Paragraph,
+            NodeKind::BulletList,
+            NodeKind::Blockquote,
+            NodeKind::Text,
+        ]);
+        retain_node_types(&mut adf, &allowed);
+
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Kept".into(),
+                            marks: None,
+                        }]),
+                    }])],
+                }],
+                version: 1,
+            }
+        );
+
+        // A table with some content left keeps it, with its emptied cells filled
+        let cell = |content| TableRowEntry::new_table_cell(content, None);
+        let kept = || {
+            cell(vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "Kept".into(),
+                    marks: None,
+                }]),
+            }])
+        };
+        let mut adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![TableRow::new(vec![cell(vec![AdfBlockNode::Rule]), kept()])],
+            }],
+            version: 1,
+        };
+        let allowed = HashSet::from([NodeKind::Paragraph, NodeKind::Table, NodeKind::Text]);
+        retain_node_types(&mut adf, &allowed);
+
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Table {
+                    attrs: None,
+                    content: vec![TableRow::new(vec![
+                        cell(vec![AdfBlockNode::Paragraph { content: None }]),
+                        kept(),
+                    ])],
+                }],
+                version: 1,
+            }
+        );
+    }
+}
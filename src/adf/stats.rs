@@ -0,0 +1,373 @@
+use super::adf_types::{AdfBlockNode, AdfNode, MediaNode, TableRowEntry};
+
+/// Counts of the human-visible text in a document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextStats {
+    pub words: usize,
+    pub chars: usize,
+}
+
+/// Counts the words in the visible text of a document, including code blocks.
+pub fn word_count(adf: &AdfBlockNode) -> usize {
+    text_stats(adf, true).words
+}
+
+/// Counts the characters in the visible text of a document, including code blocks.
+///
+/// Whitespace within a line of text is counted, but no characters are counted for the breaks
+/// between blocks or for hard breaks.
+pub fn char_count(adf: &AdfBlockNode) -> usize {
+    text_stats(adf, true).chars
+}
+
+/// Counts the words and characters in the visible text of a document.
+///
+/// Text comes from text nodes and the display text of mentions and statuses. An emoji counts as a
+/// single character. Dates, cards and media have no visible text of their own and are skipped, as
+/// is the content of code blocks unless `include_code` is set.
+pub fn text_stats(adf: &AdfBlockNode, include_code: bool) -> TextStats {
+    let mut stats = TextStats::default();
+    block_stats(adf, include_code, &mut stats);
+    stats
+}
+
+fn block_stats(node: &AdfBlockNode, include_code: bool, stats: &mut TextStats) {
+    match node {
+        AdfBlockNode::Doc { content, .. }
+        | AdfBlockNode::Blockquote { content }
+        | AdfBlockNode::Expand { content, .. }
+        | AdfBlockNode::NestedExpand { content, .. }
+        | AdfBlockNode::Panel { content, .. } => {
+            for child in content {
+                block_stats(child, include_code, stats);
+            }
+        }
+        AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } => {
+            for child in content.iter().flat_map(|item| item.content()) {
+                block_stats(child, include_code, stats);
+            }
+        }
+        AdfBlockNode::Table { content, .. } => {
+            for entry in content.iter().flat_map(|row| row.content()) {
+                let cell_content = match entry {
+                    TableRowEntry::TableHeader(header) => header.content(),
+                    TableRowEntry::TableCell(cell) => cell.content(),
+                };
+                for child in cell_content {
+                    block_stats(child, include_code, stats);
+                }
+            }
+        }
+        AdfBlockNode::Paragraph { content } | AdfBlockNode::Heading { content, .. } => {
+            inline_stats(content.iter().flatten(), stats);
+        }
+        AdfBlockNode::CodeBlock { content, .. } if include_code => {
+            inline_stats(content.iter().flatten(), stats);
+        }
+        AdfBlockNode::TaskList { content, .. } => {
+            for item in content {
+                inline_stats(item.content(), stats);
+            }
+        }
+        AdfBlockNode::DecisionList { content, .. } => {
+            for item in content {
+                inline_stats(item.content(), stats);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Adds the counts for the inline content of a single block.
+fn inline_stats<'a>(nodes: impl IntoIterator<Item = &'a AdfNode>, stats: &mut TextStats) {
+    let mut line = String::new();
+    for node in nodes {
+        let AdfNode::Text { text, .. } = node else {
+            // Any other node is a word boundary, marked with an uncounted line break
+            line.push('\n');
+            match node {
+                AdfNode::Mention { attrs } => {
+                    line.push_str(attrs.text.as_deref().unwrap_or_default())
+                }
+                AdfNode::Status { attrs } => line.push_str(&attrs.text),
+                // Any single character stands in for the emoji, whatever its text form
+                AdfNode::Emoji { .. } => line.push('\u{FFFC}'),
+                _ => {}
+            }
+            line.push('\n');
+            continue;
+        };
+        line.push_str(text);
+    }
+    stats.words += line.split_whitespace().count();
+    stats.chars += line.chars().filter(|c| !matches!(c, '\n' | '\r')).count();
+}
+
+/// Counts the nodes in a document the way Jira does when enforcing its per-document node limit.
+///
+/// Every node in the ADF tree counts once, including the document itself and structural nodes
+/// such as list items, table rows, table cells and the media inside media singles and groups.
+/// Marks are attributes of the node they apply to, so they don't count.
+pub fn node_count(adf: &AdfBlockNode) -> usize {
+    count_nodes(adf, false)
+}
+
+/// Counts the nodes in a document as [`node_count`] does, also counting each mark when
+/// `include_marks` is set.
+pub fn count_nodes(adf: &AdfBlockNode, include_marks: bool) -> usize {
+    1 + match adf {
+        AdfBlockNode::Doc { content, .. }
+        | AdfBlockNode::Blockquote { content }
+        | AdfBlockNode::Expand { content, .. }
+        | AdfBlockNode::NestedExpand { content, .. }
+        | AdfBlockNode::Panel { content, .. } => count_blocks(content, include_marks),
+        AdfBlockNode::BulletList { content } | AdfBlockNode::OrderedList { content, .. } => content
+            .iter()
+            .map(|item| 1 + count_blocks(item.content(), include_marks))
+            .sum(),
+        AdfBlockNode::Table { content, .. } => content
+            .iter()
+            .map(|row| {
+                1 + row
+                    .content()
+                    .iter()
+                    .map(|entry| {
+                        let cell_content = match entry {
+                            TableRowEntry::TableHeader(header) => header.content(),
+                            TableRowEntry::TableCell(cell) => cell.content(),
+                        };
+                        1 + count_blocks(cell_content, include_marks)
+                    })
+                    .sum::<usize>()
+            })
+            .sum(),
+        AdfBlockNode::Paragraph { content }
+        | AdfBlockNode::Heading { content, .. }
+        | AdfBlockNode::CodeBlock { content, .. } => {
+            count_inline(content.iter().flatten(), include_marks)
+        }
+        AdfBlockNode::MediaGroup { content } | AdfBlockNode::MediaSingle { content, .. } => content
+            .iter()
+            .map(|media| 1 + count_media_marks(media, include_marks))
+            .sum(),
+        AdfBlockNode::TaskList { content, .. } => content
+            .iter()
+            .map(|item| 1 + count_inline(item.content(), include_marks))
+            .sum(),
+        AdfBlockNode::DecisionList { content, .. } => content
+            .iter()
+            .map(|item| 1 + count_inline(item.content(), include_marks))
+            .sum(),
+        AdfBlockNode::Rule | AdfBlockNode::BlockCard { .. } | AdfBlockNode::Unknown => 0,
+    }
+}
+
+fn count_blocks(nodes: &[AdfBlockNode], include_marks: bool) -> usize {
+    nodes
+        .iter()
+        .map(|node| count_nodes(node, include_marks))
+        .sum()
+}
+
+fn count_inline<'a>(nodes: impl IntoIterator<Item = &'a AdfNode>, include_marks: bool) -> usize {
+    nodes
+        .into_iter()
+        .map(|node| {
+            let marks = match node {
+                AdfNode::Text { marks, .. } if include_marks => marks.as_ref().map_or(0, Vec::len),
+                AdfNode::MediaInline { marks, .. } if include_marks => {
+                    marks.as_ref().map_or(0, Vec::len)
+                }
+                _ => 0,
+            };
+            1 + marks
+        })
+        .sum()
+}
+
+fn count_media_marks(media: &MediaNode, include_marks: bool) -> usize {
+    if include_marks {
+        media.marks.as_ref().map_or(0, Vec::len)
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adf::adf_types::{
+        AdfMark, CodeBlockAttrs, EmojiAttrs, HeadingAttrs, ListItem, LocalId, MediaAttrs,
+        MediaDataType, MediaMark, MediaSingleAttrs, MediaType, MentionAttrs, PanelAttrs,
+        StatusAttrs, TableRow, TaskItem, TaskItemAttrs, TaskItemState,
+    };
+
+    fn text(text: &str) -> AdfNode {
+        AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        }
+    }
+
+    fn paragraph(content: Vec<AdfNode>) -> AdfBlockNode {
+        AdfBlockNode::Paragraph {
+            content: Some(content),
+        }
+    }
+
+    #[test]
+    fn test_text_stats() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Panel {
+                    attrs: PanelAttrs {
+                        panel_type: "info".into(),
+                    },
+                    content: vec![paragraph(vec![
+                        text("Hello "),
+                        AdfNode::Mention {
+                            attrs: MentionAttrs {
+                                id: "user-1".into(),
+                                text: Some("@Ada".into()),
+                                ..Default::default()
+                            },
+                        },
+                        AdfNode::HardBreak,
+                        AdfNode::Emoji {
+                            attrs: EmojiAttrs {
+                                id: None,
+                                short_name: ":wave:".into(),
+                                text: Some("👋".into()),
+                            },
+                        },
+                    ])],
+                },
+                AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![paragraph(vec![text("one two")])])],
+                },
+                AdfBlockNode::Table {
+                    attrs: None,
+                    content: vec![TableRow::new(vec![
+                        TableRowEntry::new_table_header(vec![paragraph(vec![text("Name")])], None),
+                        TableRowEntry::new_table_cell(vec![paragraph(vec![text("Value")])], None),
+                    ])],
+                },
+                AdfBlockNode::CodeBlock {
+                    attrs: Some(CodeBlockAttrs {
+                        language: Some("rust".into()),
+                    }),
+                    content: Some(vec![text("let x;\n")]),
+                },
+            ],
+            version: 1,
+        };
+
+        // "Hello @Ada" + emoji, "one two", "Name", "Value", "let x;"
+        assert_eq!(word_count(&adf), 9);
+        assert_eq!(char_count(&adf), 10 + 1 + 7 + 4 + 5 + 6);
+        assert_eq!(
+            text_stats(&adf, false),
+            TextStats {
+                words: 7,
+                chars: 10 + 1 + 7 + 4 + 5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_word_boundaries() {
+        let status = AdfNode::Status {
+            attrs: StatusAttrs {
+                text: "Done".into(),
+                color: "green".into(),
+                local_id: None,
+            },
+        };
+        let count = |content| word_count(&paragraph(content));
+        assert_eq!(count(vec![text("a"), AdfNode::HardBreak, text("b")]), 2);
+        assert_eq!(count(vec![text("Build"), status, text("now")]), 3);
+        assert_eq!(
+            char_count(&paragraph(vec![text("a"), AdfNode::HardBreak, text("b")])),
+            2
+        );
+    }
+
+    #[test]
+    fn test_node_count() {
+        assert_eq!(node_count(&AdfBlockNode::EMPTY_DOC), 1);
+
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 1,
+                        ..Default::default()
+                    },
+                    content: Some(vec![AdfNode::Text {
+                        text: "Title".into(),
+                        marks: Some(vec![AdfMark::Strong]),
+                    }]),
+                },
+                paragraph(vec![
+                    AdfNode::Text {
+                        text: "a".into(),
+                        marks: Some(vec![AdfMark::Strong, AdfMark::Em]),
+                    },
+                    text("b"),
+                ]),
+                AdfBlockNode::TaskList {
+                    attrs: LocalId::default(),
+                    content: vec![TaskItem::new(
+                        vec![text("Ship")],
+                        TaskItemAttrs {
+                            local_id: "1".into(),
+                            state: TaskItemState::Todo,
+                        },
+                    )],
+                },
+                AdfBlockNode::MediaSingle {
+                    attrs: MediaSingleAttrs::default(),
+                    content: vec![MediaNode {
+                        media_type: MediaType::Media,
+                        attrs: MediaAttrs {
+                            alt: None,
+                            height: None,
+                            width: None,
+                            id: "media-id".into(),
+                            collection: "collection".into(),
+                            type_: MediaDataType::File,
+                        },
+                        marks: Some(vec![MediaMark::Border {
+                            color: "#000000".into(),
+                            size: 1,
+                        }]),
+                    }],
+                },
+                AdfBlockNode::Rule,
+            ],
+            version: 1,
+        };
+        // doc, heading + text, paragraph + 2 texts, task list + item + text, media single +
+        // media, rule
+        assert_eq!(node_count(&adf), 12);
+        assert_eq!(count_nodes(&adf, true), 12 + 4);
+
+        // List items, rows and cells are nodes of their own
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![paragraph(vec![text("one")])])],
+                },
+                AdfBlockNode::Table {
+                    attrs: None,
+                    content: vec![TableRow::new(vec![
+                        TableRowEntry::new_table_header(vec![paragraph(vec![text("Name")])], None),
+                        TableRowEntry::new_table_cell(vec![paragraph(vec![text("Value")])], None),
+                    ])],
+                },
+            ],
+            version: 1,
+        };
+        assert_eq!(node_count(&adf), 1 + 4 + 8);
+    }
+}
@@ -2,101 +2,387 @@ use std::borrow::Cow;
 use std::fmt::Write;
 
 use chrono::{DateTime, Utc};
+use html_escape::encode_double_quoted_attribute;
 use urlencoding::encode;
 
 use crate::adf::adf_types::{
-    AdfBlockNode, AdfMark, AdfNode, DataSourceView, DecisionItem, ListItem, MediaDataType,
-    MediaMark, MediaNode, Subsup, TableRowEntry, TaskItem, TaskItemState,
+    AdfBlockNode, AdfMark, AdfNode, DataSourceView, DateAttrs, DecisionItem, EmojiAttrs, ListItem,
+    MediaDataType, MediaMark, MediaNode, MediaType, Subsup, TableAttrs, TableCellAttrs,
+    TableRowEntry, TaskItem, TaskItemState, clamp_heading_level, order_code_link,
 };
+use crate::emoji::shortname_to_unicode;
 use crate::html_builder::*;
+use crate::options::{ConvertOptions, HtmlProfile};
 
 pub fn adf_to_html(adf: Vec<AdfBlockNode>, buf: &str) -> String {
+    adf_to_html_with_options(adf, buf, &ConvertOptions::default())
+}
+
+pub fn adf_to_html_with_options(
+    adf: Vec<AdfBlockNode>,
+    buf: &str,
+    options: &ConvertOptions,
+) -> String {
     let mut buffer = Buffer::new();
     let node = buffer.body();
-    inner_block_adf_to_html(node, adf, buf);
+    inner_block_adf_to_html(node, adf, buf, options);
     buffer.finish()
 }
 
-fn media_adf_to_html(mut node: Node, media_entries: Vec<MediaNode>) {
+fn media_adf_to_html(mut node: Node, media_entries: Vec<MediaNode>, options: &ConvertOptions) {
     for media_node in media_entries {
-        let link = media_node
-            .marks
-            .map(|marks| {
-                marks.iter().find_map(|mark| match mark {
-                    MediaMark::Link(link) => Some(link.clone()),
-                    _ => None,
-                })
-            })
-            .flatten();
+        media_node_to_html(&mut node, media_node, options);
+    }
+}
 
-        match media_node.attrs.type_ {
-            MediaDataType::File => {
-                let mut attrs = vec![];
-                if let Some(link) = &link {
-                    attrs.push(format!("src=\"{}\"", link.href));
-                }
+/// Renders a media group as a `<div>` of `<figure>`s, captioning each image with its alt text.
+fn media_gallery_to_html(node: &mut Node, media_entries: Vec<MediaNode>, options: &ConvertOptions) {
+    let mut gallery = node.div().attr("class=\"adf-media-group\"");
+    for media_node in media_entries {
+        let caption = media_node.attrs.alt.clone();
+        let mut figure = gallery.figure();
+        media_node_to_html(&mut figure, media_node, options);
+        if let Some(caption) = caption {
+            write!(figure.figcaption(), "{}", caption).ok();
+        }
+    }
+}
+
+fn media_node_to_html(node: &mut Node, media_node: MediaNode, options: &ConvertOptions) {
+    let marks = media_node.marks.unwrap_or_default();
+    let link = marks.iter().find_map(|mark| match mark {
+        MediaMark::Link(link) if options.allows_url(&link.href) => Some(link.clone()),
+        _ => None,
+    });
+    let border = marks.iter().find_map(|mark| match mark {
+        MediaMark::Border { color, size } => Some((color, size)),
+        _ => None,
+    });
+
+    match media_node.attrs.type_ {
+        MediaDataType::File => {
+            let mut attrs = vec![];
+            if let Some(link) = &link {
                 attrs.push(format!(
-                    "data-collection=\"{}\"",
-                    media_node.attrs.collection
+                    "src=\"{}\"",
+                    encode_double_quoted_attribute(&link.href)
                 ));
-                attrs.push(format!("data-media-id=\"{}\"", media_node.attrs.id));
-                if let Some(alt) = &media_node.attrs.alt {
-                    attrs.push(format!("alt=\"{}\"", alt));
-                }
+            }
+            attrs.push(format!(
+                "data-collection=\"{}\"",
+                encode_double_quoted_attribute(&media_node.attrs.collection)
+            ));
+            attrs.push(format!(
+                "data-media-id=\"{}\"",
+                encode_double_quoted_attribute(&media_node.attrs.id)
+            ));
+            if let Some(alt) = &media_node.attrs.alt {
+                attrs.push(format!("alt=\"{}\"", encode_double_quoted_attribute(alt)));
+            }
+            if let Some((color, size)) = border {
+                attrs.push(format!(
+                    "data-border-color=\"{}\"",
+                    encode_double_quoted_attribute(color)
+                ));
+                attrs.push(format!("data-border-size=\"{size}\""));
+            }
 
-                let mut styles = vec![];
-                if let Some(width) = media_node.attrs.width {
-                    styles.push(format!("width: {}px", width));
-                }
-                if let Some(height) = media_node.attrs.height {
-                    styles.push(format!("height: {}px", height));
+            let mut styles = vec![];
+            if let Some(width) = media_node.attrs.width {
+                styles.push(format!("width: {}px", width));
+            }
+            if let Some(height) = media_node.attrs.height {
+                styles.push(format!("height: {}px", height));
+            }
+            styles.extend(border.map(|(color, size)| format!("border: {size}px solid {color}")));
+            if !styles.is_empty() {
+                attrs.push(format!(
+                    "style=\"{}\"",
+                    encode_double_quoted_attribute(&styles.join("; "))
+                ));
+            }
+            let attrs_str = attrs
+                .iter()
+                .map(|a| a.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            node.child(Cow::Borrowed("img")).attr(&attrs_str);
+        }
+        MediaDataType::Link => {
+            if let Some(link) = link {
+                let mut attrs = vec![
+                    format!("href=\"{}\"", encode_double_quoted_attribute(&link.href)),
+                    format!(
+                        "data-collection=\"{}\"",
+                        encode_double_quoted_attribute(&media_node.attrs.collection)
+                    ),
+                    format!(
+                        "data-media-id=\"{}\"",
+                        encode_double_quoted_attribute(&media_node.attrs.id)
+                    ),
+                ];
+                // Anchors have no `alt`, so it is kept in a data attribute
+                if let Some(alt) = &media_node.attrs.alt {
+                    attrs.push(format!(
+                        "data-alt=\"{}\"",
+                        encode_double_quoted_attribute(alt)
+                    ));
                 }
+                let styles: Vec<_> = [
+                    media_node
+                        .attrs
+                        .width
+                        .map(|width| format!("width: {width}px")),
+                    media_node
+                        .attrs
+                        .height
+                        .map(|height| format!("height: {height}px")),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
                 if !styles.is_empty() {
-                    attrs.push(format!("style=\"{}\"", &styles.join("; ")));
+                    attrs.push(format!("style=\"{}\"", styles.join("; ")));
                 }
-                let attrs_str = attrs
-                    .iter()
-                    .map(|a| a.as_str())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                node.child(Cow::Borrowed("img")).attr(&attrs_str);
-            }
-            MediaDataType::Link => {
-                if let Some(link) = link {
-                    let mut a = node.a().attr(&format!("href=\"{}\"", link.href));
-                    if let Some(title) = link.title.as_ref() {
-                        write!(a, "{}", title).ok();
-                    } else {
-                        write!(a, "{}", link.href).ok();
-                    }
+                let mut a = node.a().attr(&attrs.join(" "));
+                if let Some(title) = link.title.as_ref() {
+                    write!(a, "{}", title).ok();
                 } else {
-                    tracing::warn!("Media link is missing");
+                    write!(a, "{}", link.href).ok();
                 }
+            } else {
+                tracing::warn!("Media link is missing");
             }
         }
     }
 }
 
-fn table_cell_to_html(mut node: Node, adf: Vec<TableRowEntry>, buf: &str) {
+fn table_cell_to_html(
+    mut node: Node,
+    adf: Vec<TableRowEntry>,
+    buf: &str,
+    options: &ConvertOptions,
+) {
     for cell in adf {
         match cell {
             TableRowEntry::TableCell(adf_cell) => {
-                let (content, _) = adf_cell.unwrap();
-                let cell = node.td();
-                inner_block_adf_to_html(cell, content, buf);
+                let (content, attrs) = adf_cell.unwrap();
+                let cell = table_cell_attrs_to_html(node.td(), attrs, options);
+                inner_block_adf_to_html(cell, content, buf, options);
             }
             TableRowEntry::TableHeader(adf_header) => {
-                let (content, _) = adf_header.unwrap();
-                let header = node.th();
-                inner_block_adf_to_html(header, content, buf);
+                let (content, attrs) = adf_header.unwrap();
+                let header = table_cell_attrs_to_html(node.th(), attrs, options);
+                inner_block_adf_to_html(header, content, buf, options);
             }
         }
     }
 }
 
-fn task_item_to_html(mut node: Node, adf: Vec<TaskItem>, buf: &str) {
+/// Removes a trailing `— Author` paragraph from a blockquote, returning the author's inline content.
+/// A blockquote holding nothing but the attribution is left alone, so it isn't emptied.
+fn split_attribution(content: &mut Vec<AdfBlockNode>) -> Option<Vec<AdfNode>> {
+    if content.len() < 2 {
+        return None;
+    }
+    let Some(AdfBlockNode::Paragraph {
+        content: Some(inline),
+    }) = content.last()
+    else {
+        return None;
+    };
+    let Some(AdfNode::Text { text, .. }) = inline.first() else {
+        return None;
+    };
+    let prefix_len = ["—", "--"]
+        .iter()
+        .find(|prefix| text.starts_with(*prefix))?
+        .len();
+
+    let Some(AdfBlockNode::Paragraph {
+        content: Some(mut inline),
+    }) = content.pop()
+    else {
+        return None;
+    };
+    if let Some(AdfNode::Text { text, .. }) = inline.first_mut() {
+        *text = text[prefix_len..].trim_start().to_string();
+        if text.is_empty() {
+            inline.remove(0);
+        }
+    }
+    Some(inline)
+}
+
+fn table_attrs_to_html(mut node: Node<'_>, attrs: TableAttrs) -> Node<'_> {
+    if let Some(layout) = attrs.layout {
+        node = node.attr(&format!(
+            "data-layout=\"{}\"",
+            encode_double_quoted_attribute(&layout)
+        ));
+    }
+    if let Some(width) = attrs.width {
+        node = node.attr(&format!("data-width=\"{width}\""));
+    }
+    if let Some(enabled) = attrs.is_number_column_enabled {
+        node = node.attr(&format!("data-number-column=\"{enabled}\""));
+    }
+    if let Some(display_mode) = attrs.display_mode {
+        node = node.attr(&format!(
+            "data-display-mode=\"{}\"",
+            encode_double_quoted_attribute(&display_mode)
+        ));
+    }
+    node
+}
+
+fn table_cell_attrs_to_html<'a>(
+    node: Node<'a>,
+    attrs: Option<TableCellAttrs>,
+    options: &ConvertOptions,
+) -> Node<'a> {
+    let attrs = attrs.unwrap_or_default();
+    let mut node = node;
+    // A span of 1 is what a cell has anyway
+    if let Some(colspan) = attrs.colspan.filter(|&span| span != 1) {
+        node = node.attr(&format!("colspan=\"{colspan}\""));
+    }
+    if let Some(rowspan) = attrs.rowspan.filter(|&span| span != 1) {
+        node = node.attr(&format!("rowspan=\"{rowspan}\""));
+    }
+    if let Some(colwidth) = attrs.colwidth {
+        let widths: Vec<_> = colwidth.iter().map(u32::to_string).collect();
+        node = node.attr(&format!("data-colwidth=\"{}\"", widths.join(",")));
+    }
+
+    let mut styles = vec![];
+    if options.html_profile == HtmlProfile::Email {
+        styles.push(EMAIL_CELL_STYLE.to_string());
+    }
+    if let Some(background) = attrs.background {
+        styles.push(format!("background-color: {}", background));
+    }
+    if styles.is_empty() {
+        node
+    } else {
+        node.attr(&format!(
+            "style=\"{}\"",
+            encode_double_quoted_attribute(&styles.join("; "))
+        ))
+    }
+}
+
+const EMAIL_TABLE_STYLE: &str = "border-collapse: collapse";
+const EMAIL_CELL_STYLE: &str = "border: 1px solid #dfe1e6; padding: 4px 8px; text-align: left";
+const EMAIL_SECTION_STYLE: &str = "margin: 8px 0; padding: 8px 12px; border: 1px solid #dfe1e6";
+const EMAIL_CODE_STYLE: &str =
+    "background-color: #f4f5f7; padding: 8px; font-family: monospace; white-space: pre-wrap";
+const EMAIL_MENTION_STYLE: &str =
+    "color: #0052cc; background-color: #f4f5f7; border-radius: 3px; padding: 0 2px";
+const EMAIL_LIST_STYLE: &str = "list-style: none; padding-left: 0";
+
+/// The stored text of an emoji, else the glyph for a known shortname, else the shortname itself.
+fn emoji_text(attrs: &EmojiAttrs) -> Cow<'_, str> {
+    match (&attrs.text, shortname_to_unicode(&attrs.short_name)) {
+        (Some(text), _) => Cow::Borrowed(text),
+        (None, Some(glyph)) => Cow::Owned(glyph.to_string()),
+        (None, None) => Cow::Borrowed(&attrs.short_name),
+    }
+}
+
+/// The class and style that widen media laid out wider than the text column, as
+/// `(class, style)`. Other layouts keep the default width.
+fn media_single_width(layout: &str) -> Option<(&'static str, &'static str)> {
+    match layout {
+        "wide" => Some(("media-single media-wide", "width: 960px; max-width: 100%")),
+        "full-width" => Some(("media-single media-full-width", "width: 100%")),
+        _ => None,
+    }
+}
+
+/// Background colours for email panels, which have no stylesheet to pick them up by type.
+fn email_panel_color(panel_type: &str) -> &'static str {
+    match panel_type {
+        "info" => "#deebff",
+        "note" => "#eae6ff",
+        "warning" => "#fffae6",
+        "error" => "#ffebe6",
+        "success" => "#e3fcef",
+        _ => "#f4f5f7",
+    }
+}
+
+/// Maps ADF status colour names to the lozenge colours used in Atlassian products. Unknown
+/// colours are passed through as CSS.
+fn email_status_color(color: &str) -> &str {
+    match color {
+        "neutral" => "#dfe1e6",
+        "purple" => "#eae6ff",
+        "blue" => "#deebff",
+        "red" => "#ffebe6",
+        "yellow" => "#fff0b3",
+        "green" => "#e3fcef",
+        _ => color,
+    }
+}
+
+/// The moment a date node holds, from its timestamp in milliseconds. A malformed timestamp gives
+/// the Unix epoch.
+pub(crate) fn date_time(attrs: &DateAttrs) -> DateTime<Utc> {
+    let ts_ms = attrs.timestamp.parse::<i64>().unwrap_or_default();
+    DateTime::from_timestamp_millis(ts_ms).unwrap_or_default()
+}
+
+/// Display text for a mention that has no resolved name.
+pub(crate) fn mention_fallback_text(id: &str) -> String {
+    if id.is_empty() {
+        "@unknown".to_string()
+    } else {
+        format!("@{id}")
+    }
+}
+
+/// Renders an expand for email as an always-open section with its title in bold.
+fn email_section_to_html(
+    node: &mut Node,
+    title: Option<&str>,
+    content: Vec<AdfBlockNode>,
+    buf: &str,
+    options: &ConvertOptions,
+) {
+    let mut section = node
+        .div()
+        .attr(&format!("style=\"{}\"", EMAIL_SECTION_STYLE));
+    if let Some(title) = title {
+        write!(section.p().strong(), "{}", title).ok();
+    }
+    inner_block_adf_to_html(section, content, buf, options);
+}
+
+fn task_item_to_html(mut node: Node, adf: Vec<TaskItem>, buf: &str, options: &ConvertOptions) {
     for task_item in adf {
         let (content, attrs) = task_item.unwrap();
+        if options.html_profile == HtmlProfile::Email {
+            let mut task_item = node.li();
+            let marker = match attrs.state {
+                TaskItemState::Todo => "\u{2610} ",
+                TaskItemState::Done => "\u{2611} ",
+            };
+            write!(task_item, "{}", marker).ok();
+            inner_adf_to_html(task_item, content, buf, options);
+            continue;
+        }
+        if options.use_class_names {
+            let state = match attrs.state {
+                TaskItemState::Todo => "todo",
+                TaskItemState::Done => "done",
+            };
+            let task_item = node
+                .li()
+                .attr(&format!("class=\"task-item task-{}\"", state));
+            inner_adf_to_html(task_item, content, buf, options);
+            continue;
+        }
         let checked = if attrs.state == TaskItemState::Done {
             "checked"
         } else {
@@ -107,100 +393,196 @@ fn task_item_to_html(mut node: Node, adf: Vec<TaskItem>, buf: &str) {
         task_item
             .child(Cow::Borrowed("adf-task-item"))
             .attr(&format!("id=\"{}\" type=checkbox {}", local_id, checked));
-        inner_adf_to_html(task_item, content, buf);
+        inner_adf_to_html(task_item, content, buf, options);
     }
 }
 
-fn decision_item_to_html(mut node: Node, adf: Vec<DecisionItem>, buf: &str) {
+fn decision_item_to_html(
+    mut node: Node,
+    adf: Vec<DecisionItem>,
+    buf: &str,
+    options: &ConvertOptions,
+) {
     for decision_item in adf {
         let (content, attrs) = decision_item.unwrap();
+        if options.html_profile == HtmlProfile::Email {
+            let mut decision_item = node.li();
+            write!(decision_item, "\u{25C6} ").ok();
+            inner_adf_to_html(decision_item, content, buf, options);
+            continue;
+        }
+        if options.use_class_names {
+            let decision_item = node.li().attr("class=\"decision-item\"");
+            inner_adf_to_html(decision_item, content, buf, options);
+            continue;
+        }
         let mut li = node.li();
         let child = li
             .child(Cow::Borrowed("adf-decision-item"))
             .attr(&format!("id=\"{}\"", attrs.local_id));
-        inner_adf_to_html(child, content, buf);
+        inner_adf_to_html(child, content, buf, options);
     }
 }
 
-fn inner_list_to_html(mut node: Node, adf: Vec<ListItem>, buf: &str) {
+fn inner_list_to_html(mut node: Node, adf: Vec<ListItem>, buf: &str, options: &ConvertOptions) {
     for list_item in adf {
         let content = list_item.unwrap();
         let list_item = node.li();
-        inner_block_adf_to_html(list_item, content, buf);
+        inner_block_adf_to_html(list_item, content, buf, options);
     }
 }
 
-fn inner_adf_to_html(mut node: Node, adf: Vec<AdfNode>, buf: &str) {
+fn inner_adf_to_html(mut node: Node, adf: Vec<AdfNode>, buf: &str, options: &ConvertOptions) {
     for adf_node in adf {
         match adf_node {
             AdfNode::Date { attrs } => {
-                let ts_ms = attrs.timestamp.parse::<i64>().unwrap_or_default();
-                let dt: DateTime<Utc> = DateTime::from_timestamp_millis(ts_ms).unwrap_or_default();
+                let dt = date_time(&attrs);
                 let date_str = dt.to_rfc3339();
+                if options.html_profile == HtmlProfile::Email {
+                    write!(node, "{}", dt.format("%Y-%m-%d")).ok();
+                    continue;
+                }
                 let mut date = node.time().attr(&format!("datetime=\"{}\"", date_str));
                 write!(date, "{}", date_str).ok();
             }
+            AdfNode::Emoji { attrs } if options.html_profile != HtmlProfile::RoundTrip => {
+                match (&options.emoji_cdn_base, &attrs.id) {
+                    (Some(base), Some(id)) => {
+                        let src = format!("{}/{}.png", base.trim_end_matches('/'), encode(id));
+                        node.img().attr(&format!(
+                            "src=\"{}\" alt=\"{}\"",
+                            encode_double_quoted_attribute(&src),
+                            encode_double_quoted_attribute(&attrs.short_name)
+                        ));
+                    }
+                    _ => {
+                        write!(node, "{}", emoji_text(&attrs)).ok();
+                    }
+                }
+            }
             AdfNode::Emoji { attrs } => {
                 let mut emoji = node
                     .child(Cow::Borrowed("adf-emoji"))
                     .attr(&format!("aria-alt=\"{}\"", attrs.short_name));
-                if let Some(text) = &attrs.text {
-                    write!(emoji, "{}", text).ok();
-                } else {
-                    write!(emoji, "{}", attrs.short_name).ok();
+                if let Some(id) = &attrs.id {
+                    emoji = emoji.attr(&format!(
+                        "data-emoji-id=\"{}\"",
+                        encode_double_quoted_attribute(id)
+                    ));
                 }
+                write!(emoji, "{}", emoji_text(&attrs)).ok();
             }
             AdfNode::HardBreak => {
                 node.br();
             }
+            AdfNode::MediaInline { attrs, marks } => media_node_to_html(
+                &mut node,
+                MediaNode {
+                    media_type: MediaType::Media,
+                    attrs,
+                    marks,
+                },
+                options,
+            ),
             AdfNode::InlineCard { attrs } => {
-                if let Some(url) = &attrs.url {
+                if let Some(url) = &attrs.url
+                    && options.allows_url(url)
+                {
                     let mut a_tag = node
                         .a()
-                        .attr(&format!("href={}", url))
+                        .attr(&format!("href=\"{}\"", encode_double_quoted_attribute(url)))
                         .attr("data-inline-card=\"true\"")
                         .attr("target=\"_blank\"")
                         .attr("rel=\"noopener noreferrer\"");
-                    write!(a_tag, "External Link").ok();
+                    write!(a_tag, "{url}").ok();
                 }
             }
-            AdfNode::Mention { attrs } => {
+            AdfNode::Mention { attrs } if options.html_profile == HtmlProfile::Email => {
                 let mut mention = node
-                    .child(Cow::Borrowed("adf-mention"))
-                    .attr(&format!("data-mention-id=\"{}\"", attrs.id));
+                    .span()
+                    .attr(&format!("style=\"{}\"", EMAIL_MENTION_STYLE));
+                let text = attrs
+                    .text
+                    .unwrap_or_else(|| mention_fallback_text(&attrs.id));
+                write!(mention, "{}", text).ok();
+            }
+            AdfNode::Mention { attrs } => {
+                let mut mention = node.child(Cow::Borrowed("adf-mention")).attr(&format!(
+                    "data-mention-id=\"{}\"",
+                    encode_double_quoted_attribute(&attrs.id)
+                ));
 
-                if let Some(user_type) = &attrs.user_type {
-                    mention = mention.attr(&format!(
-                        "data-mention-user-type={}",
-                        serde_json::to_string(user_type).expect("value should be serializable")
-                    ));
-                }
-                if let Some(access_level) = &attrs.access_level {
-                    mention = mention.attr(&format!(
-                        "data-mention-access-level={}",
-                        serde_json::to_string(access_level).expect("value should be serializable")
-                    ));
+                // The enums serialize to quoted strings, which double as the attribute quotes
+                if let Some(Ok(user_type)) = attrs.user_type.as_ref().map(serde_json::to_string) {
+                    mention = mention.attr(&format!("data-mention-user-type={user_type}"));
                 }
-                if let Some(text) = &attrs.text {
-                    write!(mention, "{}", text).ok();
+                if let Some(Ok(access_level)) =
+                    attrs.access_level.as_ref().map(serde_json::to_string)
+                {
+                    mention = mention.attr(&format!("data-mention-access-level={access_level}"));
                 }
+                match &attrs.text {
+                    Some(text) => write!(mention, "{}", text).ok(),
+                    None => {
+                        // Marked so the placeholder isn't read back as the mention's text
+                        mention = mention.attr("data-mention-fallback=\"true\"");
+                        write!(mention, "{}", mention_fallback_text(&attrs.id)).ok()
+                    }
+                };
+            }
+            AdfNode::Status { attrs } if options.html_profile == HtmlProfile::Email => {
+                let style = format!(
+                    "background-color: {}; border-radius: 3px; padding: 0 4px; font-size: 11px; \
+                     font-weight: bold; text-transform: uppercase",
+                    email_status_color(&attrs.color)
+                );
+                let mut status = node.span().attr(&format!(
+                    "style=\"{}\"",
+                    encode_double_quoted_attribute(&style)
+                ));
+                write!(status, "{}", attrs.text).ok();
+            }
+            AdfNode::Status { attrs } if options.use_class_names => {
+                let mut status = node.span().attr(&format!(
+                    "class=\"status status-{}\"",
+                    encode_double_quoted_attribute(&attrs.color)
+                ));
+                write!(status, "{}", attrs.text).ok();
             }
             AdfNode::Status { attrs } => {
                 let mut status = node.child(Cow::Borrowed("adf-status")).attr(&format!(
                     "style=\"background-color: {}\" aria-label=\"{}\"",
-                    attrs.color,
-                    attrs.local_id.unwrap_or_default()
+                    encode_double_quoted_attribute(&attrs.color),
+                    encode_double_quoted_attribute(&attrs.text)
                 ));
+                if let Some(local_id) = &attrs.local_id {
+                    status = status.attr(&format!(
+                        "data-local-id=\"{}\"",
+                        encode_double_quoted_attribute(local_id)
+                    ));
+                }
                 write!(status, "{}", attrs.text).ok();
             }
             AdfNode::Text { text, marks } => {
-                fn apply_marks(node: &mut Node, marks: &[AdfMark], text: &str) -> std::fmt::Result {
+                fn apply_marks(
+                    node: &mut Node,
+                    marks: &[AdfMark],
+                    text: &str,
+                    options: &ConvertOptions,
+                ) -> std::fmt::Result {
                     if let Some((first, rest)) = marks.split_first() {
                         let mut wrapped_node = match first {
+                            // Links with disallowed schemes keep only their text
+                            AdfMark::Link(mark) if !options.allows_url(&mark.href) => {
+                                return apply_marks(node, rest, text, options);
+                            }
                             AdfMark::Strong => node.strong(),
                             AdfMark::Em => node.em(),
                             AdfMark::Code => node.code(),
-                            AdfMark::Link(mark) => node.a().attr(&format!("href={}", mark.href)),
+                            AdfMark::Link(mark) => node.a().attr(&format!(
+                                "href=\"{}\"",
+                                encode_double_quoted_attribute(&mark.href)
+                            )),
                             AdfMark::Strike => node.del(),
                             AdfMark::Subsup { type_ } => match type_ {
                                 Subsup::Sup => node.sup(),
@@ -209,19 +591,27 @@ fn inner_adf_to_html(mut node: Node, adf: Vec<AdfNode>, buf: &str) {
                             AdfMark::Underline => {
                                 node.span().attr("style=text-decoration:underline")
                             }
-                            AdfMark::TextColor { color } => {
-                                node.span().attr(&format!("style=\"color: {color}\""))
-                            }
-                            AdfMark::BackgroundColor { color } => node
-                                .span()
-                                .attr(&format!("style=\"background-color: {color}\"")),
+                            AdfMark::TextColor { .. } => node.span().attr(&format!(
+                                "style=\"color: {}\"",
+                                encode_double_quoted_attribute(
+                                    &first.canonical_color().unwrap_or_default()
+                                )
+                            )),
+                            AdfMark::BackgroundColor { .. } => node.span().attr(&format!(
+                                "style=\"background-color: {}\"",
+                                encode_double_quoted_attribute(
+                                    &first.canonical_color().unwrap_or_default()
+                                )
+                            )),
                         };
-                        apply_marks(&mut wrapped_node, rest, text)
+                        apply_marks(&mut wrapped_node, rest, text, options)
                     } else {
                         write!(node, "{}", text)
                     }
                 }
-                apply_marks(&mut node, &marks.unwrap_or_default(), &text).ok();
+                let mut marks = marks.unwrap_or_default();
+                order_code_link(&mut marks);
+                apply_marks(&mut node, &marks, &text, options).ok();
             }
             AdfNode::Unknown => {
                 tracing::warn!("Unknown node type in {}", buf);
@@ -230,163 +620,295 @@ fn inner_adf_to_html(mut node: Node, adf: Vec<AdfNode>, buf: &str) {
     }
 }
 
-fn inner_block_adf_to_html(mut node: Node, adf: Vec<AdfBlockNode>, buf: &str) {
+fn inner_block_adf_to_html(
+    mut node: Node,
+    adf: Vec<AdfBlockNode>,
+    buf: &str,
+    options: &ConvertOptions,
+) {
     for adf_node in adf {
         match adf_node {
-            AdfBlockNode::Blockquote { content } => {
-                let blockquote = node.blockquote();
-                inner_block_adf_to_html(blockquote, content, buf);
+            AdfBlockNode::Blockquote { mut content } => {
+                let attribution = if options.blockquote_attribution {
+                    split_attribution(&mut content)
+                } else {
+                    None
+                };
+                match attribution {
+                    Some(attribution) => {
+                        let mut figure = node.figure();
+                        inner_block_adf_to_html(figure.blockquote(), content, buf, options);
+                        inner_adf_to_html(figure.figcaption(), attribution, buf, options);
+                    }
+                    None => {
+                        inner_block_adf_to_html(node.blockquote(), content, buf, options);
+                    }
+                }
+            }
+            AdfBlockNode::BlockCard { attrs }
+                if options.html_profile == HtmlProfile::Email
+                    && !options.allows_url(&attrs.url) =>
+            {
+                write!(node.p(), "{}", attrs.url).ok();
+            }
+            AdfBlockNode::BlockCard { attrs } if options.html_profile == HtmlProfile::Email => {
+                let mut para = node.p();
+                let mut link = para.a().attr(&format!(
+                    "href=\"{}\"",
+                    encode_double_quoted_attribute(&attrs.url)
+                ));
+                write!(link, "{}", attrs.url).ok();
             }
             AdfBlockNode::BlockCard { attrs } => {
-                let mut block_card = node
-                    .child(Cow::Borrowed("adf-block-card"))
-                    .attr(&format!("data-block-card=\"{}\"", attrs.url));
-                let jql_attr = encode(&attrs.datasource.parameters.jql);
+                let mut block_card = node.child(Cow::Borrowed("adf-block-card")).attr(&format!(
+                    "data-block-card=\"{}\"",
+                    encode_double_quoted_attribute(&attrs.url)
+                ));
+                // A plain URL card has no data source to describe
+                let Some(source) = attrs.datasource else {
+                    continue;
+                };
+                let jql_attr = encode(&source.parameters.jql);
                 let mut datasource = block_card
                     .child(Cow::Borrowed("adf-block-card-data-source"))
-                    .attr(&format!("data-source=\"{}\"", attrs.datasource.id))
+                    .attr(&format!(
+                        "data-source=\"{}\"",
+                        encode_double_quoted_attribute(&source.id)
+                    ))
                     .attr(&format!(
                         "data-cloud-id=\"{}\"",
-                        attrs.datasource.parameters.cloud_id
+                        encode_double_quoted_attribute(&source.parameters.cloud_id)
                     ))
                     .attr(&format!("data-jql=\"{}\"", jql_attr));
-                for view in attrs.datasource.views {
+                for view in source.views {
                     match view {
                         DataSourceView::Table(properties) => {
                             let mut table = datasource
                                 .child(Cow::Borrowed("adf-block-card-view"))
-                                .attr(&format!("data-type=\"table\""));
+                                .attr("data-type=\"table\"");
                             for (i, column) in properties.columns.into_iter().enumerate() {
-                                table = table.attr(&format!("data-key-{}=\"{}\"", i, column.key));
+                                table = table.attr(&format!(
+                                    "data-key-{}=\"{}\"",
+                                    i,
+                                    encode_double_quoted_attribute(&column.key)
+                                ));
                             }
                         }
                     }
                 }
             }
             AdfBlockNode::BulletList { content } => {
-                inner_list_to_html(node.ul(), content, buf);
+                inner_list_to_html(node.ul(), content, buf, options);
             }
             AdfBlockNode::CodeBlock { attrs, content } => {
                 let mut pre = node.pre();
+                if options.html_profile == HtmlProfile::Email {
+                    pre = pre.attr(&format!("style=\"{}\"", EMAIL_CODE_STYLE));
+                }
                 let mut code_block = pre.code();
-                if let Some(attrs) = &attrs {
-                    if let Some(language) = &attrs.language {
-                        code_block = code_block.attr(&format!("class=\"language-{}\"", language));
-                    }
+                if options.html_profile != HtmlProfile::Email
+                    && let Some(attrs) = &attrs
+                    && let Some(language) = &attrs.language
+                {
+                    code_block = code_block.attr(&format!("class=\"language-{}\"", language));
                 }
                 if let Some(content) = content {
-                    inner_adf_to_html(code_block, content, buf);
+                    inner_adf_to_html(code_block, content, buf, options);
                 }
             }
             AdfBlockNode::Doc { content, .. } => {
-                let doc = node.div();
-                inner_block_adf_to_html(doc, content, buf);
+                let mut doc = node.div();
+                if let Some(dir) = options.default_dir {
+                    doc = doc.attr(&format!("dir=\"{dir}\""));
+                }
+                inner_block_adf_to_html(doc, content, buf, options);
+            }
+            AdfBlockNode::Expand { content, attrs }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                email_section_to_html(&mut node, attrs.title.as_deref(), content, buf, options);
             }
             AdfBlockNode::Expand { content, attrs } => {
                 let mut expand = node.details();
+                if attrs.expanded {
+                    expand = expand.attr("open");
+                }
                 if let Some(title) = attrs.title.as_ref() {
                     write!(expand.summary(), "{}", title).ok();
                 }
-                inner_block_adf_to_html(expand, content, buf);
+                inner_block_adf_to_html(expand, content, buf, options);
             }
             AdfBlockNode::Heading { attrs, content } => {
-                let heading = match attrs.level {
+                let heading = match clamp_heading_level(attrs.level) {
                     1 => node.h1(),
                     2 => node.h2(),
                     3 => node.h3(),
                     4 => node.h4(),
                     5 => node.h5(),
-                    6 => node.h6(),
                     _ => node.h6(),
                 };
                 if let Some(content) = content {
-                    inner_adf_to_html(heading, content, buf);
+                    inner_adf_to_html(heading, content, buf, options);
                 }
             }
-            AdfBlockNode::MediaGroup { content } => {
-                let media_group = node.child(Cow::Borrowed("adf-media-group"));
-                media_adf_to_html(media_group, content);
+            AdfBlockNode::MediaGroup { content } => match options.html_profile {
+                HtmlProfile::RoundTrip => {
+                    let media_group = node.child(Cow::Borrowed("adf-media-group"));
+                    media_adf_to_html(media_group, content, options);
+                }
+                HtmlProfile::Portable => media_gallery_to_html(&mut node, content, options),
+                HtmlProfile::Email => media_adf_to_html(node.div(), content, options),
+            },
+            AdfBlockNode::MediaSingle { content, .. }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                media_adf_to_html(node.div(), content, options);
             }
             AdfBlockNode::MediaSingle { content, attrs } => {
                 let mut media_single = node.child(Cow::Borrowed("adf-media-single"));
-                media_single = media_single.attr(&format!("data-layout=\"{}\"", attrs.layout));
-                media_adf_to_html(media_single, content);
+                media_single = media_single.attr(&format!(
+                    "data-layout=\"{}\"",
+                    encode_double_quoted_attribute(&attrs.layout)
+                ));
+                if let Some(width) = &attrs.width {
+                    media_single = media_single.attr(&format!("data-width=\"{width}\""));
+                }
+                if let Some(width_type) = &attrs.width_type {
+                    media_single = media_single.attr(&format!(
+                        "data-width-type=\"{}\"",
+                        encode_double_quoted_attribute(width_type)
+                    ));
+                }
+                if let Some((class, style)) = media_single_width(&attrs.layout) {
+                    media_single = if options.use_class_names {
+                        media_single.attr(&format!("class=\"{class}\""))
+                    } else {
+                        media_single.attr(&format!("style=\"{style}\""))
+                    };
+                }
+                media_adf_to_html(media_single, content, options);
+            }
+            AdfBlockNode::NestedExpand { content, attrs }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                email_section_to_html(&mut node, Some(&attrs.title), content, buf, options);
             }
             AdfBlockNode::NestedExpand { content, attrs } => {
                 let mut expand = node.details().attr("data-nested=\"true\"");
+                if attrs.expanded {
+                    expand = expand.attr("open");
+                }
                 write!(expand.summary(), "{}", attrs.title).ok();
-                inner_block_adf_to_html(expand, content, buf);
+                inner_block_adf_to_html(expand, content, buf, options);
+            }
+            AdfBlockNode::OrderedList { content, attrs } => {
+                let mut list = node.ol();
+                if let Some(order) = attrs.and_then(|attrs| attrs.order) {
+                    list = list.attr(&format!("start=\"{order}\""));
+                }
+                inner_list_to_html(list, content, buf, options);
             }
-            AdfBlockNode::OrderedList { content, .. } => {
-                inner_list_to_html(node.ol(), content, buf);
+            AdfBlockNode::Panel { content, attrs }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                let panel = node.div().attr(&format!(
+                    "style=\"margin: 8px 0; padding: 8px 12px; border-radius: 3px; \
+                     background-color: {}\"",
+                    email_panel_color(&attrs.panel_type)
+                ));
+                inner_block_adf_to_html(panel, content, buf, options);
+            }
+            AdfBlockNode::Panel { content, attrs } if options.use_class_names => {
+                let panel = node.div().attr(&format!(
+                    "class=\"panel panel-{}\"",
+                    encode_double_quoted_attribute(&attrs.panel_type)
+                ));
+                inner_block_adf_to_html(panel, content, buf, options);
             }
             AdfBlockNode::Panel { content, attrs } => {
-                let panel_type = attrs.panel_type.as_str();
-                let panel = node
-                    .figure()
-                    .attr(&format!("data-panel-type=\"{panel_type}\""));
-                inner_block_adf_to_html(panel, content, buf);
+                let panel = node.figure().attr(&format!(
+                    "data-panel-type=\"{}\"",
+                    encode_double_quoted_attribute(&attrs.panel_type)
+                ));
+                inner_block_adf_to_html(panel, content, buf, options);
             }
             AdfBlockNode::Paragraph { content } => {
                 let para = node.p();
                 if let Some(content) = content {
-                    inner_adf_to_html(para, content, buf);
+                    inner_adf_to_html(para, content, buf, options);
                 }
             }
             AdfBlockNode::Rule => {
                 node.hr();
             }
-            AdfBlockNode::Table { content, .. } => {
+            AdfBlockNode::Table { content, attrs } => {
                 let mut table = node.table();
-                tracing::debug!(?content, "Table content");
-
-                // Extract header rows and other rows
-                let mut header_rows = vec![];
-                let mut body_rows = vec![];
-
-                for row in content {
-                    if row
-                        .content()
-                        .iter()
-                        .any(|n| matches!(n, TableRowEntry::TableHeader { .. }))
-                    {
-                        header_rows.push(row.clone());
-                    } else {
-                        body_rows.push(row.clone());
-                    }
+                if options.html_profile == HtmlProfile::Email {
+                    table = table.attr(&format!("style=\"{}\"", EMAIL_TABLE_STYLE));
+                } else if let Some(attrs) = attrs {
+                    table = table_attrs_to_html(table, attrs);
                 }
+                tracing::trace!(?content, "Table content");
+
+                // Leading rows made only of headers form the head; everything after stays in
+                // order in the body, which is always present as Jira expects it
+                let header_count = content
+                    .iter()
+                    .take_while(|row| {
+                        !row.content().is_empty()
+                            && row
+                                .content()
+                                .iter()
+                                .all(|n| matches!(n, TableRowEntry::TableHeader { .. }))
+                    })
+                    .count();
+                let mut rows = content.into_iter();
 
-                if !header_rows.is_empty() {
-                    tracing::debug!(?header_rows, "Header rows");
+                if header_count > 0 {
                     let mut thead = table.thead();
-                    for row in header_rows {
-                        let content = row.unwrap();
-                        table_cell_to_html(thead.tr(), content, buf);
+                    for row in rows.by_ref().take(header_count) {
+                        table_cell_to_html(thead.tr(), row.unwrap(), buf, options);
                     }
                 }
 
-                if !body_rows.is_empty() {
-                    tracing::debug!(?body_rows, "Body rows");
-                    let mut tbody = table.tbody();
-                    for row in body_rows {
-                        let content = row.unwrap();
-                        table_cell_to_html(tbody.tr(), content, buf);
-                    }
+                let mut tbody = table.tbody();
+                for row in rows {
+                    table_cell_to_html(tbody.tr(), row.unwrap(), buf, options);
                 }
             }
+            AdfBlockNode::TaskList { content, .. }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                let task_list = node.ul().attr(&format!("style=\"{}\"", EMAIL_LIST_STYLE));
+                task_item_to_html(task_list, content, buf, options);
+            }
+            AdfBlockNode::TaskList { content, .. } if options.use_class_names => {
+                let task_list = node.ul().attr("class=\"task-list\"");
+                task_item_to_html(task_list, content, buf, options);
+            }
             AdfBlockNode::TaskList { content, attrs } => {
                 node.child(Cow::Borrowed("adf-local-data"))
-                    .attr(&format!("data-tag=\"task-list\""))
+                    .attr("data-tag=\"task-list\"")
                     .attr(&format!("id=\"{}\"", attrs.local_id));
                 let task_list = node.ul();
-                task_item_to_html(task_list, content, buf);
+                task_item_to_html(task_list, content, buf, options);
+            }
+            AdfBlockNode::DecisionList { content, .. }
+                if options.html_profile == HtmlProfile::Email =>
+            {
+                let decision_list = node.ul().attr(&format!("style=\"{}\"", EMAIL_LIST_STYLE));
+                decision_item_to_html(decision_list, content, buf, options);
+            }
+            AdfBlockNode::DecisionList { content, .. } if options.use_class_names => {
+                let decision_list = node.ul().attr("class=\"decision-list\"");
+                decision_item_to_html(decision_list, content, buf, options);
             }
             AdfBlockNode::DecisionList { content, attrs } => {
                 node.child(Cow::Borrowed("adf-local-data"))
-                    .attr(&format!("data-tag=\"decision-list\""))
+                    .attr("data-tag=\"decision-list\"")
                     .attr(&format!("id=\"{}\"", attrs.local_id));
                 let decision_list = node.ul();
-                decision_item_to_html(decision_list, content, buf);
+                decision_item_to_html(decision_list, content, buf, options);
             }
             AdfBlockNode::Unknown => {
                 tracing::warn!("Unknown block type encountered in {}", buf);
@@ -400,7 +922,8 @@ mod tests {
     use super::*;
     use crate::adf::adf_types::*;
     use crate::html_to_adf::html_to_adf;
-    use crate::markdown::{adf_to_markdown, markdown_to_adf};
+    use crate::markdown::{adf_to_markdown, html_to_markdown, markdown_to_adf};
+    use crate::options::TextDirection;
 
     fn roundtrip_adf_html_adf(adf: AdfBlockNode) {
         let html = adf_to_html(vec![adf.clone()], "");
@@ -410,7 +933,7 @@ mod tests {
     }
 
     fn roundtrip_adf_html_md_html_adf(adf: AdfBlockNode) {
-        let markdown = adf_to_markdown(&[adf.clone()], "");
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
         tracing::debug!(markdown = %markdown, "Roundtrip markdown");
         let back = markdown_to_adf(&markdown).unwrap();
         assert_eq!(
@@ -434,11 +957,43 @@ mod tests {
         roundtrip_adf_html_md_html_adf(adf);
     }
 
+    #[test]
+    fn test_html_special_characters_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: r#"if a < b && c > "d" then <div> &amp; 'e' "#.into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "<b> & &amp;".into(),
+                        marks: Some(vec![AdfMark::Code]),
+                    },
+                ]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Entities in attribute values after a `>` are left alone
+        assert_eq!(
+            html_to_markdown(
+                r#"<p><a href="https://example.com/?q=a>b&amp;c=d">Search</a></p>"#.into()
+            ),
+            r#"<a href="https://example.com/?q=a&gt;b&amp;c=d">Search</a>"#
+        );
+    }
+
     #[test]
     fn test_heading_roundtrip() {
         let adf = AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Heading {
-                attrs: HeadingAttrs { level: 2 },
+                attrs: HeadingAttrs {
+                    level: 2,
+                    ..Default::default()
+                },
                 content: Some(vec![AdfNode::Text {
                     text: "Heading level 2".into(),
                     marks: None,
@@ -471,21 +1026,29 @@ mod tests {
     }
 
     #[test]
-    fn test_media_group_roundtrip() {
+    fn test_list_in_panel_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let item = |text: &str| ListItem::new(vec![paragraph(text)]);
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::MediaGroup {
-                content: vec![MediaNode {
-                    media_type: MediaType::Media,
-                    attrs: MediaAttrs {
-                        alt: Some("Image description".into()),
-                        height: None,
-                        width: None,
-                        id: "media-id".into(),
-                        collection: "collection".into(),
-                        type_: MediaDataType::File,
+            content: vec![AdfBlockNode::Panel {
+                attrs: PanelAttrs {
+                    panel_type: "note".into(),
+                },
+                content: vec![
+                    paragraph("Steps:"),
+                    AdfBlockNode::BulletList {
+                        content: vec![item("Back up"), item("Upgrade")],
                     },
-                    marks: None,
-                }],
+                    AdfBlockNode::OrderedList {
+                        content: vec![item("Verify")],
+                        attrs: None,
+                    },
+                ],
             }],
             version: 1,
         };
@@ -494,84 +1057,415 @@ mod tests {
     }
 
     #[test]
-    fn test_media_single_roundtrip() {
+    fn test_link_autolink_markdown() {
+        let link = |text: &str, href: &str| AdfNode::Text {
+            text: text.into(),
+            marks: Some(vec![AdfMark::Link(LinkMark {
+                href: href.into(),
+                ..Default::default()
+            })]),
+        };
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::MediaSingle {
-                attrs: MediaSingleAttrs {
-                    layout: "center".into(),
-                },
-                content: vec![MediaNode {
-                    media_type: MediaType::Media,
-                    attrs: MediaAttrs {
-                        alt: None,
-                        height: Some(300),
-                        width: Some(300),
-                        id: "media-id".into(),
-                        collection: "collection".into(),
-                        type_: MediaDataType::File,
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    link("https://example.com/a", "https://example.com/a"),
+                    AdfNode::Text {
+                        text: " and ".into(),
+                        marks: None,
                     },
-                    marks: None,
-                }],
+                    link("the docs", "https://example.com/docs"),
+                ]),
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(
+            markdown.starts_with("<https://example.com/a> and <a href="),
+            "{markdown}"
+        );
         roundtrip_adf_html_md_html_adf(adf);
+
+        let adf = markdown_to_adf("<https://example.com/b>").unwrap();
+        let AdfBlockNode::Doc { content, .. } = adf else {
+            panic!("Expected a document");
+        };
+        assert_eq!(
+            content,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![link("https://example.com/b", "https://example.com/b")]),
+            }]
+        );
     }
 
     #[test]
-    fn test_task_list_roundtrip() {
-        let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::TaskList {
-                attrs: LocalId {
-                    local_id: "task-list-1".into(),
-                },
-                content: vec![
-                    TaskItem::new(
-                        vec![AdfNode::Text {
-                            text: "Task item".into(),
-                            marks: None,
-                        }],
-                        TaskItemAttrs {
-                            local_id: "item-1".into(),
-                            state: TaskItemState::Todo,
-                        },
-                    ),
-                    TaskItem::new(
-                        vec![AdfNode::Text {
-                            text: "Task item 2".into(),
-                            marks: None,
-                        }],
-                        TaskItemAttrs {
-                            local_id: "item-2".into(),
-                            state: TaskItemState::Done,
-                        },
-                    ),
-                ],
+    fn test_link_around_code() {
+        let link = AdfMark::Link(LinkMark {
+            href: "https://example.com".into(),
+            ..Default::default()
+        });
+        let doc = |marks: Vec<AdfMark>| AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "x".into(),
+                    marks: Some(marks),
+                }]),
             }],
             version: 1,
         };
+        let adf = doc(vec![link.clone(), AdfMark::Code]);
         roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
-    }
+        roundtrip_adf_html_md_html_adf(adf.clone());
+
+        // The link wraps the code whichever order the marks are in
+        let expected =
+            "<body><div><p><a href=\"https://example.com\"><code>x</code></a></p></div></body>";
+        assert_eq!(
+            adf_to_html(vec![doc(vec![AdfMark::Code, link])], ""),
+            expected
+        );
+        assert_eq!(
+            html_to_adf(r#"<p><code><a href="https://example.com">x</a></code></p>"#),
+            adf
+        );
+    }
 
     #[test]
-    fn test_status_emoji_roundtrip() {
+    fn test_success_panel_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Panel {
+                attrs: PanelAttrs {
+                    panel_type: "success".into(),
+                },
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Deployed".into(),
+                        marks: None,
+                    }]),
+                }],
+            }],
+            version: 1,
+        };
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains("data-panel-type=\"success\""));
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_panel_markdown_alerts() {
+        for (panel_type, alert) in [
+            ("info", "NOTE"),
+            ("warning", "WARNING"),
+            ("error", "CAUTION"),
+            ("success", "TIP"),
+        ] {
+            let adf = AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Panel {
+                    attrs: PanelAttrs {
+                        panel_type: panel_type.into(),
+                    },
+                    content: vec![
+                        AdfBlockNode::Paragraph {
+                            content: Some(vec![AdfNode::Text {
+                                text: "First".into(),
+                                marks: None,
+                            }]),
+                        },
+                        AdfBlockNode::BulletList {
+                            content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                                content: Some(vec![AdfNode::Text {
+                                    text: "Item".into(),
+                                    marks: None,
+                                }]),
+                            }])],
+                        },
+                    ],
+                }],
+                version: 1,
+            };
+            let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+            assert!(
+                markdown.starts_with(&format!("> [!{alert}]\n> First\n>\n")),
+                "Unexpected markdown for {panel_type}: {markdown}"
+            );
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+
+        // Panels without an alert equivalent stay raw HTML
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Panel {
+                attrs: PanelAttrs {
+                    panel_type: "note".into(),
+                },
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Aside".into(),
+                        marks: None,
+                    }]),
+                }],
+            }],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(markdown.contains("data-panel-type=\"note\""));
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Alert-looking text in a plain blockquote is left alone
+        let adf = markdown_to_adf("> [!IMPORTANT]\n> Quote").unwrap();
+        let AdfBlockNode::Doc { content, .. } = adf else {
+            panic!("Expected a document");
+        };
+        assert!(matches!(content[0], AdfBlockNode::Blockquote { .. }));
+    }
+
+    #[test]
+    fn test_media_group_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::MediaGroup {
+                content: vec![MediaNode {
+                    media_type: MediaType::Media,
+                    attrs: MediaAttrs {
+                        alt: Some("Image description".into()),
+                        height: None,
+                        width: None,
+                        id: "media-id".into(),
+                        collection: "collection".into(),
+                        type_: MediaDataType::File,
+                    },
+                    marks: None,
+                }],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_media_link_roundtrip() {
+        let media_link = |title: Option<&str>, alt: Option<&str>, width: Option<u32>| MediaNode {
+            media_type: MediaType::Media,
+            attrs: MediaAttrs {
+                alt: alt.map(Into::into),
+                height: None,
+                width,
+                id: "media-id".into(),
+                collection: "collection".into(),
+                type_: MediaDataType::Link,
+            },
+            marks: Some(vec![MediaMark::Link(LinkMark {
+                href: "https://example.com/report.pdf".into(),
+                title: title.map(Into::into),
+                ..Default::default()
+            })]),
+        };
         let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::MediaGroup {
+                    content: vec![
+                        media_link(Some("Quarterly report"), Some("Report"), None),
+                        media_link(None, None, None),
+                    ],
+                },
+                AdfBlockNode::MediaSingle {
+                    attrs: MediaSingleAttrs {
+                        layout: "center".into(),
+                        ..Default::default()
+                    },
+                    content: vec![media_link(Some("Report"), None, Some(400))],
+                },
+            ],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_inline_image_markdown_sizes() {
+        let image = |width: Option<u32>, height: Option<u32>| AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Paragraph {
-                content: Some(vec![
-                    AdfNode::Status {
+                content: Some(vec![AdfNode::MediaInline {
+                    attrs: MediaAttrs {
+                        alt: Some("Logo".into()),
+                        height,
+                        width,
+                        id: "".into(),
+                        collection: "".into(),
+                        type_: MediaDataType::File,
+                    },
+                    marks: Some(vec![MediaMark::Link(LinkMark {
+                        href: "https://example.com/logo.png".into(),
+                        ..Default::default()
+                    })]),
+                }]),
+            }],
+            version: 1,
+        };
+
+        // Markdown has no image size, so a sized image stays HTML
+        let sized = image(Some(120), Some(40));
+        let markdown = adf_to_markdown(std::slice::from_ref(&sized), "");
+        assert!(
+            markdown.contains(r#"style="width: 120px; height: 40px""#),
+            "{markdown}"
+        );
+        roundtrip_adf_html_md_html_adf(sized);
+
+        let plain = image(None, None);
+        let markdown = adf_to_markdown(std::slice::from_ref(&plain), "");
+        assert_eq!(markdown.trim(), "![Logo](https://example.com/logo.png)");
+        roundtrip_adf_html_md_html_adf(plain);
+    }
+
+    #[test]
+    fn test_media_group_portable_gallery() {
+        let adf = AdfBlockNode::MediaGroup {
+            content: vec![MediaNode {
+                media_type: MediaType::Media,
+                attrs: MediaAttrs {
+                    alt: Some("Image description".into()),
+                    height: None,
+                    width: None,
+                    id: "media-id".into(),
+                    collection: "collection".into(),
+                    type_: MediaDataType::File,
+                },
+                marks: None,
+            }],
+        };
+        let options = ConvertOptions {
+            html_profile: HtmlProfile::Portable,
+            ..Default::default()
+        };
+        let html = adf_to_html_with_options(vec![adf.clone()], "", &options);
+        assert!(!html.contains("adf-media-group>"), "{html}");
+        assert!(html.contains(r#"<div class="adf-media-group">"#), "{html}");
+        assert!(html.contains("<figure>"), "{html}");
+        assert!(
+            html.contains("<figcaption>Image description</figcaption>"),
+            "{html}"
+        );
+
+        let html = adf_to_html(vec![adf], "");
+        assert!(html.contains("<adf-media-group>"), "{html}");
+    }
+
+    #[test]
+    fn test_class_name_rendering() {
+        let adf = vec![
+            AdfBlockNode::Panel {
+                attrs: PanelAttrs {
+                    panel_type: "info".into(),
+                },
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Status {
                         attrs: StatusAttrs {
                             text: "Done".into(),
                             color: "green".into(),
-                            local_id: Some("status-1".into()),
+                            local_id: None,
                         },
+                    }]),
+                }],
+            },
+            AdfBlockNode::TaskList {
+                attrs: LocalId {
+                    local_id: "list-1".into(),
+                },
+                content: vec![TaskItem::new(
+                    vec![AdfNode::Text {
+                        text: "Ship it".into(),
+                        marks: None,
+                    }],
+                    TaskItemAttrs {
+                        local_id: "task-1".into(),
+                        state: TaskItemState::Done,
                     },
-                    AdfNode::Emoji {
-                        attrs: EmojiAttrs {
-                            text: Some("😄".into()),
-                            short_name: ":smile:".into(),
+                )],
+            },
+        ];
+        let options = ConvertOptions {
+            use_class_names: true,
+            ..Default::default()
+        };
+        let html = adf_to_html_with_options(adf, "", &options);
+        assert!(!html.contains("adf-"), "{html}");
+        assert!(html.contains(r#"<div class="panel panel-info">"#), "{html}");
+        assert!(
+            html.contains(r#"<span class="status status-green">Done</span>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<li class="task-item task-done">"#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_blockquote_attribution() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let adf = AdfBlockNode::Blockquote {
+            content: vec![
+                paragraph("Simplicity is prerequisite for reliability."),
+                paragraph("— Edsger Dijkstra"),
+            ],
+        };
+
+        let options = ConvertOptions {
+            blockquote_attribution: true,
+            ..Default::default()
+        };
+        let html = adf_to_html_with_options(vec![adf.clone()], "", &options);
+        assert!(
+            html.contains(
+                "<figure><blockquote><p>Simplicity is prerequisite for reliability.</p></blockquote><figcaption>Edsger Dijkstra</figcaption></figure>"
+            ),
+            "{html}"
+        );
+
+        // Off by default
+        let html = adf_to_html(vec![adf], "");
+        assert!(!html.contains("figcaption"), "{html}");
+        assert!(html.contains("<p>— Edsger Dijkstra</p>"), "{html}");
+
+        // A quote that is only an attribution stays a quote rather than an empty blockquote
+        let adf = AdfBlockNode::Blockquote {
+            content: vec![paragraph("— Edsger Dijkstra")],
+        };
+        let html = adf_to_html_with_options(vec![adf], "", &options);
+        assert!(!html.contains("figcaption"), "{html}");
+        assert!(
+            html.contains("<blockquote><p>— Edsger Dijkstra</p></blockquote>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_media_inline_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "See ".into(),
+                        marks: None,
+                    },
+                    AdfNode::MediaInline {
+                        attrs: MediaAttrs {
+                            collection: "uploads".into(),
+                            id: "media-1".into(),
+                            alt: Some("icon".into()),
+                            ..Default::default()
                         },
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: " here".into(),
+                        marks: None,
                     },
                 ]),
             }],
@@ -582,39 +1476,1097 @@ mod tests {
     }
 
     #[test]
-    fn test_expand_roundtrip() {
-        let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::Expand {
-                attrs: ExpandAttrs {
-                    title: Some("Expand Title".into()),
-                },
-                content: vec![AdfBlockNode::Paragraph {
+    fn test_media_single_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::MediaSingle {
+                attrs: MediaSingleAttrs {
+                    layout: "center".into(),
+                    width: serde_json::Number::from_f64(66.67),
+                    width_type: Some("percentage".into()),
+                },
+                content: vec![MediaNode {
+                    media_type: MediaType::Media,
+                    attrs: MediaAttrs {
+                        alt: None,
+                        height: Some(300),
+                        width: Some(300),
+                        id: "media-id".into(),
+                        collection: "collection".into(),
+                        type_: MediaDataType::File,
+                    },
+                    marks: None,
+                }],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_media_single_wide_layouts() {
+        let media_single = |layout: &str| AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::MediaSingle {
+                attrs: MediaSingleAttrs {
+                    layout: layout.into(),
+                    ..Default::default()
+                },
+                content: vec![MediaNode {
+                    media_type: MediaType::Media,
+                    attrs: MediaAttrs {
+                        alt: None,
+                        height: Some(300),
+                        width: Some(1200),
+                        id: "media-id".into(),
+                        collection: "collection".into(),
+                        type_: MediaDataType::File,
+                    },
+                    marks: None,
+                }],
+            }],
+            version: 1,
+        };
+        let class_names = ConvertOptions {
+            use_class_names: true,
+            ..Default::default()
+        };
+
+        let adf = media_single("full-width");
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"<adf-media-single data-layout="full-width" style="width: 100%">"#),
+            "{html}"
+        );
+        let html = adf_to_html_with_options(vec![adf.clone()], "", &class_names);
+        assert!(
+            html.contains(r#"data-layout="full-width" class="media-single media-full-width""#),
+            "{html}"
+        );
+        roundtrip_adf_html_adf(adf);
+
+        let adf = media_single("wide");
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"style="width: 960px; max-width: 100%""#),
+            "{html}"
+        );
+        roundtrip_adf_html_adf(adf);
+
+        // Other layouts keep the default width
+        let html = adf_to_html_with_options(vec![media_single("center")], "", &class_names);
+        assert!(
+            html.contains(r#"<adf-media-single data-layout="center">"#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_media_single_border_roundtrip() {
+        let border = MediaMark::Border {
+            color: "#091e4224".into(),
+            size: 2,
+        };
+        let link = MediaMark::Link(LinkMark {
+            href: "https://example.com/image.png".into(),
+            ..Default::default()
+        });
+        for marks in [vec![border.clone()], vec![link, border]] {
+            let adf = AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::MediaSingle {
+                    attrs: MediaSingleAttrs {
+                        layout: "center".into(),
+                        ..Default::default()
+                    },
+                    content: vec![MediaNode {
+                        media_type: MediaType::Media,
+                        attrs: MediaAttrs {
+                            alt: None,
+                            height: Some(300),
+                            width: Some(400),
+                            id: "media-id".into(),
+                            collection: "collection".into(),
+                            type_: MediaDataType::File,
+                        },
+                        marks: Some(marks),
+                    }],
+                }],
+                version: 1,
+            };
+            let html = adf_to_html(vec![adf.clone()], "");
+            assert!(
+                html.contains(r##"data-border-color="#091e4224" data-border-size="2""##),
+                "{html}"
+            );
+            assert!(
+                html.contains("style=\"width: 400px; height: 300px; border: 2px solid #091e4224\""),
+                "{html}"
+            );
+            roundtrip_adf_html_adf(adf.clone());
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+
+        // The data attributes win over a border style that disagrees with them
+        let adf = html_to_adf(
+            r#"<adf-media-single data-layout="center"><img data-collection="c" data-media-id="m" data-border-color="red" data-border-size="3" style="border: 1px solid blue"></adf-media-single>"#,
+        );
+        let AdfBlockNode::Doc { content, .. } = adf else {
+            panic!("Expected a document");
+        };
+        let [AdfBlockNode::MediaSingle { content, .. }] = content.as_slice() else {
+            panic!("Expected a media single: {content:?}");
+        };
+        assert_eq!(
+            content[0].marks,
+            Some(vec![MediaMark::Border {
+                color: "red".into(),
+                size: 3,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_media_single_in_panel_and_expand_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let media_single = AdfBlockNode::MediaSingle {
+            attrs: MediaSingleAttrs {
+                layout: "center".into(),
+                ..Default::default()
+            },
+            content: vec![MediaNode {
+                media_type: MediaType::Media,
+                attrs: MediaAttrs {
+                    alt: Some("Diagram".into()),
+                    height: Some(300),
+                    width: Some(400),
+                    id: "media-id".into(),
+                    collection: "collection".into(),
+                    type_: MediaDataType::File,
+                },
+                marks: None,
+            }],
+        };
+        let surrounded = vec![
+            paragraph("Before"),
+            media_single.clone(),
+            paragraph("After"),
+        ];
+        for content in [surrounded, vec![media_single]] {
+            let containers = [
+                AdfBlockNode::Panel {
+                    attrs: PanelAttrs {
+                        panel_type: "info".into(),
+                    },
+                    content: content.clone(),
+                },
+                AdfBlockNode::Expand {
+                    attrs: ExpandAttrs {
+                        title: Some("Screenshots".into()),
+                        ..Default::default()
+                    },
+                    content,
+                },
+            ];
+            for container in containers {
+                let adf = AdfBlockNode::Doc {
+                    content: vec![container],
+                    version: 1,
+                };
+                roundtrip_adf_html_adf(adf.clone());
+                roundtrip_adf_html_md_html_adf(adf);
+            }
+        }
+    }
+
+    #[test]
+    fn test_task_list_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::TaskList {
+                attrs: LocalId {
+                    local_id: "task-list-1".into(),
+                },
+                content: vec![
+                    TaskItem::new(
+                        vec![AdfNode::Text {
+                            text: "Task item".into(),
+                            marks: None,
+                        }],
+                        TaskItemAttrs {
+                            local_id: "item-1".into(),
+                            state: TaskItemState::Todo,
+                        },
+                    ),
+                    TaskItem::new(
+                        vec![AdfNode::Text {
+                            text: "Task item 2".into(),
+                            marks: None,
+                        }],
+                        TaskItemAttrs {
+                            local_id: "item-2".into(),
+                            state: TaskItemState::Done,
+                        },
+                    ),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_status_emoji_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Status {
+                        attrs: StatusAttrs {
+                            text: "Done".into(),
+                            color: "green".into(),
+                            local_id: Some("status-1".into()),
+                        },
+                    },
+                    AdfNode::Emoji {
+                        attrs: EmojiAttrs {
+                            id: None,
+                            text: Some("😄".into()),
+                            short_name: ":smile:".into(),
+                        },
+                    },
+                ]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_status_local_id_roundtrip() {
+        let status = |text: &str, local_id: Option<&str>| AdfNode::Status {
+            attrs: StatusAttrs {
+                text: text.into(),
+                color: "blue".into(),
+                local_id: local_id.map(Into::into),
+            },
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    status("In \"review\"", Some("a1b2c3")),
+                    AdfNode::Text {
+                        text: " then ".into(),
+                        marks: None,
+                    },
+                    status("Blocked", None),
+                ]),
+            }],
+            version: 1,
+        };
+
+        // The id is data; the accessible label is the text a reader sees
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"aria-label="In &quot;review&quot;" data-local-id="a1b2c3""#),
+            "{html}"
+        );
+        assert!(!html.contains(r#"aria-label="a1b2c3""#), "{html}");
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_emoji_cdn_rendering() {
+        let emoji = |id: Option<&str>, text: Option<&str>| AdfNode::Emoji {
+            attrs: EmojiAttrs {
+                short_name: ":party-parrot:".into(),
+                id: id.map(Into::into),
+                text: text.map(Into::into),
+            },
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    emoji(Some("atlassian-party_parrot"), None),
+                    emoji(None, Some("🦜")),
+                    emoji(None, None),
+                ]),
+            }],
+            version: 1,
+        };
+
+        let options = ConvertOptions {
+            html_profile: HtmlProfile::Portable,
+            emoji_cdn_base: Some("https://emoji.example.com/".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            adf_to_html_with_options(vec![adf.clone()], "", &options),
+            "<body><div><p><img src=\"https://emoji.example.com/atlassian-party_parrot.png\" \
+             alt=\":party-parrot:\" />🦜:party-parrot:</p></div></body>"
+        );
+
+        // Without a CDN the id is only carried along for the round trip
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains("data-emoji-id=\"atlassian-party_parrot\""));
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![emoji(Some("atlassian-party_parrot"), Some("🦜"))]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_emoji_shortname_glyph() {
+        let emoji = |short_name: &str, text: Option<&str>| AdfNode::Emoji {
+            attrs: EmojiAttrs {
+                short_name: short_name.into(),
+                id: None,
+                text: text.map(Into::into),
+            },
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    emoji(":tada:", None),
+                    emoji(":bar_chart:", Some("chart")),
+                    emoji(":party-parrot:", None),
+                ]),
+            }],
+            version: 1,
+        };
+
+        let options = ConvertOptions {
+            html_profile: HtmlProfile::Portable,
+            ..Default::default()
+        };
+        assert_eq!(
+            adf_to_html_with_options(vec![adf.clone()], "", &options),
+            "<body><div><p>🎉chart:party-parrot:</p></div></body>"
+        );
+        let html = adf_to_html(vec![adf], "");
+        assert!(html.contains("aria-alt=\":tada:\">🎉</adf-emoji>"));
+    }
+
+    #[test]
+    fn test_status_markdown_syntax() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Status {
+                    attrs: StatusAttrs {
+                        text: "Done".into(),
+                        color: "green".into(),
+                        local_id: Some("status-1".into()),
+                    },
+                }]),
+            }],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(
+            markdown.contains("{status:Done|green|status-1}"),
+            "{markdown}"
+        );
+        assert!(!markdown.contains("<adf-status"), "{markdown}");
+        roundtrip_adf_html_md_html_adf(adf);
+
+        let parsed =
+            markdown_to_adf("Build is {status:Failed|red} and `{status:Kept|blue}`").unwrap();
+        assert_eq!(
+            parsed,
+            AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![
+                        AdfNode::Text {
+                            text: "Build is ".into(),
+                            marks: None,
+                        },
+                        AdfNode::Status {
+                            attrs: StatusAttrs {
+                                text: "Failed".into(),
+                                color: "red".into(),
+                                local_id: None,
+                            },
+                        },
+                        AdfNode::Text {
+                            text: " and ".into(),
+                            marks: None,
+                        },
+                        AdfNode::Text {
+                            text: "{status:Kept|blue}".into(),
+                            marks: Some(vec![AdfMark::Code]),
+                        },
+                    ]),
+                }],
+                version: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_status_markdown_escaping() {
+        let paragraph = |content: Vec<AdfNode>| AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(content),
+            }],
+            version: 1,
+        };
+        let status = |text: &str, color: &str| AdfNode::Status {
+            attrs: StatusAttrs {
+                text: text.into(),
+                color: color.into(),
+                local_id: None,
+            },
+        };
+
+        // Literal syntax in text stays text
+        let adf = paragraph(vec![AdfNode::Text {
+            text: "Type {status:Done|green} for a status".into(),
+            marks: None,
+        }]);
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Statuses the syntax can't hold stay as HTML
+        for (text, color) in [
+            ("In | out", "blue"),
+            ("{x}", "red"),
+            ("<b>bold</b> & \"quoted\"", "green"),
+        ] {
+            roundtrip_adf_html_md_html_adf(paragraph(vec![status(text, color)]));
+        }
+
+        // Values are escaped when the syntax becomes HTML
+        assert_eq!(
+            markdown_to_adf(r#"{status:a & "b"|red" onclick="x}"#).unwrap(),
+            paragraph(vec![status(r#"a & "b""#, r#"red" onclick="x"#)])
+        );
+
+        // A `>` in an attribute value doesn't end the tag early
+        assert_eq!(
+            markdown_to_adf(
+                r#"<a href="https://example.com" title="a><code>">Build</a> {status:Done|green}"#
+            )
+            .unwrap(),
+            markdown_to_adf("[Build](https://example.com) {status:Done|green}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_expand_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Expand {
+                attrs: ExpandAttrs {
+                    title: Some("Expand Title".into()),
+                    ..Default::default()
+                },
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Expandable content".into(),
+                        marks: None,
+                    }]),
+                }],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_nested_expand_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::NestedExpand {
+                attrs: NestedAttrs {
+                    title: "Nested Title".into(),
+                    ..Default::default()
+                },
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Nested content".into(),
+                        marks: None,
+                    }]),
+                }],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_expanded_expand_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Expand {
+                attrs: ExpandAttrs {
+                    title: Some("Details".into()),
+                    expanded: true,
+                },
+                content: vec![AdfBlockNode::NestedExpand {
+                    attrs: NestedAttrs {
+                        title: "More".into(),
+                        expanded: true,
+                    },
+                    content: vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Shown".into(),
+                            marks: None,
+                        }]),
+                    }],
+                }],
+            }],
+            version: 1,
+        };
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains("<details open>"), "{html}");
+        assert_eq!(
+            serde_json::to_value(&adf).unwrap()["content"][0]["attrs"],
+            serde_json::json!({ "title": "Details", "__expanded": true })
+        );
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_date_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Date {
+                    attrs: DateAttrs {
+                        timestamp: "1700000000".into(),
+                    },
+                }]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_mention_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Mention {
+                    attrs: MentionAttrs {
+                        id: "user-1".into(),
+                        text: Some("Mentioned User".into()),
+                        access_level: Some(AccessLevel::Site),
+                        user_type: Some(UserType::App),
+                    },
+                }]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_mention_in_heading_roundtrip() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 2,
+                        ..Default::default()
+                    },
+                    content: Some(vec![
+                        text("Owner: "),
+                        AdfNode::Mention {
+                            attrs: MentionAttrs {
+                                id: "user-1".into(),
+                                text: Some("@Ada".into()),
+                                access_level: None,
+                                user_type: None,
+                            },
+                        },
+                        text(" and "),
+                        AdfNode::Mention {
+                            attrs: MentionAttrs {
+                                id: "user-2".into(),
+                                text: Some("@Grace".into()),
+                                access_level: None,
+                                user_type: None,
+                            },
+                        },
+                    ]),
+                },
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![text("Body")]),
+                },
+            ],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_mention_text_is_kept_verbatim() {
+        // Text is written exactly as stored, so neither converter adds or strips an `@`
+        for text in ["Jane Doe", "@Jane Doe"] {
+            let adf = AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Mention {
+                        attrs: MentionAttrs {
+                            id: "user-1".into(),
+                            text: Some(text.into()),
+                            ..Default::default()
+                        },
+                    }]),
+                }],
+                version: 1,
+            };
+            roundtrip_adf_html_adf(adf.clone());
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+    }
+
+    #[test]
+    fn test_mention_without_text() {
+        for (id, fallback) in [("user-1", "@user-1"), ("", "@unknown")] {
+            let adf = AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Mention {
+                        attrs: MentionAttrs {
+                            id: id.into(),
+                            ..Default::default()
+                        },
+                    }]),
+                }],
+                version: 1,
+            };
+            let html = adf_to_html(vec![adf.clone()], "");
+            assert!(
+                html.contains(&format!(">{fallback}</adf-mention>")),
+                "{html}"
+            );
+            let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+            assert!(markdown.contains(fallback), "{markdown}");
+            roundtrip_adf_html_adf(adf.clone());
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+    }
+
+    #[test]
+    fn test_adversarial_mention_attrs() {
+        for id in [
+            r#"user" onclick="alert(1)"#,
+            "<adf-mention>",
+            "a&amp;b's",
+            "",
+        ] {
+            let adf = AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Mention {
+                        attrs: MentionAttrs {
+                            id: id.into(),
+                            text: Some(r#"<b>"@Name"</b> & co"#.into()),
+                            access_level: Some(AccessLevel::None),
+                            user_type: Some(UserType::Special),
+                        },
+                    }]),
+                }],
+                version: 1,
+            };
+            let html = adf_to_html(vec![adf.clone()], "");
+            assert!(!html.contains("onclick=\""), "{html}");
+            roundtrip_adf_html_adf(adf.clone());
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+
+        // Unknown enum values from the wire fall back rather than failing
+        let mention: AdfNode = serde_json::from_value(serde_json::json!({
+            "type": "mention",
+            "attrs": { "id": "x", "accessLevel": "<script>", "userType": "\"" },
+        }))
+        .unwrap();
+        let html = adf_to_html(
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![mention]),
+            }],
+            "",
+        );
+        assert!(
+            html.contains(r#"data-mention-access-level="NONE""#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_inline_card_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::InlineCard {
+                    attrs: InlineCardAttrs {
+                        url: Some("https://example.com".into()),
+                    },
+                }]),
+            }],
+            version: 1,
+        };
+        // The URL is the link text, so the anchor is the same every time it is rendered
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"rel="noopener noreferrer">https://example.com</a>"#),
+            "{html}"
+        );
+        assert_eq!(html_to_adf(&html), adf);
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_rule_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Rule],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_rule_markdown() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 2,
+                        ..Default::default()
+                    },
+                    content: Some(vec![AdfNode::Text {
+                        text: "Title".into(),
+                        marks: None,
+                    }]),
+                },
+                AdfBlockNode::Rule,
+                paragraph("Above"),
+                AdfBlockNode::Rule,
+                paragraph("Below"),
+                AdfBlockNode::Panel {
+                    attrs: PanelAttrs {
+                        panel_type: "info".into(),
+                    },
+                    content: vec![paragraph("Inside"), AdfBlockNode::Rule],
+                },
+            ],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert_eq!(
+            markdown,
+            "## Title\n\n---\n\nAbove\n\n---\n\nBelow\n\n> [!NOTE]\n> Inside\n>\n> ---"
+        );
+        roundtrip_adf_html_md_html_adf(adf);
+
+        for rule in ["---", "***", "___", "- - -", "* * *", "_ _ _"] {
+            let adf = markdown_to_adf(&format!("Above\n\n{rule}\n\nBelow")).unwrap();
+            assert_eq!(
+                adf,
+                AdfBlockNode::Doc {
+                    content: vec![paragraph("Above"), AdfBlockNode::Rule, paragraph("Below")],
+                    version: 1,
+                },
+                "Thematic break {rule:?} wasn't parsed as a rule"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bullet_list_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![
+                    ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Bullet 1".into(),
+                            marks: None,
+                        }]),
+                    }]),
+                    ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Bullet 2".into(),
+                            marks: None,
+                        }]),
+                    }]),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_code_block_in_list_item_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![
+                    ListItem::new(vec![
+                        AdfBlockNode::Paragraph {
+                            content: Some(vec![AdfNode::Text {
+                                text: "Run this:".into(),
+                                marks: None,
+                            }]),
+                        },
+                        AdfBlockNode::CodeBlock {
+                            attrs: None,
+                            content: Some(vec![AdfNode::Text {
+                                text: "cargo test\n".into(),
+                                marks: None,
+                            }]),
+                        },
+                    ]),
+                    ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Done".into(),
+                            marks: None,
+                        }]),
+                    }]),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_list_items_with_marks_roundtrip() {
+        let item = || {
+            ListItem::new(vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Bold".into(),
+                        marks: Some(vec![AdfMark::Strong]),
+                    },
+                    AdfNode::Text {
+                        text: " and ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "linked".into(),
+                        marks: Some(vec![
+                            AdfMark::Link(LinkMark {
+                                href: "https://example.com".into(),
+                                ..Default::default()
+                            }),
+                            AdfMark::Strong,
+                        ]),
+                    },
+                ]),
+            }])
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::BulletList {
+                    content: vec![item(), item()],
+                },
+                AdfBlockNode::OrderedList {
+                    content: vec![item()],
+                    attrs: None,
+                },
+            ],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf.clone());
+
+        // Item text that isn't wrapped in a paragraph keeps its marks too
+        let html = r#"<ul><li><strong>Bold</strong> and <a href="https://example.com"><strong>linked</strong></a></li><li><strong>Bold</strong> and <a href="https://example.com"><strong>linked</strong></a></li></ul><ol><li><strong>Bold</strong> and <a href="https://example.com"><strong>linked</strong></a></li></ol>"#;
+        assert_eq!(html_to_adf(html), adf);
+    }
+
+    #[test]
+    fn test_nested_bullet_list_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![
+                    ListItem::new(vec![
+                        paragraph("a"),
+                        AdfBlockNode::BulletList {
+                            content: vec![
+                                ListItem::new(vec![paragraph("b")]),
+                                ListItem::new(vec![paragraph("c")]),
+                            ],
+                        },
+                    ]),
+                    ListItem::new(vec![paragraph("d")]),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf.clone());
+        assert_eq!(markdown_to_adf("- a\n  - b\n  - c\n- d").unwrap(), adf);
+    }
+
+    #[test]
+    fn test_ordered_list_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::OrderedList {
+                content: vec![
+                    ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Ordered 1".into(),
+                            marks: None,
+                        }]),
+                    }]),
+                    ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Ordered 2".into(),
+                            marks: None,
+                        }]),
+                    }]),
+                ],
+                attrs: None,
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_ordered_list_start_roundtrip() {
+        let list = |attrs| AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::OrderedList {
+                content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Fifth".into(),
+                        marks: None,
+                    }]),
+                }])],
+                attrs,
+            }],
+            version: 1,
+        };
+
+        let adf = list(Some(OrderedListAttrs { order: Some(5) }));
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains(r#"<ol start="5">"#), "{html}");
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Without a start number the list stays a plain `<ol>`
+        let html = adf_to_html(vec![list(None)], "");
+        assert!(html.contains("<ol>"), "{html}");
+        assert_eq!(html_to_adf(&html), list(None));
+    }
+
+    #[test]
+    fn test_blockquote_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Blockquote {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Blockquoted text".into(),
+                        marks: None,
+                    }]),
+                }],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_codeblock_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::CodeBlock {
+                attrs: None,
+                content: Some(vec![AdfNode::Text {
+                    text: "let x = 42;\n".into(),
+                    marks: None,
+                }]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_codeblock_language_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::CodeBlock {
+                attrs: Some(CodeBlockAttrs {
+                    language: Some("rust".into()),
+                }),
+                content: Some(vec![AdfNode::Text {
+                    text: "let x = 42;\n".into(),
+                    marks: None,
+                }]),
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_markdown_fenced_code_language() {
+        let adf = markdown_to_adf("```python\nprint(1)\n```").unwrap();
+        assert_eq!(
+            adf,
+            AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::CodeBlock {
+                    attrs: Some(CodeBlockAttrs {
+                        language: Some("python".into()),
+                    }),
                     content: Some(vec![AdfNode::Text {
-                        text: "Expandable content".into(),
+                        text: "print(1)\n".into(),
                         marks: None,
                     }]),
                 }],
-            }],
-            version: 1,
-        };
-        roundtrip_adf_html_adf(adf.clone());
+                version: 1,
+            }
+        );
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(markdown.starts_with("```python\n"), "{markdown}");
         roundtrip_adf_html_md_html_adf(adf);
     }
 
     #[test]
-    fn test_nested_expand_roundtrip() {
+    fn test_empty_codeblock_roundtrip() {
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::NestedExpand {
-                attrs: NestedAttrs {
-                    title: "Nested Title".into(),
+            content: vec![
+                AdfBlockNode::CodeBlock {
+                    attrs: None,
+                    content: None,
                 },
-                content: vec![AdfBlockNode::Paragraph {
-                    content: Some(vec![AdfNode::Text {
-                        text: "Nested content".into(),
-                        marks: None,
-                    }]),
-                }],
-            }],
+                AdfBlockNode::CodeBlock {
+                    attrs: Some(CodeBlockAttrs {
+                        language: Some("rust".into()),
+                    }),
+                    content: None,
+                },
+            ],
             version: 1,
         };
         roundtrip_adf_html_adf(adf.clone());
@@ -622,49 +2574,162 @@ mod tests {
     }
 
     #[test]
-    fn test_date_roundtrip() {
-        let adf = AdfBlockNode::Doc {
+    fn test_palette_text_color_renders_as_hex() {
+        let colored = |color: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: "Colored".into(),
+                marks: Some(vec![AdfMark::TextColor {
+                    color: color.into(),
+                }]),
+            }]),
+        };
+        let html = adf_to_html(
+            vec![
+                colored("bold_blue"),
+                colored("#0747a6"),
+                colored("rebeccapurple"),
+            ],
+            "",
+        );
+        assert_eq!(
+            html.matches(r#"style="color: #0747a6""#).count(),
+            2,
+            "{html}"
+        );
+        assert!(html.contains(r#"style="color: rebeccapurple""#), "{html}");
+    }
+
+    #[test]
+    fn test_color_attributes_are_encoded() {
+        let color = r#"red" onmouseover="alert(1)"#;
+        let html = adf_to_html(
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "Colored".into(),
+                    marks: Some(vec![
+                        AdfMark::TextColor {
+                            color: color.into(),
+                        },
+                        AdfMark::BackgroundColor {
+                            color: color.into(),
+                        },
+                    ]),
+                }]),
+            }],
+            "",
+        );
+        assert!(!html.contains(r#"" onmouseover"#), "{html}");
+        assert!(html.contains("color: red&quot; onmouseover"), "{html}");
+
+        let html = adf_to_html(
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Status {
+                    attrs: StatusAttrs {
+                        text: "Done".into(),
+                        color: color.into(),
+                        local_id: None,
+                    },
+                }]),
+            }],
+            "",
+        );
+        assert!(!html.contains(r#"" onmouseover"#), "{html}");
+
+        let html = adf_to_html(
+            vec![AdfBlockNode::MediaSingle {
+                attrs: MediaSingleAttrs {
+                    layout: color.into(),
+                    width: None,
+                    width_type: Some(r#"x" onclick="alert(1)"#.into()),
+                },
+                content: vec![],
+            }],
+            "",
+        );
+        assert!(!html.contains(r#"" onmouseover"#), "{html}");
+        assert!(!html.contains(r#"" onclick"#), "{html}");
+
+        // Class names built from attributes
+        let adf = vec![AdfBlockNode::Panel {
+            attrs: PanelAttrs {
+                panel_type: color.into(),
+            },
             content: vec![AdfBlockNode::Paragraph {
-                content: Some(vec![AdfNode::Date {
-                    attrs: DateAttrs {
-                        timestamp: "1700000000".into(),
+                content: Some(vec![AdfNode::Status {
+                    attrs: StatusAttrs {
+                        text: "Done".into(),
+                        color: color.into(),
+                        local_id: None,
                     },
                 }]),
             }],
-            version: 1,
+        }];
+        let class_names = ConvertOptions {
+            use_class_names: true,
+            ..Default::default()
         };
-        roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
+        let html = adf_to_html_with_options(adf.clone(), "", &class_names);
+        assert!(!html.contains(r#"" onmouseover"#), "{html}");
+        let html = adf_to_html(adf, "");
+        assert!(!html.contains(r#"" onmouseover"#), "{html}");
     }
 
     #[test]
-    fn test_mention_roundtrip() {
+    fn test_background_color_roundtrip() {
         let adf = AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Paragraph {
-                content: Some(vec![AdfNode::Mention {
-                    attrs: MentionAttrs {
-                        id: "user-1".into(),
-                        text: Some("Mentioned User".into()),
-                        access_level: Some(AccessLevel::Site),
-                        user_type: Some(UserType::App),
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Plain ".into(),
+                        marks: None,
                     },
-                }]),
+                    AdfNode::Text {
+                        text: "highlighted".into(),
+                        marks: Some(vec![AdfMark::BackgroundColor {
+                            color: "#fedec8".into(),
+                        }]),
+                    },
+                    AdfNode::Text {
+                        text: " and ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "bold".into(),
+                        marks: Some(vec![
+                            AdfMark::Strong,
+                            AdfMark::BackgroundColor {
+                                color: "#fedec8".into(),
+                            },
+                        ]),
+                    },
+                ]),
             }],
             version: 1,
         };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(
+            markdown.contains(r#"<span style="background-color: #fedec8">highlighted</span>"#),
+            "{markdown}"
+        );
         roundtrip_adf_html_adf(adf.clone());
         roundtrip_adf_html_md_html_adf(adf);
     }
 
     #[test]
-    fn test_inline_card_roundtrip() {
+    fn test_hardbreak_roundtrip() {
         let adf = AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Paragraph {
-                content: Some(vec![AdfNode::InlineCard {
-                    attrs: InlineCardAttrs {
-                        url: Some("https://example.com".into()),
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Line one".into(),
+                        marks: None,
                     },
-                }]),
+                    AdfNode::HardBreak,
+                    AdfNode::Text {
+                        text: "Line two".into(),
+                        marks: None,
+                    },
+                ]),
             }],
             version: 1,
         };
@@ -673,9 +2738,68 @@ mod tests {
     }
 
     #[test]
-    fn test_rule_roundtrip() {
+    fn test_markdown_line_breaks() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+
+        // A hard break becomes a Markdown hard break and adjacent paragraphs a blank line
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::Rule],
+            content: vec![
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![text("Line one"), AdfNode::HardBreak, text("Line two")]),
+                },
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![text("Next")]),
+                },
+            ],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert_eq!(markdown, "Line one  \nLine two\n\nNext");
+        assert_eq!(markdown_to_adf(&markdown).unwrap(), adf);
+
+        // Both Markdown hard break forms parse as a hard break
+        let hard_break = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![text("Line one"), AdfNode::HardBreak, text("Line two")]),
+            }],
+            version: 1,
+        };
+        assert_eq!(markdown_to_adf("Line one  \nLine two").unwrap(), hard_break);
+        assert_eq!(markdown_to_adf("Line one\\\nLine two").unwrap(), hard_break);
+
+        // A soft-wrapped paragraph stays one paragraph with the newline rendered as a space
+        assert_eq!(
+            markdown_to_adf("Line one\nLine two").unwrap(),
+            AdfBlockNode::Doc {
+                content: vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![text("Line one Line two")]),
+                }],
+                version: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decision_list_roundtrip() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::DecisionList {
+                attrs: LocalId {
+                    local_id: "decision-list-1".into(),
+                },
+                content: vec![DecisionItem::new(
+                    vec![AdfNode::Text {
+                        text: "Decision content".into(),
+                        marks: None,
+                    }],
+                    DecisionItemAttrs {
+                        state: DecisionItemState,
+                        local_id: "item-1".into(),
+                    },
+                )],
+            }],
             version: 1,
         };
         roundtrip_adf_html_adf(adf.clone());
@@ -683,22 +2807,29 @@ mod tests {
     }
 
     #[test]
-    fn test_bullet_list_roundtrip() {
+    fn test_table_roundtrip() {
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::BulletList {
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
                 content: vec![
-                    ListItem::new(vec![AdfBlockNode::Paragraph {
-                        content: Some(vec![AdfNode::Text {
-                            text: "Bullet 1".into(),
-                            marks: None,
-                        }]),
-                    }]),
-                    ListItem::new(vec![AdfBlockNode::Paragraph {
-                        content: Some(vec![AdfNode::Text {
-                            text: "Bullet 2".into(),
-                            marks: None,
-                        }]),
-                    }]),
+                    TableRow::new(vec![TableRowEntry::new_table_header(
+                        vec![AdfBlockNode::Paragraph {
+                            content: Some(vec![AdfNode::Text {
+                                text: "Header".into(),
+                                marks: None,
+                            }]),
+                        }],
+                        None,
+                    )]),
+                    TableRow::new(vec![TableRowEntry::new_table_cell(
+                        vec![AdfBlockNode::Paragraph {
+                            content: Some(vec![AdfNode::Text {
+                                text: "Cell".into(),
+                                marks: None,
+                            }]),
+                        }],
+                        None,
+                    )]),
                 ],
             }],
             version: 1,
@@ -708,112 +2839,205 @@ mod tests {
     }
 
     #[test]
-    fn test_ordered_list_roundtrip() {
+    fn test_table_cell_background_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::OrderedList {
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
                 content: vec![
-                    ListItem::new(vec![AdfBlockNode::Paragraph {
-                        content: Some(vec![AdfNode::Text {
-                            text: "Ordered 1".into(),
-                            marks: None,
-                        }]),
-                    }]),
-                    ListItem::new(vec![AdfBlockNode::Paragraph {
-                        content: Some(vec![AdfNode::Text {
-                            text: "Ordered 2".into(),
-                            marks: None,
-                        }]),
-                    }]),
+                    TableRow::new(vec![TableRowEntry::new_table_header(
+                        vec![paragraph("Status")],
+                        Some(TableCellAttrs {
+                            background: Some("#deebff".into()),
+                            ..Default::default()
+                        }),
+                    )]),
+                    TableRow::new(vec![TableRowEntry::new_table_cell(
+                        vec![paragraph("Green")],
+                        Some(TableCellAttrs {
+                            background: Some("#e3fcef".into()),
+                            ..Default::default()
+                        }),
+                    )]),
                 ],
-                attrs: None,
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
+        roundtrip_adf_html_adf(adf);
     }
 
     #[test]
-    fn test_blockquote_roundtrip() {
-        let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::Blockquote {
-                content: vec![AdfBlockNode::Paragraph {
-                    content: Some(vec![AdfNode::Text {
-                        text: "Blockquoted text".into(),
-                        marks: None,
-                    }]),
-                }],
+    fn test_table_attrs_roundtrip() {
+        let table = |attrs| AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Table {
+                attrs,
+                content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                    vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "Cell".into(),
+                            marks: None,
+                        }]),
+                    }],
+                    None,
+                )])],
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
+
+        let adf = table(Some(TableAttrs {
+            layout: Some("full-width".into()),
+            ..Default::default()
+        }));
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"<table data-layout="full-width">"#),
+            "{html}"
+        );
+        roundtrip_adf_html_adf(adf);
+
+        roundtrip_adf_html_adf(table(Some(TableAttrs {
+            is_number_column_enabled: Some(false),
+            layout: Some("center".into()),
+            width: Some(760),
+            display_mode: Some("fixed".into()),
+        })));
+
+        // Attributes that are all unset are the same as none
+        assert_eq!(
+            html_to_adf(&adf_to_html(vec![table(Some(TableAttrs::default()))], "")),
+            table(None)
+        );
     }
 
     #[test]
-    fn test_codeblock_roundtrip() {
+    fn test_table_cell_spans_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::CodeBlock {
+            content: vec![AdfBlockNode::Table {
                 attrs: None,
-                content: Some(vec![AdfNode::Text {
-                    text: "let x = 42;\n".into(),
-                    marks: None,
-                }]),
+                content: vec![
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(
+                            vec![paragraph("Merged")],
+                            Some(TableCellAttrs {
+                                colspan: Some(2),
+                                colwidth: Some(vec![100, 200]),
+                                ..Default::default()
+                            }),
+                        ),
+                        TableRowEntry::new_table_header(vec![paragraph("Plain")], None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_cell(
+                            vec![paragraph("Tall")],
+                            Some(TableCellAttrs {
+                                rowspan: Some(2),
+                                background: Some("#deebff".into()),
+                                ..Default::default()
+                            }),
+                        ),
+                        TableRowEntry::new_table_cell(vec![paragraph("a")], None),
+                        TableRowEntry::new_table_cell(vec![paragraph("b")], None),
+                    ]),
+                ],
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(
+            html.contains(r#"<th colspan="2" data-colwidth="100,200">"#),
+            "{html}"
+        );
+        assert!(html.contains(r#"<td rowspan="2" style="#), "{html}");
+        roundtrip_adf_html_adf(adf);
+
+        // A span of 1 is the default, so it is neither rendered nor parsed back
+        let cell = |attrs| AdfBlockNode::Table {
+            attrs: None,
+            content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                vec![paragraph("One")],
+                attrs,
+            )])],
+        };
+        let html = adf_to_html(
+            vec![cell(Some(TableCellAttrs {
+                colspan: Some(1),
+                ..Default::default()
+            }))],
+            "",
+        );
+        assert!(!html.contains("colspan"), "{html}");
+        assert_eq!(
+            html_to_adf(r#"<table><tr><td colspan="1" rowspan="1"><p>One</p></td></tr></table>"#),
+            AdfBlockNode::Doc {
+                content: vec![cell(None)],
+                version: 1,
+            }
+        );
     }
 
     #[test]
-    fn test_hardbreak_roundtrip() {
+    fn test_header_only_table_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::Paragraph {
-                content: Some(vec![
-                    AdfNode::Text {
-                        text: "Line one".into(),
-                        marks: None,
-                    },
-                    AdfNode::HardBreak,
-                    AdfNode::Text {
-                        text: "Line two".into(),
-                        marks: None,
-                    },
-                ]),
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![TableRow::new(vec![
+                    TableRowEntry::new_table_header(vec![paragraph("Name")], None),
+                    TableRowEntry::new_table_header(vec![paragraph("Value")], None),
+                ])],
             }],
             version: 1,
         };
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains("</thead><tbody></tbody></table>"), "{html}");
         roundtrip_adf_html_adf(adf.clone());
         roundtrip_adf_html_md_html_adf(adf);
-    }
 
-    #[test]
-    fn test_decision_list_roundtrip() {
+        // Rows led by a header cell stay in the body, in order
         let adf = AdfBlockNode::Doc {
-            content: vec![AdfBlockNode::DecisionList {
-                attrs: LocalId {
-                    local_id: "decision-list-1".into(),
-                },
-                content: vec![DecisionItem::new(
-                    vec![AdfNode::Text {
-                        text: "Decision content".into(),
-                        marks: None,
-                    }],
-                    DecisionItemAttrs {
-                        state: DecisionItemState,
-                        local_id: "item-1".into(),
-                    },
-                )],
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(vec![paragraph("Key")], None),
+                        TableRowEntry::new_table_header(vec![paragraph("Value")], None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_cell(vec![paragraph("a")], None),
+                        TableRowEntry::new_table_cell(vec![paragraph("1")], None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(vec![paragraph("Total")], None),
+                        TableRowEntry::new_table_cell(vec![paragraph("1")], None),
+                    ]),
+                ],
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
-        roundtrip_adf_html_md_html_adf(adf);
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert_eq!(html.matches("<thead>").count(), 1, "{html}");
+        assert!(html.contains("</thead><tbody><tr>"), "{html}");
+        roundtrip_adf_html_adf(adf);
     }
 
     #[test]
-    fn test_table_roundtrip() {
+    fn test_table_cell_with_pipe_roundtrip() {
         let adf = AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Table {
                 attrs: None,
@@ -821,7 +3045,7 @@ mod tests {
                     TableRow::new(vec![TableRowEntry::new_table_header(
                         vec![AdfBlockNode::Paragraph {
                             content: Some(vec![AdfNode::Text {
-                                text: "Header".into(),
+                                text: "Choice".into(),
                                 marks: None,
                             }]),
                         }],
@@ -830,7 +3054,7 @@ mod tests {
                     TableRow::new(vec![TableRowEntry::new_table_cell(
                         vec![AdfBlockNode::Paragraph {
                             content: Some(vec![AdfNode::Text {
-                                text: "Cell".into(),
+                                text: "a|b".into(),
                                 marks: None,
                             }]),
                         }],
@@ -840,7 +3064,8 @@ mod tests {
             }],
             version: 1,
         };
-        roundtrip_adf_html_adf(adf.clone());
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(markdown.contains("| a\\|b |"), "{markdown}");
         roundtrip_adf_html_md_html_adf(adf);
     }
 
@@ -849,7 +3074,10 @@ mod tests {
         let adf = AdfBlockNode::Doc {
             content: vec![
                 AdfBlockNode::Heading {
-                    attrs: HeadingAttrs { level: 1 },
+                    attrs: HeadingAttrs {
+                        level: 1,
+                        ..Default::default()
+                    },
                     content: Some(vec![AdfNode::Text {
                         text: "Document Title".into(),
                         marks: None,
@@ -1003,6 +3231,7 @@ mod tests {
                 AdfBlockNode::Expand {
                     attrs: ExpandAttrs {
                         title: Some("See more".into()),
+                        ..Default::default()
                     },
                     content: vec![AdfBlockNode::Paragraph {
                         content: Some(vec![AdfNode::Text {
@@ -1029,6 +3258,7 @@ mod tests {
                     },
                     AdfNode::Emoji {
                         attrs: EmojiAttrs {
+                            id: None,
                             text: Some("😄".into()),
                             short_name: ":smile:".into(),
                         },
@@ -1085,6 +3315,7 @@ mod tests {
                     AdfBlockNode::Expand {
                         attrs: ExpandAttrs {
                             title: Some("Expand inside panel".into()),
+                            ..Default::default()
                         },
                         content: vec![AdfBlockNode::Paragraph {
                             content: Some(vec![AdfNode::Text {
@@ -1182,6 +3413,7 @@ mod tests {
                                     },
                                     AdfNode::Emoji {
                                         attrs: EmojiAttrs {
+                                            id: None,
                                             text: Some("📊".into()),
                                             short_name: ":bar_chart:".into(),
                                         },
@@ -1283,7 +3515,10 @@ mod tests {
         let adf = AdfBlockNode::Doc {
             content: vec![
                 AdfBlockNode::Heading {
-                    attrs: HeadingAttrs { level: 1 },
+                    attrs: HeadingAttrs {
+                        level: 1,
+                        ..Default::default()
+                    },
                     content: Some(vec![AdfNode::Text {
                         text: "Comprehensive Doc".into(),
                         marks: None,
@@ -1294,11 +3529,12 @@ mod tests {
                         AdfNode::Text {
                             text: " Mixed content paragraph ".into(),
                             marks: Some(vec![AdfMark::TextColor {
-                                color: "blue".into(),
+                                color: "#4c9aff".into(),
                             }]),
                         },
                         AdfNode::Emoji {
                             attrs: EmojiAttrs {
+                                id: None,
                                 text: Some("🎉".into()),
                                 short_name: ":tada:".into(),
                             },
@@ -1330,6 +3566,7 @@ mod tests {
                 AdfBlockNode::Expand {
                     attrs: ExpandAttrs {
                         title: Some("Expand Block".into()),
+                        ..Default::default()
                     },
                     content: vec![AdfBlockNode::Paragraph {
                         content: Some(vec![AdfNode::Text {
@@ -1387,7 +3624,10 @@ mod tests {
     fn test_header_with_emoji() {
         let adf = AdfBlockNode::Doc {
             content: vec![AdfBlockNode::Heading {
-                attrs: HeadingAttrs { level: 1 },
+                attrs: HeadingAttrs {
+                    level: 1,
+                    ..Default::default()
+                },
                 content: Some(vec![
                     AdfNode::Text {
                         text: "🚀 Let's launch ".into(),
@@ -1395,6 +3635,7 @@ mod tests {
                     },
                     AdfNode::Emoji {
                         attrs: EmojiAttrs {
+                            id: None,
                             text: Some("😄".into()),
                             short_name: ":smile:".into(),
                         },
@@ -1409,7 +3650,7 @@ mod tests {
         };
 
         // ADF -> Markdown -> ADF should roundtrip cleanly
-        let markdown = adf_to_markdown(&[adf.clone()], "");
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
         let parsed = markdown_to_adf(&markdown).unwrap();
 
         assert_eq!(
@@ -1417,4 +3658,499 @@ mod tests {
             "Failed roundtrip for header containing emoji: {markdown}"
         );
     }
+
+    #[test]
+    fn test_table_cell_with_inline_content() {
+        let adf: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{
+                "type": "table",
+                "content": [{
+                    "type": "tableRow",
+                    "content": [
+                        { "type": "tableHeader", "content": [{ "type": "text", "text": "Name" }] },
+                        {
+                            "type": "tableCell",
+                            "content": [
+                                { "type": "text", "text": "Value" },
+                                { "type": "paragraph", "content": [{ "type": "text", "text": "More" }] },
+                            ],
+                        },
+                    ],
+                }],
+            }],
+        }))
+        .unwrap();
+
+        let html = adf_to_html(vec![adf.clone()], "");
+        assert!(html.contains("<th><p>Name</p></th>"), "{html}");
+        assert!(html.contains("<td><p>Value</p><p>More</p></td>"), "{html}");
+        roundtrip_adf_html_adf(adf);
+    }
+
+    #[test]
+    fn test_mixed_nested_lists_markdown() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let item = |text: &str| ListItem::new(vec![paragraph(text)]);
+
+        let expected = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![
+                    paragraph("a"),
+                    AdfBlockNode::OrderedList {
+                        attrs: None,
+                        content: vec![item("b"), item("c")],
+                    },
+                ])],
+            }],
+            version: 1,
+        };
+        assert_eq!(markdown_to_adf("- a\n  1. b\n  2. c").unwrap(), expected);
+        roundtrip_adf_html_md_html_adf(expected);
+
+        // The other way around, with a bullet list two levels down
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::OrderedList {
+                attrs: None,
+                content: vec![
+                    ListItem::new(vec![
+                        paragraph("First"),
+                        AdfBlockNode::BulletList {
+                            content: vec![ListItem::new(vec![
+                                paragraph("Inner"),
+                                AdfBlockNode::OrderedList {
+                                    attrs: None,
+                                    content: vec![item("Deepest")],
+                                },
+                            ])],
+                        },
+                    ]),
+                    item("Second"),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_tight_and_loose_list_markdown() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+
+        let tight = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![
+                    ListItem::new(vec![
+                        paragraph("One"),
+                        AdfBlockNode::OrderedList {
+                            attrs: None,
+                            content: vec![ListItem::new(vec![paragraph("Nested")])],
+                        },
+                    ]),
+                    ListItem::new(vec![paragraph("Two")]),
+                ],
+            }],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&tight), "");
+        assert_eq!(markdown, "*   One\n    1.  Nested\n*   Two");
+        roundtrip_adf_html_md_html_adf(tight);
+
+        let loose = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::OrderedList {
+                attrs: None,
+                content: vec![
+                    ListItem::new(vec![paragraph("First"), paragraph("More")]),
+                    ListItem::new(vec![paragraph("Second")]),
+                ],
+            }],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&loose), "");
+        assert!(markdown.contains("1.  First\n"), "{markdown}");
+        assert!(
+            markdown
+                .lines()
+                .filter(|line| line.trim().is_empty())
+                .count()
+                >= 2,
+            "Loose list items should be separated by blank lines: {markdown}"
+        );
+        roundtrip_adf_html_md_html_adf(loose);
+    }
+
+    #[test]
+    fn test_default_dir() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "שלום עולם".into(),
+                    marks: None,
+                }]),
+            }],
+            version: 1,
+        };
+        let options = ConvertOptions {
+            default_dir: Some(TextDirection::Rtl),
+            ..Default::default()
+        };
+        let html = adf_to_html_with_options(vec![adf.clone()], "", &options);
+        assert_eq!(html, "<body><div dir=\"rtl\"><p>שלום עולם</p></div></body>");
+        assert_eq!(html_to_adf(&html), adf);
+
+        assert!(!adf_to_html(vec![adf], "").contains("dir="));
+    }
+
+    #[test]
+    fn test_disallowed_url_schemes() {
+        let adf = markdown_to_adf(
+            "[Click](javascript:alert(1)) [Docs](https://example.com) [Mail](mailto:a@example.com) \
+             [Local](/wiki/a:b)\n\n![Pixel](data:image/png;base64,AAAA)",
+        )
+        .unwrap();
+        // Browsers ignore case, surrounding spaces and tabs in schemes
+        let spaced = serde_json::from_str(
+            &serde_json::to_string(&adf)
+                .unwrap()
+                .replace("javascript:", " JaVa\\tScRiPt:"),
+        )
+        .unwrap();
+
+        for adf in [adf.clone(), spaced] {
+            let safe = adf_to_html(vec![adf], "");
+            assert!(!safe.to_lowercase().contains("script:"), "{safe}");
+            assert!(!safe.contains("data:"), "{safe}");
+            assert!(safe.contains("Click"), "{safe}");
+            assert!(safe.contains(r#"href="https://example.com""#), "{safe}");
+            assert!(safe.contains(r#"href="mailto:a@example.com""#), "{safe}");
+            assert!(safe.contains(r#"href="/wiki/a:b""#), "{safe}");
+        }
+
+        let options = ConvertOptions {
+            allowed_url_schemes: None,
+            ..Default::default()
+        };
+        let permissive = adf_to_html_with_options(vec![adf], "", &options);
+        assert!(
+            permissive.contains(r#"href="javascript:alert(1)""#),
+            "{permissive}"
+        );
+        assert!(
+            permissive.contains(r#"src="data:image/png;base64,AAAA""#),
+            "{permissive}"
+        );
+    }
+
+    #[test]
+    fn test_email_profile() {
+        let text = |text: &str| serde_json::json!({ "type": "text", "text": text });
+        let paragraph = |text: &str| serde_json::json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] });
+        let adf: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                {
+                    "type": "paragraph",
+                    "content": [
+                        { "type": "status", "attrs": { "text": "Done", "color": "green" } },
+                        { "type": "mention", "attrs": { "id": "user-1", "text": "@Ada" } },
+                        { "type": "emoji", "attrs": { "shortName": ":smile:", "text": "😄" } },
+                        { "type": "date", "attrs": { "timestamp": "1700000000000" } },
+                    ],
+                },
+                { "type": "panel", "attrs": { "panelType": "warning" }, "content": [paragraph("Careful")] },
+                {
+                    "type": "expand",
+                    "attrs": { "title": "More" },
+                    "content": [{
+                        "type": "nestedExpand",
+                        "attrs": { "title": "Deeper" },
+                        "content": [paragraph("Hidden")],
+                    }],
+                },
+                {
+                    "type": "table",
+                    "content": [{
+                        "type": "tableRow",
+                        "content": [
+                            { "type": "tableHeader", "content": [paragraph("Name")] },
+                            {
+                                "type": "tableCell",
+                                "attrs": { "background": "#ffeeee" },
+                                "content": [paragraph("Value")],
+                            },
+                        ],
+                    }],
+                },
+                {
+                    "type": "taskList",
+                    "attrs": { "localId": "tasks" },
+                    "content": [{
+                        "type": "taskItem",
+                        "attrs": { "localId": "task-1", "state": "DONE" },
+                        "content": [text("Ship it")],
+                    }],
+                },
+                {
+                    "type": "decisionList",
+                    "attrs": { "localId": "decisions" },
+                    "content": [{
+                        "type": "decisionItem",
+                        "attrs": { "localId": "decision-1", "state": "DECIDED" },
+                        "content": [text("Agreed")],
+                    }],
+                },
+                { "type": "codeBlock", "attrs": { "language": "rust" }, "content": [text("let x;")] },
+            ],
+        }))
+        .unwrap();
+        let options = ConvertOptions {
+            html_profile: HtmlProfile::Email,
+            use_class_names: true,
+            ..Default::default()
+        };
+        let html = adf_to_html_with_options(vec![adf], "", &options);
+
+        for forbidden in [
+            "<adf-", "<details", "<summary", "<figure", "<time", "class=",
+        ] {
+            assert!(!html.contains(forbidden), "Found {forbidden} in {html}");
+        }
+        for expected in [
+            ">Done</span>",
+            ">@Ada</span>",
+            "😄",
+            "2023-11-14",
+            "background-color: #fffae6",
+            "<strong>More</strong>",
+            "<strong>Deeper</strong>",
+            "<table style=\"border-collapse: collapse\">",
+            "background-color: #ffeeee",
+            "\u{2611} Ship it",
+            "\u{25C6} Agreed",
+            "<pre style=",
+        ] {
+            assert!(html.contains(expected), "Missing {expected} in {html}");
+        }
+    }
+
+    #[test]
+    fn test_panel_in_table_cell_roundtrip() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let panel = AdfBlockNode::Panel {
+            attrs: PanelAttrs {
+                panel_type: "warning".into(),
+            },
+            content: vec![paragraph("Careful")],
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(vec![paragraph("Step")], None),
+                        TableRowEntry::new_table_header(vec![paragraph("Notes")], None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_cell(vec![paragraph("Deploy")], None),
+                        TableRowEntry::new_table_cell(vec![panel.clone()], None),
+                    ]),
+                ],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Markdown flattens a cell onto one line, so only HTML keeps blocks after the panel
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                    vec![panel, paragraph("After")],
+                    None,
+                )])],
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf);
+    }
+
+    #[test]
+    fn test_block_card_roundtrip() {
+        // Enough columns that `data-key-10` has to sort after `data-key-9`
+        let columns = (0..12)
+            .map(|i| TableColumn {
+                key: format!("customfield_{i}"),
+            })
+            .collect();
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BlockCard {
+                attrs: BlockCardAttrs {
+                    datasource: Some(DataSourceAttrs {
+                        id: "datasource-1".into(),
+                        parameters: DataSourceParameters {
+                            cloud_id: "cloud-1".into(),
+                            jql:
+                                r#"status = "In Progress" AND labels in (a&b, "c%d") ORDER BY rank"#
+                                    .into(),
+                        },
+                        views: vec![DataSourceView::Table(TableViewProperties { columns })],
+                    }),
+                    url: "https://example.atlassian.net/issues/?jql=status%20%3D%20open&x=1".into(),
+                },
+            }],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf);
+
+        // A plain URL card has no data source
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BlockCard {
+                attrs: BlockCardAttrs {
+                    datasource: None,
+                    url: "https://example.com/page".into(),
+                },
+            }],
+            version: 1,
+        };
+        let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+        assert!(markdown.contains("<adf-block-card "), "{markdown}");
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+
+        // Between paragraphs, the card stays a block of its own
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                paragraph("Before"),
+                AdfBlockNode::BlockCard {
+                    attrs: BlockCardAttrs {
+                        datasource: None,
+                        url: "https://example.com/page?a=1&b=2".into(),
+                    },
+                },
+                paragraph("After"),
+            ],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
+
+    #[test]
+    fn test_block_card_markdown() {
+        let block_card = serde_json::json!({
+            "type": "blockCard",
+            "attrs": {
+                "url": "https://example.atlassian.net/issues/?jql=project%20%3D%20ABC",
+                "datasource": {
+                    "id": "datasource-1",
+                    "parameters": { "cloudId": "cloud-1", "jql": "project = ABC" },
+                    "views": [{
+                        "type": "table",
+                        "properties": { "columns": [{ "key": "summary" }, { "key": "status" }] },
+                    }],
+                },
+            },
+        });
+        let top_level: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [block_card],
+        }))
+        .unwrap();
+        let in_table: AdfBlockNode = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{
+                "type": "table",
+                "content": [
+                    {
+                        "type": "tableRow",
+                        "content": [{
+                            "type": "tableHeader",
+                            "content": [{
+                                "type": "paragraph",
+                                "content": [{ "type": "text", "text": "Issues" }],
+                            }],
+                        }],
+                    },
+                    {
+                        "type": "tableRow",
+                        "content": [{ "type": "tableCell", "content": [block_card] }],
+                    },
+                ],
+            }],
+        }))
+        .unwrap();
+
+        // The table cell converter keeps the same custom elements as the document converter
+        for adf in [top_level, in_table] {
+            let markdown = adf_to_markdown(std::slice::from_ref(&adf), "");
+            assert!(markdown.contains("<adf-block-card "), "{markdown}");
+            roundtrip_adf_html_adf(adf.clone());
+            roundtrip_adf_html_md_html_adf(adf);
+        }
+    }
+
+    #[test]
+    fn test_zwj_sequences_and_direction_marks() {
+        let text = |text: &str, marks: Option<Vec<AdfMark>>| AdfNode::Text {
+            text: text.into(),
+            marks,
+        };
+        let adf = AdfBlockNode::Doc {
+            content: vec![
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![
+                        text("\u{200F}שלום\u{200F} ", None),
+                        text("👩\u{200D}💻", Some(vec![AdfMark::Strong])),
+                        text("\u{200E} and 👨\u{200D}👩\u{200D}👧 ", None),
+                        AdfNode::Emoji {
+                            attrs: EmojiAttrs {
+                                id: None,
+                                short_name: ":family:".into(),
+                                text: Some("👨\u{200D}👩\u{200D}👧".into()),
+                            },
+                        },
+                    ]),
+                },
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![
+                        text("in", None),
+                        text("tra", Some(vec![AdfMark::Em])),
+                        text("word \u{200B}", None),
+                        text("!", Some(vec![AdfMark::Strong])),
+                        text("x", None),
+                    ]),
+                },
+            ],
+            version: 1,
+        };
+        roundtrip_adf_html_adf(adf.clone());
+        roundtrip_adf_html_md_html_adf(adf);
+    }
 }
@@ -0,0 +1,378 @@
+use crate::adf::adf_types::{AdfBlockNode, clamp_heading_level};
+use crate::options::ConvertOptions;
+use crate::render::{RenderBackend, RenderedCell, render_adf, render_adf_with_options};
+
+/// Heading underline characters, by heading level.
+const HEADING_UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+/// Panel types paired with the admonition they render as. Other panels become notes.
+const PANEL_ADMONITIONS: [(&str, &str); 4] = [
+    ("warning", "warning"),
+    ("error", "error"),
+    ("success", "tip"),
+    ("tip", "tip"),
+];
+
+/// Renders ADF as reStructuredText, using Sphinx's `code-block` directive for code.
+///
+/// Panels become admonitions, titled expands become generic admonitions and tables become
+/// `list-table` directives. reStructuredText can't nest inline markup, so text with several
+/// marks may not render as intended.
+pub fn adf_to_rst(adf: &[AdfBlockNode]) -> String {
+    render_adf(adf, &RstBackend)
+}
+
+/// Like [`adf_to_rst`], with URLs filtered by the options'
+/// [`allowed_url_schemes`](ConvertOptions::allowed_url_schemes).
+pub fn adf_to_rst_with_options(adf: &[AdfBlockNode], options: &ConvertOptions) -> String {
+    render_adf_with_options(adf, &RstBackend, options)
+}
+
+/// The [`RenderBackend`] behind [`adf_to_rst`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RstBackend;
+
+impl RstBackend {
+    fn list(&self, items: Vec<(String, Vec<String>)>) -> String {
+        let multiline = items
+            .iter()
+            .any(|(_, blocks)| blocks.len() > 1 || blocks.iter().any(|block| block.contains('\n')));
+        items
+            .into_iter()
+            .map(|(marker, blocks)| {
+                let body = indent_except_first(&blocks.join("\n\n"), marker.len());
+                format!("{marker}{body}").trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            // Items spanning several lines need blank lines to keep them apart
+            .join(if multiline { "\n\n" } else { "\n" })
+    }
+}
+
+impl RenderBackend for RstBackend {
+    fn paragraph(&self, content: &str) -> String {
+        content.to_string()
+    }
+
+    fn heading(&self, level: u8, content: &str) -> String {
+        let underline = HEADING_UNDERLINES[usize::from(clamp_heading_level(level)) - 1];
+        let width = content.chars().count().max(1);
+        format!("{content}\n{}", underline.to_string().repeat(width))
+    }
+
+    fn code_block(&self, language: Option<&str>, code: &str) -> String {
+        let directive = match language {
+            Some(language) => format!(".. code-block:: {language}"),
+            None => "::".to_string(),
+        };
+        format!("{directive}\n\n{}", indent(code.trim_end_matches('\n'), 3))
+    }
+
+    fn blockquote(&self, blocks: Vec<String>) -> String {
+        indent(&blocks.join("\n\n"), 4)
+    }
+
+    fn bullet_list(&self, items: Vec<Vec<String>>) -> String {
+        self.list(
+            items
+                .into_iter()
+                .map(|blocks| ("- ".to_string(), blocks))
+                .collect(),
+        )
+    }
+
+    fn ordered_list(&self, start: u32, items: Vec<Vec<String>>) -> String {
+        self.list(
+            (start..)
+                .zip(items)
+                .map(|(number, blocks)| (format!("{number}. "), blocks))
+                .collect(),
+        )
+    }
+
+    fn table(&self, rows: Vec<Vec<RenderedCell>>) -> String {
+        let header_rows = rows
+            .iter()
+            .take_while(|row| !row.is_empty() && row.iter().all(|cell| cell.header))
+            .count();
+        let mut rst = String::from(".. list-table::\n");
+        if header_rows > 0 {
+            rst.push_str(&format!("   :header-rows: {header_rows}\n"));
+        }
+        rst.push('\n');
+        for row in rows {
+            for (i, cell) in row.into_iter().enumerate() {
+                let marker = if i == 0 { "   * - " } else { "     - " };
+                let body = indent_except_first(&cell.blocks.join("\n\n"), 7);
+                rst.push_str(format!("{marker}{body}").trim_end());
+                rst.push('\n');
+            }
+        }
+        rst.trim_end().to_string()
+    }
+
+    fn panel(&self, panel_type: &str, blocks: Vec<String>) -> String {
+        let admonition = PANEL_ADMONITIONS
+            .iter()
+            .find(|(ty, _)| *ty == panel_type)
+            .map_or("note", |(_, admonition)| admonition);
+        directive(&format!(".. {admonition}::"), blocks)
+    }
+
+    fn expand(&self, title: Option<&str>, blocks: Vec<String>) -> String {
+        match title {
+            Some(title) => directive(&format!(".. admonition:: {title}"), blocks),
+            None => blocks.join("\n\n"),
+        }
+    }
+
+    fn rule(&self) -> String {
+        "----------".to_string()
+    }
+
+    fn media(&self, src: Option<&str>, alt: Option<&str>) -> String {
+        match (src, alt) {
+            (Some(src), Some(alt)) => format!(".. image:: {src}\n   :alt: {alt}"),
+            (Some(src), None) => format!(".. image:: {src}"),
+            (None, Some(alt)) => self.text(alt),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '\\' | '*' | '`' | '|' | '_') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    fn strong(&self, content: &str) -> String {
+        format!("**{content}**")
+    }
+
+    fn em(&self, content: &str) -> String {
+        format!("*{content}*")
+    }
+
+    fn code(&self, code: &str) -> String {
+        format!("``{code}``")
+    }
+
+    fn link(&self, href: &str, content: &str) -> String {
+        // Anonymous references, so links sharing text don't clash
+        format!("`{content} <{href}>`__")
+    }
+
+    fn hard_break(&self) -> String {
+        "\n".to_string()
+    }
+}
+
+/// Renders a directive with the blocks as its indented body.
+fn directive(header: &str, blocks: Vec<String>) -> String {
+    if blocks.is_empty() {
+        return header.to_string();
+    }
+    format!("{header}\n\n{}", indent(&blocks.join("\n\n"), 3))
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let padding = " ".repeat(width);
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{padding}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Indents every line but the first, for content following a list marker.
+fn indent_except_first(text: &str, width: usize) -> String {
+    match text.split_once('\n') {
+        Some((first, rest)) => format!("{first}\n{}", indent(rest, width)),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_adf_to_rst() {
+        let text = |text: &str| json!({ "type": "text", "text": text });
+        let paragraph = |text: &str| json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] });
+        let adf: AdfBlockNode = serde_json::from_value(json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                { "type": "heading", "attrs": { "level": 1 }, "content": [text("Release notes")] },
+                {
+                    "type": "paragraph",
+                    "content": [
+                        text("See "),
+                        {
+                            "type": "text",
+                            "text": "the docs",
+                            "marks": [{ "type": "link", "attrs": { "href": "https://example.com" } }],
+                        },
+                        text(" for *all* changes, or run "),
+                        { "type": "text", "text": "make_docs", "marks": [{ "type": "code" }] },
+                        text("."),
+                    ],
+                },
+                {
+                    "type": "bulletList",
+                    "content": [
+                        {
+                            "type": "listItem",
+                            "content": [
+                                paragraph("Parser"),
+                                {
+                                    "type": "orderedList",
+                                    "content": [{ "type": "listItem", "content": [paragraph("Faster")] }],
+                                },
+                            ],
+                        },
+                        { "type": "listItem", "content": [paragraph("Renderer")] },
+                    ],
+                },
+                {
+                    "type": "codeBlock",
+                    "attrs": { "language": "rust" },
+                    "content": [text("fn main() {\n    run();\n}\n")],
+                },
+                { "type": "panel", "attrs": { "panelType": "warning" }, "content": [paragraph("Breaking")] },
+                {
+                    "type": "table",
+                    "content": [
+                        {
+                            "type": "tableRow",
+                            "content": [
+                                { "type": "tableHeader", "content": [paragraph("Name")] },
+                                { "type": "tableHeader", "content": [paragraph("Value")] },
+                            ],
+                        },
+                        {
+                            "type": "tableRow",
+                            "content": [
+                                { "type": "tableCell", "content": [paragraph("a")] },
+                                { "type": "tableCell", "content": [paragraph("b"), paragraph("c")] },
+                            ],
+                        },
+                    ],
+                },
+                {
+                    "type": "taskList",
+                    "attrs": { "localId": "tasks" },
+                    "content": [
+                        { "type": "taskItem", "attrs": { "localId": "1", "state": "DONE" }, "content": [text("Ship")] },
+                        { "type": "taskItem", "attrs": { "localId": "2", "state": "TODO" }, "content": [text("Announce")] },
+                    ],
+                },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            adf_to_rst(&[adf]),
+            r#"Release notes
+=============
+
+See `the docs <https://example.com>`__ for \*all\* changes, or run ``make_docs``.
+
+- Parser
+
+  1. Faster
+
+- Renderer
+
+.. code-block:: rust
+
+   fn main() {
+       run();
+   }
+
+.. warning::
+
+   Breaking
+
+.. list-table::
+   :header-rows: 1
+
+   * - Name
+     - Value
+   * - a
+     - b
+
+       c
+
+- [x] Ship
+- [ ] Announce
+"#
+        );
+    }
+
+    #[test]
+    fn test_adf_to_rst_urls_and_mentions() {
+        let adf: AdfBlockNode = serde_json::from_value(json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                {
+                    "type": "paragraph",
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": "click",
+                            "marks": [{ "type": "link", "attrs": { "href": "javascript:alert(1)" } }],
+                        },
+                        { "type": "text", "text": " " },
+                        { "type": "inlineCard", "attrs": { "url": "javascript:alert(2)" } },
+                        { "type": "mention", "attrs": { "id": "" } },
+                        { "type": "text", "text": " " },
+                        { "type": "mention", "attrs": { "id": "abc" } },
+                    ],
+                },
+                { "type": "blockCard", "attrs": { "url": "javascript:alert(3)" } },
+                {
+                    "type": "mediaSingle",
+                    "attrs": { "layout": "center" },
+                    "content": [{
+                        "type": "media",
+                        "attrs": { "type": "file", "id": "1", "alt": "diagram" },
+                        "marks": [{ "type": "link", "attrs": { "href": "javascript:alert(4)" } }],
+                    }],
+                },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            adf_to_rst(std::slice::from_ref(&adf)),
+            "click @unknown @abc\n\ndiagram\n"
+        );
+
+        // Without a scheme allowlist the links are kept
+        let options = ConvertOptions {
+            allowed_url_schemes: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            adf_to_rst_with_options(&[adf], &options),
+            "`click <javascript:alert(1)>`__ `javascript:alert(2) <javascript:alert(2)>`__@unknown @abc\n\n\
+             `javascript:alert(3) <javascript:alert(3)>`__\n\n\
+             .. image:: javascript:alert(4)\n   :alt: diagram\n"
+        );
+    }
+}
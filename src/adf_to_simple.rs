@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::adf::adf_types::AdfBlockNode;
+
+/// A flattened, uniform view of an ADF tree intended for template engines.
+///
+/// Every node has the same shape regardless of its ADF type: the ADF `type` becomes `kind`,
+/// `attrs` (plus any other fields such as `marks`) are merged into `attrs`, and `content`
+/// becomes `children`. The conversion is lossy in the sense that it cannot be turned back into
+/// typed ADF, but no information from the source document is dropped.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SimpleNode {
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub attrs: Map<String, Value>,
+    /// The text of a `text` node, or the concatenated text of the direct `text` children of any
+    /// other node (e.g. the text of a heading or paragraph).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SimpleNode>,
+}
+
+pub fn adf_to_simple(adf: &AdfBlockNode) -> SimpleNode {
+    match serde_json::to_value(adf) {
+        Ok(value) => value_to_simple(value),
+        Err(err) => {
+            tracing::warn!("Failed to serialize ADF for simplification: {}", err);
+            SimpleNode::default()
+        }
+    }
+}
+
+fn value_to_simple(value: Value) -> SimpleNode {
+    let Value::Object(mut object) = value else {
+        return SimpleNode::default();
+    };
+
+    let kind = match object.remove("type") {
+        Some(Value::String(kind)) => kind,
+        _ => String::new(),
+    };
+    let own_text = match object.remove("text") {
+        Some(Value::String(text)) => Some(text),
+        _ => None,
+    };
+    let children: Vec<SimpleNode> = match object.remove("content") {
+        Some(Value::Array(items)) => items.into_iter().map(value_to_simple).collect(),
+        _ => vec![],
+    };
+    let mut attrs = match object.remove("attrs") {
+        Some(Value::Object(attrs)) => attrs,
+        _ => Map::new(),
+    };
+    // Remaining fields such as `marks` or `version` are kept alongside the attrs
+    attrs.extend(object);
+
+    let text = own_text.or_else(|| {
+        let text: String = children
+            .iter()
+            .filter(|child| child.kind == "text")
+            .filter_map(|child| child.text.as_deref())
+            .collect();
+        if text.is_empty() { None } else { Some(text) }
+    });
+
+    SimpleNode {
+        kind,
+        attrs,
+        text,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::adf::adf_types::{AdfMark, AdfNode, HeadingAttrs, ListItem};
+
+    #[test]
+    fn test_heading_to_simple() {
+        let adf = AdfBlockNode::Heading {
+            attrs: HeadingAttrs {
+                level: 2,
+                ..Default::default()
+            },
+            content: Some(vec![
+                AdfNode::Text {
+                    text: "Release ".into(),
+                    marks: None,
+                },
+                AdfNode::Text {
+                    text: "notes".into(),
+                    marks: Some(vec![AdfMark::Strong]),
+                },
+            ]),
+        };
+
+        let simple = adf_to_simple(&adf);
+        assert_eq!(simple.kind, "heading");
+        assert_eq!(simple.attrs.get("level"), Some(&json!(2)));
+        assert_eq!(simple.text.as_deref(), Some("Release notes"));
+        assert_eq!(simple.children.len(), 2);
+        assert_eq!(
+            simple.children[1].attrs.get("marks"),
+            Some(&json!([{ "type": "strong" }]))
+        );
+    }
+
+    #[test]
+    fn test_doc_to_simple() {
+        let adf = AdfBlockNode::Doc {
+            content: vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Item".into(),
+                        marks: None,
+                    }]),
+                }])],
+            }],
+            version: 1,
+        };
+
+        let simple = adf_to_simple(&adf);
+        assert_eq!(
+            serde_json::to_value(&simple).unwrap(),
+            json!({
+                "kind": "doc",
+                "attrs": { "version": 1 },
+                "children": [{
+                    "kind": "bulletList",
+                    "children": [{
+                        "kind": "listItem",
+                        "children": [{
+                            "kind": "paragraph",
+                            "text": "Item",
+                            "children": [{ "kind": "text", "text": "Item" }],
+                        }],
+                    }],
+                }],
+            })
+        );
+    }
+}
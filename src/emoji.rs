@@ -0,0 +1,196 @@
+/// Resolves a common emoji shortname, such as `:smile:` or `tada`, to its Unicode glyph.
+///
+/// Only emoji that are a single code point are known, so shortnames for sequences such as flags
+/// or families give `None`, as do custom emoji like `:party-parrot:`.
+pub fn shortname_to_unicode(short_name: &str) -> Option<char> {
+    let name = short_name.trim().trim_matches(':');
+    Some(match name {
+        // Faces
+        "smile" => '😄',
+        "smiley" => '😃',
+        "grinning" => '😀',
+        "grin" => '😁',
+        "laughing" | "satisfied" => '😆',
+        "sweat_smile" => '😅',
+        "joy" => '😂',
+        "rofl" => '🤣',
+        "slight_smile" | "slightly_smiling_face" => '🙂',
+        "upside_down" | "upside_down_face" => '🙃',
+        "wink" => '😉',
+        "blush" => '😊',
+        "innocent" => '😇',
+        "heart_eyes" => '😍',
+        "kissing_heart" => '😘',
+        "yum" => '😋',
+        "stuck_out_tongue" => '😛',
+        "thinking" | "thinking_face" => '🤔',
+        "neutral_face" => '😐',
+        "expressionless" => '😑',
+        "no_mouth" => '😶',
+        "smirk" => '😏',
+        "unamused" => '😒',
+        "rolling_eyes" => '🙄',
+        "grimacing" => '😬',
+        "relieved" => '😌',
+        "pensive" => '😔',
+        "sleepy" => '😪',
+        "sleeping" => '😴',
+        "mask" => '😷',
+        "sunglasses" => '😎',
+        "nerd" | "nerd_face" => '🤓',
+        "confused" => '😕',
+        "worried" => '😟',
+        "slight_frown" | "slightly_frowning_face" => '🙁',
+        "open_mouth" => '😮',
+        "astonished" => '😲',
+        "flushed" => '😳',
+        "scream" => '😱',
+        "cry" => '😢',
+        "sob" => '😭',
+        "angry" => '😠',
+        "rage" => '😡',
+        "sweat" => '😓',
+        "tired_face" => '😫',
+        "party" | "partying_face" => '🥳',
+        "skull" => '💀',
+        "poop" | "hankey" => '💩',
+        "robot" => '🤖',
+        // Hands and people
+        "thumbsup" | "+1" => '👍',
+        "thumbsdown" | "-1" => '👎',
+        "ok_hand" => '👌',
+        "wave" => '👋',
+        "clap" => '👏',
+        "raised_hands" => '🙌',
+        "pray" => '🙏',
+        "muscle" => '💪',
+        "point_up_2" => '👆',
+        "point_down" => '👇',
+        "point_left" => '👈',
+        "point_right" => '👉',
+        "fist" => '✊',
+        "handshake" => '🤝',
+        "crossed_fingers" => '🤞',
+        "eyes" => '👀',
+        "brain" => '🧠',
+        // Symbols
+        "white_check_mark" => '✅',
+        "heavy_check_mark" | "check_mark" => '✔',
+        "x" => '❌',
+        "negative_squared_cross_mark" => '❎',
+        "question" => '❓',
+        "grey_question" => '❔',
+        "exclamation" => '❗',
+        "grey_exclamation" => '❕',
+        "no_entry" => '⛔',
+        "no_entry_sign" => '🚫',
+        "red_circle" => '🔴',
+        "large_blue_circle" | "blue_circle" => '🔵',
+        "green_circle" => '🟢',
+        "yellow_circle" => '🟡',
+        "white_circle" => '⚪',
+        "black_circle" => '⚫',
+        "star" => '⭐',
+        "star2" => '🌟',
+        "sparkles" => '✨',
+        "zap" => '⚡',
+        "fire" => '🔥',
+        "boom" => '💥',
+        "100" => '💯',
+        "heart" => '❤',
+        "broken_heart" => '💔',
+        "green_heart" => '💚',
+        "blue_heart" => '💙',
+        "yellow_heart" => '💛',
+        "purple_heart" => '💜',
+        "arrow_right" => '➡',
+        "arrow_left" => '⬅',
+        "arrow_up" => '⬆',
+        "arrow_down" => '⬇',
+        "hourglass" => '⌛',
+        "stopwatch" => '⏱',
+        "alarm_clock" => '⏰',
+        "lock" => '🔒',
+        "unlock" => '🔓',
+        "key" => '🔑',
+        "bell" => '🔔',
+        "link" => '🔗',
+        "mag" => '🔍',
+        // Objects and activities
+        "tada" => '🎉',
+        "confetti_ball" => '🎊',
+        "gift" => '🎁',
+        "trophy" => '🏆',
+        "medal" | "sports_medal" => '🏅',
+        "dart" => '🎯',
+        "rocket" => '🚀',
+        "bulb" => '💡',
+        "memo" | "pencil" => '📝',
+        "book" | "open_book" => '📖',
+        "books" => '📚',
+        "bookmark" => '🔖',
+        "calendar" => '📆',
+        "date" => '📅',
+        "clipboard" => '📋',
+        "pushpin" => '📌',
+        "paperclip" => '📎',
+        "chart_with_upwards_trend" => '📈',
+        "chart_with_downwards_trend" => '📉',
+        "bar_chart" => '📊',
+        "file_folder" => '📁',
+        "package" => '📦',
+        "email" | "e-mail" => '📧',
+        "envelope" => '✉',
+        "phone" | "telephone" => '☎',
+        "computer" => '💻',
+        "keyboard" => '⌨',
+        "wrench" => '🔧',
+        "hammer" => '🔨',
+        "gear" => '⚙',
+        "construction" => '🚧',
+        "bug" => '🐛',
+        "coffee" => '☕',
+        "beer" => '🍺',
+        "pizza" => '🍕',
+        "cake" => '🍰',
+        "moneybag" => '💰',
+        "dollar" => '💵',
+        "speech_balloon" => '💬',
+        "thought_balloon" => '💭',
+        "loudspeaker" => '📢',
+        "mega" => '📣',
+        "sun" | "sunny" => '☀',
+        "cloud" => '☁',
+        "umbrella" => '☔',
+        "snowflake" => '❄',
+        "rainbow" => '🌈',
+        "earth_americas" => '🌎',
+        "globe_with_meridians" => '🌐',
+        "house" => '🏠',
+        "office" => '🏢',
+        "cat" => '🐱',
+        "dog" => '🐶',
+        "unicorn" => '🦄',
+        "turtle" => '🐢',
+        "snail" => '🐌',
+        "seedling" => '🌱',
+        "four_leaf_clover" => '🍀',
+        "rose" => '🌹',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortname_to_unicode() {
+        assert_eq!(shortname_to_unicode(":smile:"), Some('😄'));
+        assert_eq!(shortname_to_unicode(":tada:"), Some('🎉'));
+        assert_eq!(shortname_to_unicode("bar_chart"), Some('📊'));
+        assert_eq!(shortname_to_unicode(":+1:"), Some('👍'));
+        assert_eq!(shortname_to_unicode(":party-parrot:"), None);
+        assert_eq!(shortname_to_unicode(""), None);
+    }
+}
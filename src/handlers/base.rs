@@ -8,11 +8,63 @@ use crate::{
 
 pub(crate) fn hard_break_start_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, _element: Element| {
+        ADFBuilder::flush_text(state);
+        // More breaks after a split would only open the next paragraph with blank lines
+        if state.trim_text_seam && at_paragraph_start(state) {
+            return true;
+        }
+        if state.options.split_on_double_break && split_on_double_break(state) {
+            state.trim_text_seam = true;
+            return true;
+        }
         ADFBuilder::flush_text_and_push_inline(state, AdfNode::HardBreak);
         true
     }) as HandlerFn
 }
 
+/// Whether inline content added now would be the first in its paragraph.
+fn at_paragraph_start(state: &ADFBuilderState) -> bool {
+    match state.stack.last() {
+        Some(BlockContext::Paragraph(nodes)) => nodes.is_empty(),
+        Some(BlockContext::Blockquote(nodes)) | Some(BlockContext::ListItem(nodes)) => matches!(
+            nodes.last(),
+            Some(AdfBlockNode::Paragraph { content }) if content.as_ref().is_none_or(Vec::is_empty)
+        ),
+        _ => false,
+    }
+}
+
+/// Turns a hard break directly following another one into a paragraph break.
+fn split_on_double_break(state: &mut ADFBuilderState) -> bool {
+    match state.stack.last_mut() {
+        Some(BlockContext::Paragraph(nodes)) => {
+            if !matches!(nodes.last(), Some(AdfNode::HardBreak)) {
+                return false;
+            }
+            nodes.pop();
+            ADFBuilder::close_current_block(state);
+            state.stack.push(BlockContext::Paragraph(vec![]));
+            true
+        }
+        Some(BlockContext::Blockquote(nodes)) | Some(BlockContext::ListItem(nodes)) => {
+            let Some(AdfBlockNode::Paragraph {
+                content: Some(content),
+            }) = nodes.last_mut()
+            else {
+                return false;
+            };
+            if !matches!(content.last(), Some(AdfNode::HardBreak)) {
+                return false;
+            }
+            content.pop();
+            // Inline content following the break starts filling this paragraph
+            nodes.push(AdfBlockNode::Paragraph { content: None });
+            true
+        }
+        _ => false,
+    }
+}
+
 pub(crate) fn rule_start_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, _element: Element| {
         ADFBuilder::flush_text(state);
@@ -23,8 +75,8 @@ pub(crate) fn rule_start_handler() -> HandlerFn {
                 BlockContext::Paragraph(_)
                     | BlockContext::ListItem(_)
                     | BlockContext::Blockquote(_)
-                    | BlockContext::TableBlockCell(_)
-                    | BlockContext::TableBlockHeader(_)
+                    | BlockContext::TableBlockCell(..)
+                    | BlockContext::TableBlockHeader(..)
             )
         ) {
             ADFBuilder::close_current_block(state);
@@ -34,17 +86,39 @@ pub(crate) fn rule_start_handler() -> HandlerFn {
     }) as HandlerFn
 }
 
+/// Reads a code block language from either a `data-language` attribute or a `language-` class.
+fn code_language(element: &Element) -> Option<String> {
+    element.attrs.iter().find_map(|attr| {
+        match attr.name.local.as_ref() {
+            "data-language" => Some(attr.value.trim().to_string()),
+            "class" => attr
+                .value
+                .split_whitespace()
+                .find_map(|class| class.strip_prefix("language-"))
+                .map(str::to_string),
+            _ => None,
+        }
+        .filter(|language| !language.is_empty())
+    })
+}
+
 pub(crate) fn code_start_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
         ADFBuilder::flush_text(state);
-        let in_pre = state
+        let code_block = state
             .stack
-            .iter()
-            .any(|ctx| matches!(ctx, BlockContext::CodeBlock(_)));
-        if !in_pre {
-            state.mark_stack.push(AdfMark::Code);
+            .iter_mut()
+            .rev()
+            .find(|ctx| matches!(ctx, BlockContext::CodeBlock(..)));
+        match code_block {
+            // Inside <pre> the code is handled purely as a block, only the language is of interest
+            Some(BlockContext::CodeBlock(_, language)) => {
+                if language.is_none() {
+                    *language = code_language(&element);
+                }
+            }
+            _ => state.mark_stack.push(AdfMark::Code),
         }
-        // If inside <pre>, do nothing (handled purely as block)
         true
     }) as HandlerFn
 }
@@ -55,7 +129,7 @@ pub(crate) fn code_end_handler() -> HandlerFn {
         let in_pre = state
             .stack
             .iter()
-            .any(|ctx| matches!(ctx, BlockContext::CodeBlock(_)));
+            .any(|ctx| matches!(ctx, BlockContext::CodeBlock(..)));
         if !in_pre {
             ADFBuilder::pop_mark(state, |m| matches!(m, AdfMark::Code));
         }
@@ -106,7 +180,8 @@ pub(crate) fn div_start_handler() -> HandlerFn {
 pub(crate) fn div_end_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, _element: Element| {
         ADFBuilder::flush_text(state);
-        if let Some(BlockContext::CustomBlock(CustomBlockType::Div, nodes, _)) = state.stack.pop() {
+        let frame = state.stack.pop();
+        if let Some(BlockContext::CustomBlock(CustomBlockType::Div, nodes, _)) = frame {
             if nodes.is_empty() {
                 return true;
             }
@@ -115,7 +190,7 @@ pub(crate) fn div_end_handler() -> HandlerFn {
                 ADFBuilder::push_node_block_to_parent(state, node);
             }
         } else {
-            panic!("Mismatched div close tag");
+            state.fail_mismatched_close("div", frame);
         }
         true
     }) as HandlerFn
@@ -129,6 +204,7 @@ pub(crate) fn ul_start_handler() -> HandlerFn {
         state.stack.push(BlockContext::PendingList {
             nodes: vec![],
             ordered: false,
+            start: None,
             local_id: custom_id.map(|id| id.local_id),
             local_tag: custom_tag,
         });
@@ -137,11 +213,17 @@ pub(crate) fn ul_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn ol_start_handler() -> HandlerFn {
-    Box::new(|state, _| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
+        let start = element
+            .attrs
+            .iter()
+            .find(|attr| attr.name.local.as_ref() == "start")
+            .and_then(|attr| attr.value.trim().parse().ok());
         state.stack.push(BlockContext::PendingList {
             nodes: vec![],
             ordered: true,
+            start,
             local_id: None,
             local_tag: None,
         });
@@ -166,9 +248,11 @@ pub(crate) fn p_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn pre_start_handler() -> HandlerFn {
-    Box::new(|state, _| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
-        state.stack.push(BlockContext::CodeBlock(vec![]));
+        state
+            .stack
+            .push(BlockContext::CodeBlock(vec![], code_language(&element)));
         true
     })
 }
@@ -320,20 +404,20 @@ pub(crate) fn mark_end_handler() -> HandlerFn {
 pub(crate) fn header_end_handler(level: u8) -> HandlerFn {
     Box::new(move |state, _| {
         ADFBuilder::flush_text(state);
-        if let Some(BlockContext::Heading(lvl, nodes)) = state.stack.pop() {
-            if lvl == level {
+        match state.stack.pop() {
+            Some(BlockContext::Heading(lvl, nodes)) if lvl == level => {
                 ADFBuilder::push_node_block_to_parent(
                     state,
                     AdfBlockNode::Heading {
-                        attrs: HeadingAttrs { level },
+                        attrs: HeadingAttrs {
+                            level,
+                            ..Default::default()
+                        },
                         content: Some(nodes),
                     },
                 );
-            } else {
-                panic!("Mismatched heading close level");
             }
-        } else {
-            panic!("Mismatched heading close tag");
+            frame => state.fail_mismatched_close(&format!("h{level}"), frame),
         }
         true
     })
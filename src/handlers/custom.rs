@@ -2,10 +2,11 @@ use std::collections::HashMap;
 
 use chrono::DateTime;
 
-use super::{ADFBuilderState, BlockContext, CustomBlockType, Element};
+use super::{ADFBuilderState, BlockContext, CustomBlockType, Element, find_attr};
 use crate::{
     adf::adf_types::{
-        AccessLevel, AdfBlockNode, AdfNode, EmojiAttrs, LocalId, StatusAttrs, UserType,
+        AccessLevel, AdfBlockNode, AdfNode, BlockCardAttrs, DataSourceView, EmojiAttrs, LocalId,
+        StatusAttrs, TableColumn, TableViewProperties, UserType,
     },
     html_to_adf::{ADFBuilder, HandlerFn, extract_style},
 };
@@ -26,9 +27,11 @@ pub(crate) fn date_start_handler() -> HandlerFn {
     }) as HandlerFn
 }
 
+/// ADF dates only carry a timestamp, so the visible text of the `<time>` element (e.g. "March 5")
+/// is intentionally dropped; renderers derive the display text from the timestamp instead.
 pub(crate) fn date_end_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, _element: Element| {
-        ADFBuilder::flush_text(state);
+        state.current_text.clear();
         if let Some(BlockContext::CustomBlock(CustomBlockType::Date, _, attrs)) = state.stack.pop()
         {
             let timestamp_str = attrs.get("datetime").cloned().unwrap_or_default();
@@ -103,41 +106,39 @@ pub(crate) fn summary_end_handler() -> HandlerFn {
 }
 
 pub(crate) fn details_end_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
         ADFBuilder::flush_text(state);
 
-        if let Some(BlockContext::CustomBlock(ty, nodes, attrs)) = state.stack.pop() {
-            let title = attrs.get("data-summary").cloned().unwrap_or_default();
-            let nodes = ADFBuilder::trim_empty_paragraphs(nodes);
+        let frame = state.stack.pop();
+        let Some(BlockContext::CustomBlock(
+            ty @ (CustomBlockType::Expand | CustomBlockType::NestedExpand),
+            nodes,
+            attrs,
+        )) = frame
+        else {
+            state.fail_mismatched_close(&element.tag, frame);
+            return true;
+        };
+        let title = attrs.get("data-summary").cloned().unwrap_or_default();
+        let expanded = attrs.contains_key("open");
+        let nodes = ADFBuilder::trim_empty_paragraphs(nodes);
 
-            match ty {
-                CustomBlockType::Expand => {
-                    ADFBuilder::push_node_block_to_parent(
-                        state,
-                        AdfBlockNode::Expand {
-                            attrs: crate::adf::adf_types::ExpandAttrs {
-                                title: if title.is_empty() { None } else { Some(title) },
-                            },
-                            content: nodes,
-                        },
-                    );
-                    true
-                }
-                CustomBlockType::NestedExpand => {
-                    ADFBuilder::push_node_block_to_parent(
-                        state,
-                        AdfBlockNode::NestedExpand {
-                            attrs: crate::adf::adf_types::NestedAttrs { title },
-                            content: nodes,
-                        },
-                    );
-                    true
-                }
-                _ => false,
+        let node = if ty == CustomBlockType::Expand {
+            AdfBlockNode::Expand {
+                attrs: crate::adf::adf_types::ExpandAttrs {
+                    title: if title.is_empty() { None } else { Some(title) },
+                    expanded,
+                },
+                content: nodes,
             }
         } else {
-            false
-        }
+            AdfBlockNode::NestedExpand {
+                attrs: crate::adf::adf_types::NestedAttrs { title, expanded },
+                content: nodes,
+            }
+        };
+        ADFBuilder::push_node_block_to_parent(state, node);
+        true
     })
 }
 
@@ -205,24 +206,23 @@ pub(crate) fn mention_end_handler() -> HandlerFn {
         {
             let text = state.current_text.trim().to_string();
             state.current_text.clear();
+            let has_text = !text.is_empty() && !attrs.contains_key("data-mention-fallback");
             let user_type: Option<UserType> = attrs
                 .get("data-mention-user-type")
                 .map(|s| format!("\"{}\"", s.as_str()))
-                .map(|s| serde_json::from_str(&s).ok())
-                .flatten();
+                .and_then(|s| serde_json::from_str(&s).ok());
 
             let access_level: Option<AccessLevel> = attrs
                 .get("data-mention-access-level")
                 .map(|s| format!("\"{}\"", s.as_str()))
-                .map(|s| serde_json::from_str(&s).ok())
-                .flatten();
+                .and_then(|s| serde_json::from_str(&s).ok());
 
             ADFBuilder::push_node_to_parent(
                 state,
                 AdfNode::Mention {
                     attrs: crate::adf::adf_types::MentionAttrs {
                         id: attrs.get("data-mention-id").cloned().unwrap_or_default(),
-                        text: if text.is_empty() { None } else { Some(text) },
+                        text: has_text.then_some(text),
                         user_type,
                         access_level,
                     },
@@ -235,6 +235,85 @@ pub(crate) fn mention_end_handler() -> HandlerFn {
     })
 }
 
+pub(crate) fn block_card_start_handler() -> HandlerFn {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        ADFBuilder::flush_text(state);
+        state.stack.push(BlockContext::BlockCard(BlockCardAttrs {
+            url: find_attr(&element, "data-block-card")
+                .unwrap_or_default()
+                .to_string(),
+            ..Default::default()
+        }));
+        true
+    }) as HandlerFn
+}
+
+pub(crate) fn block_card_data_source_start_handler() -> HandlerFn {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        let Some(BlockContext::BlockCard(attrs)) = state.stack.last_mut() else {
+            return false;
+        };
+        let datasource = attrs.datasource.get_or_insert_default();
+        datasource.id = find_attr(&element, "data-source")
+            .unwrap_or_default()
+            .to_string();
+        datasource.parameters.cloud_id = find_attr(&element, "data-cloud-id")
+            .unwrap_or_default()
+            .to_string();
+        // The JQL is URL encoded when rendered
+        let jql = find_attr(&element, "data-jql").unwrap_or_default();
+        datasource.parameters.jql = urlencoding::decode(jql)
+            .map(|jql| jql.into_owned())
+            .unwrap_or_else(|_| jql.to_string());
+        true
+    }) as HandlerFn
+}
+
+pub(crate) fn block_card_view_start_handler() -> HandlerFn {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        let Some(BlockContext::BlockCard(attrs)) = state.stack.last_mut() else {
+            return false;
+        };
+        if find_attr(&element, "data-type") != Some("table") {
+            return false;
+        }
+        let mut columns: Vec<(usize, String)> = element
+            .attrs
+            .iter()
+            .filter_map(|attr| {
+                let index = attr.name.local.strip_prefix("data-key-")?.parse().ok()?;
+                Some((index, attr.value.to_string()))
+            })
+            .collect();
+        columns.sort_by_key(|(index, _)| *index);
+        attrs
+            .datasource
+            .get_or_insert_default()
+            .views
+            .push(DataSourceView::Table(TableViewProperties {
+                columns: columns
+                    .into_iter()
+                    .map(|(_, key)| TableColumn { key })
+                    .collect(),
+            }));
+        true
+    }) as HandlerFn
+}
+
+pub(crate) fn block_card_end_handler() -> HandlerFn {
+    Box::new(|state: &mut ADFBuilderState, _element: Element| {
+        if !matches!(state.stack.last(), Some(BlockContext::BlockCard(_))) {
+            return false;
+        }
+        if let Some(BlockContext::BlockCard(attrs)) = state.stack.pop() {
+            // The card is described by its attributes, so any text inside it is dropped
+            state.current_text.clear();
+            ADFBuilder::push_node_block_to_parent(state, AdfBlockNode::BlockCard { attrs });
+        }
+        true
+    }) as HandlerFn
+}
+
 pub(crate) fn local_data_start_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, element: Element| {
         let local_id = element
@@ -272,16 +351,16 @@ pub(crate) fn status_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn status_end_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
-        if let Some(BlockContext::CustomBlock(CustomBlockType::Status, _, attrs)) =
-            state.stack.pop()
-        {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        let frame = state.stack.pop();
+        if let Some(BlockContext::CustomBlock(CustomBlockType::Status, _, attrs)) = frame {
             let text = state.current_text.trim().to_string();
             state.current_text.clear();
             let color = attrs
                 .get("style")
                 .and_then(|style| extract_style(style, "background-color"));
-            let local_id = attrs.get("aria-label").map(|id| id.to_string());
+            // `aria-label` holds the status text for assistive technology, not the id
+            let local_id = attrs.get("data-local-id").map(|id| id.to_string());
             ADFBuilder::push_node_to_parent(
                 state,
                 AdfNode::Status {
@@ -293,14 +372,25 @@ pub(crate) fn status_end_handler() -> HandlerFn {
                 },
             );
         } else {
-            panic!("Mismatched status close tag");
+            state.fail_mismatched_close(&element.tag, frame);
         }
         true
     }) as HandlerFn
 }
 
+fn in_status(state: &ADFBuilderState) -> bool {
+    matches!(
+        state.stack.last(),
+        Some(BlockContext::CustomBlock(CustomBlockType::Status, _, _))
+    )
+}
+
 pub(crate) fn emoji_start_handler() -> HandlerFn {
     Box::new(|state: &mut ADFBuilderState, element: Element| {
+        // Inside a status the emoji is kept as part of the status text
+        if in_status(state) {
+            return true;
+        }
         ADFBuilder::flush_text(state);
 
         let mut node_attrs = HashMap::new();
@@ -316,9 +406,12 @@ pub(crate) fn emoji_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn emoji_end_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
-        if let Some(BlockContext::CustomBlock(CustomBlockType::Emoji, _, attrs)) = state.stack.pop()
-        {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        if in_status(state) {
+            return true;
+        }
+        let frame = state.stack.pop();
+        if let Some(BlockContext::CustomBlock(CustomBlockType::Emoji, _, attrs)) = frame {
             let short_name = if let Some(value) = attrs.get("aria-alt") {
                 value.clone()
             } else {
@@ -332,12 +425,13 @@ pub(crate) fn emoji_end_handler() -> HandlerFn {
                 AdfNode::Emoji {
                     attrs: EmojiAttrs {
                         text: Some(text),
+                        id: attrs.get("data-emoji-id").cloned(),
                         short_name,
                     },
                 },
             );
         } else {
-            panic!("Mismatched emoji close tag");
+            state.fail_mismatched_close(&element.tag, frame);
         }
         true
     }) as HandlerFn
@@ -19,7 +19,7 @@ pub(crate) fn decision_start_handler() -> HandlerFn {
                     break inner;
                 }
                 None => {
-                    panic!("No list item found in stack");
+                    unreachable!("A list item was found in the stack");
                 }
                 _ => {
                     // continue
@@ -29,13 +29,11 @@ pub(crate) fn decision_start_handler() -> HandlerFn {
 
         let mut nodes = vec![];
         for node in inner {
-            match node {
-                AdfBlockNode::Paragraph {
-                    content: Some(para_nodes),
-                } => {
-                    nodes.extend(para_nodes);
-                }
-                _ => {}
+            if let AdfBlockNode::Paragraph {
+                content: Some(para_nodes),
+            } = node
+            {
+                nodes.extend(para_nodes);
             };
         }
 
@@ -44,7 +42,7 @@ pub(crate) fn decision_start_handler() -> HandlerFn {
             .iter()
             .find(|attr| attr.name.local.as_ref() == "id")
             .map(|id| id.value.to_string())
-            .unwrap_or_default();
+            .unwrap_or_else(|| state.generate_local_id());
         let decision_item = BlockContext::DecisionItem(nodes, local_id);
         state.stack.push(decision_item);
         true
@@ -1,10 +1,10 @@
-use super::{ADFBuilderState, BlockContext, CustomBlockType, Element, MediaBlockType};
+use super::{ADFBuilderState, BlockContext, CustomBlockType, Element, MediaBlockType, find_attr};
 use crate::{
     adf::adf_types::{
         AdfBlockNode, AdfNode, LinkMark, MediaAttrs, MediaDataType, MediaMark, MediaNode,
         MediaSingleAttrs, MediaType,
     },
-    html_to_adf::{ADFBuilder, HandlerFn, extract_style},
+    html_to_adf::{ADFBuilder, AdfConvertError, HandlerFn, extract_style},
 };
 
 pub(crate) fn media_single_start_handler() -> HandlerFn {
@@ -24,23 +24,26 @@ pub(crate) fn media_single_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn media_single_end_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
         ADFBuilder::flush_text(state);
-        if let Some(BlockContext::MediaBlock(MediaBlockType::MediaSingle, nodes, attrs)) =
-            state.stack.pop()
-        {
+        let frame = state.stack.pop();
+        if let Some(BlockContext::MediaBlock(MediaBlockType::MediaSingle, nodes, attrs)) = frame {
             ADFBuilder::push_node_block_to_parent(
                 state,
                 AdfBlockNode::MediaSingle {
                     attrs: MediaSingleAttrs {
                         layout: attrs
                             .get("data-layout")
-                            .expect("Required attribute data-layout")
-                            .to_string(),
+                            .cloned()
+                            .unwrap_or_else(|| "center".to_string()),
+                        width: attrs.get("data-width").and_then(|width| width.parse().ok()),
+                        width_type: attrs.get("data-width-type").cloned(),
                     },
                     content: nodes,
                 },
             );
+        } else {
+            state.fail_mismatched_close(&element.tag, frame);
         }
         true
     }) as HandlerFn
@@ -63,15 +66,16 @@ pub(crate) fn media_group_start_handler() -> HandlerFn {
 }
 
 pub(crate) fn media_group_end_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, _element: Element| {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
         ADFBuilder::flush_text(state);
-        if let Some(BlockContext::MediaBlock(MediaBlockType::MediaGroup, nodes, _)) =
-            state.stack.pop()
-        {
+        let frame = state.stack.pop();
+        if let Some(BlockContext::MediaBlock(MediaBlockType::MediaGroup, nodes, _)) = frame {
             ADFBuilder::push_node_block_to_parent(
                 state,
                 AdfBlockNode::MediaGroup { content: nodes },
             );
+        } else {
+            state.fail_mismatched_close(&element.tag, frame);
         }
         true
     }) as HandlerFn
@@ -88,68 +92,126 @@ impl ADFBuilder {
             BlockContext::MediaBlock(_, nodes, _) => {
                 nodes.push(node);
             }
-            _ => {
-                panic!("Expected MediaBlock on stack");
+            frame => {
+                let parent = frame.describe();
+                let error = state.unexpected_parent("media", parent);
+                state.fail(error);
             }
         }
     }
 }
 
-pub(crate) fn media_and_inline_card_start_handler() -> HandlerFn {
-    Box::new(|state, element| {
-        if matches!(state.stack.last(), Some(BlockContext::MediaBlock { .. })) {
-            let id = element
-                .attrs
-                .iter()
-                .find(|attr| attr.name.local.as_ref() == "data-media-id")
-                .map(|attr| attr.value.as_ref().to_string())
-                .unwrap_or_default();
-
-            let collection = element
-                .attrs
-                .iter()
-                .find(|attr| attr.name.local.as_ref() == "data-collection")
-                .map(|attr| attr.value.as_ref().to_string())
-                .unwrap_or_default();
-
-            let alt = element
-                .attrs
-                .iter()
-                .find(|attr| attr.name.local.as_ref() == "alt")
-                .map(|attr| attr.value.as_ref().to_string());
+/// Reads the media attributes carried by an `<img>` or media `<a>`.
+fn media_attrs(element: &Element, type_: MediaDataType) -> MediaAttrs {
+    let style = find_attr(element, "style");
+    let dimension = |name: &str| {
+        style
+            .and_then(|style| extract_style(style, name))
+            .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok())
+    };
 
-            let style = element
-                .attrs
-                .iter()
-                .find(|attr| attr.name.local.as_ref() == "style");
+    MediaAttrs {
+        // Link media are anchors, which keep their alt text in a data attribute
+        alt: find_attr(element, "alt")
+            .or_else(|| find_attr(element, "data-alt"))
+            .map(str::to_string),
+        collection: find_attr(element, "data-collection")
+            .unwrap_or_default()
+            .to_string(),
+        id: find_attr(element, "data-media-id")
+            .unwrap_or_default()
+            .to_string(),
+        type_,
+        width: dimension("width"),
+        height: dimension("height"),
+    }
+}
 
-            let width = style
-                .and_then(|style| extract_style(&style.value, "width"))
-                .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok());
+/// Reads the marks of an `<img>`: its source as a link and its border style as a border.
+fn media_marks(element: &Element) -> Option<Vec<MediaMark>> {
+    let mut marks = vec![];
+    let mut style_border = None;
+    let mut border_color = None;
+    let mut border_size = None;
+    for attr in &element.attrs {
+        match attr.name.local.as_ref() {
+            "src" => marks.insert(
+                0,
+                MediaMark::Link(LinkMark {
+                    href: attr.value.to_string(),
+                    ..Default::default()
+                }),
+            ),
+            "data-border-color" => border_color = Some(attr.value.to_string()),
+            "data-border-size" => border_size = attr.value.parse().ok(),
+            "style" => {
+                // Rendered as `border: {size}px solid {color}`
+                let border = extract_style(&attr.value, "border");
+                let mut parts = border.as_deref().unwrap_or_default().splitn(3, ' ');
+                if let (Some(size), Some(_), Some(color)) =
+                    (parts.next(), parts.next(), parts.next())
+                    && let Ok(size) = size.trim_end_matches("px").parse()
+                {
+                    style_border = Some(MediaMark::Border {
+                        color: color.trim().to_string(),
+                        size,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    // The data attributes are exact, so they win over a style that may have been edited
+    let border = match (border_color, border_size) {
+        (Some(color), Some(size)) => Some(MediaMark::Border { color, size }),
+        _ => style_border,
+    };
+    marks.extend(border);
+    if marks.is_empty() { None } else { Some(marks) }
+}
 
-            let height = style
-                .and_then(|style| extract_style(&style.value, "height"))
-                .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok());
+/// Whether the innermost open block can take an inline node, directly or through a paragraph.
+fn accepts_inline_node(state: &ADFBuilderState) -> bool {
+    matches!(
+        state.stack.last(),
+        Some(
+            BlockContext::Paragraph(_)
+                | BlockContext::Heading(..)
+                | BlockContext::Document(_)
+                | BlockContext::Blockquote(_)
+                | BlockContext::ListItem(_)
+                | BlockContext::TableBlockCell(..)
+                | BlockContext::TableBlockHeader(..)
+                | BlockContext::CustomBlock(
+                    CustomBlockType::Div
+                        | CustomBlockType::Expand
+                        | CustomBlockType::NestedExpand
+                        | CustomBlockType::Panel,
+                    ..
+                )
+        )
+    )
+}
 
+pub(crate) fn media_and_inline_card_start_handler() -> HandlerFn {
+    Box::new(|state, element| {
+        if matches!(state.stack.last(), Some(BlockContext::MediaBlock { .. })) {
             if element.tag == "a" {
-                let type_ = MediaDataType::Link;
-                let href = element
+                // A link without a target has nothing to point the media at
+                let Some(href) = element
                     .attrs
                     .iter()
                     .find(|attr| attr.name.local.as_ref() == "href")
                     .map(|attr| attr.value.as_ref().to_string())
-                    .expect("a tag should have href");
+                else {
+                    return false;
+                };
 
+                // The anchor's text is the link's title, read when the anchor closes
+                state.current_text.clear();
                 let media_node = MediaNode {
                     media_type: MediaType::Media,
-                    attrs: MediaAttrs {
-                        alt,
-                        collection,
-                        id,
-                        type_,
-                        width,
-                        height,
-                    },
+                    attrs: media_attrs(&element, MediaDataType::Link),
                     marks: Some(vec![MediaMark::Link(LinkMark {
                         href,
                         ..Default::default()
@@ -159,28 +221,38 @@ pub(crate) fn media_and_inline_card_start_handler() -> HandlerFn {
                 ADFBuilder::push_media_node_to_parent(state, media_node);
                 return true;
             } else if element.tag == "img" {
-                let type_ = MediaDataType::File;
-
                 let media_node = MediaNode {
                     media_type: MediaType::Media,
-                    attrs: MediaAttrs {
-                        alt,
-                        collection,
-                        id,
-                        type_,
-                        width,
-                        height,
-                    },
-                    marks: None,
+                    attrs: media_attrs(&element, MediaDataType::File),
+                    marks: media_marks(&element),
                 };
 
                 ADFBuilder::push_media_node_to_parent(state, media_node);
                 return true;
             } else {
-                panic!("Unknown media type {}", element.tag);
+                state.fail(AdfConvertError::InvalidElement {
+                    tag: element.tag,
+                    reason: "not a media element".to_string(),
+                });
+                return true;
             };
         }
 
+        // --- INLINE MEDIA HANDLING ---
+        // An image in running text stays inline, with its source kept as a link mark the same
+        // way file media render theirs
+        if element.tag == "img" && accepts_inline_node(state) {
+            ADFBuilder::flush_text(state);
+            ADFBuilder::push_node_to_parent(
+                state,
+                AdfNode::MediaInline {
+                    attrs: media_attrs(&element, MediaDataType::File),
+                    marks: media_marks(&element),
+                },
+            );
+            return true;
+        }
+
         // --- INLINE CARD HANDLING ---
         if element.tag == "a" {
             let has_inline_card = element
@@ -208,7 +280,11 @@ pub(crate) fn media_and_inline_card_start_handler() -> HandlerFn {
                     ));
                     return true;
                 } else {
-                    panic!("Inline card without href");
+                    state.fail(AdfConvertError::InvalidElement {
+                        tag: element.tag,
+                        reason: "inline card without an href".to_string(),
+                    });
+                    return true;
                 }
             }
         }
@@ -222,8 +298,29 @@ pub(crate) fn inline_card_end_handler() -> HandlerFn {
             return false;
         }
 
-        let attrs = match state.stack.last() {
+        let attrs = match state.stack.last_mut() {
             Some(BlockContext::CustomBlock(CustomBlockType::InlineCard, _, attrs)) => attrs.clone(),
+            Some(BlockContext::MediaBlock(_, nodes, _)) => {
+                let text = std::mem::take(&mut state.current_text);
+                if let Some(MediaNode {
+                    attrs:
+                        MediaAttrs {
+                            type_: MediaDataType::Link,
+                            ..
+                        },
+                    marks: Some(marks),
+                    ..
+                }) = nodes.last_mut()
+                    && let Some(MediaMark::Link(link)) = marks.first_mut()
+                {
+                    // Untitled links show their href instead
+                    let text = text.trim();
+                    if !text.is_empty() && text != link.href {
+                        link.title = Some(text.to_string());
+                    }
+                }
+                return true;
+            }
             _ => {
                 return false;
             }
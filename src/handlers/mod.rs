@@ -16,9 +16,12 @@ pub(crate) use table::*;
 pub(crate) use tasks::*;
 
 use crate::adf::adf_types::{
-    AdfBlockNode, AdfMark, AdfNode, DecisionItem, ListItem, LocalId, MediaNode, TableRow,
-    TableRowEntry, TaskItem, TaskItemState,
+    AdfBlockNode, AdfMark, AdfNode, BlockCardAttrs, DecisionItem, ListItem, LocalId, MediaNode,
+    TableAttrs, TableCellAttrs, TableRow, TableRowEntry, TaskItem, TaskItemState,
 };
+use crate::html_to_adf::AdfConvertError;
+use crate::options::ConvertOptions;
+use strum_macros::IntoStaticStr;
 
 #[derive(Debug)]
 pub struct Element {
@@ -27,12 +30,80 @@ pub struct Element {
     pub self_closing: bool,
 }
 
+/// The value of the element's `name` attribute, if it has one.
+pub(crate) fn find_attr<'a>(element: &'a Element, name: &str) -> Option<&'a str> {
+    element
+        .attrs
+        .iter()
+        .find(|attr| attr.name.local.as_ref() == name)
+        .map(|attr| attr.value.as_ref())
+}
+
 pub struct ADFBuilderState {
     pub stack: Vec<BlockContext>,
     pub mark_stack: Vec<AdfMark>,
     pub current_text: String,
     pub custom_block_id: Option<LocalId>,
     pub custom_block_tag: Option<String>,
+    pub options: ConvertOptions,
+    /// How many local ids have been generated for nodes that arrived without one.
+    pub generated_local_ids: u64,
+    /// The first error hit while building, after which the rest of the input is ignored.
+    pub error: Option<AdfConvertError>,
+    /// Set where raw HTML was dropped or split out of a paragraph, or a double break split one,
+    /// so the whitespace and hard breaks opening the next text are trimmed rather than doubled
+    /// or left at the paragraph's edge.
+    pub trim_text_seam: bool,
+}
+
+impl ADFBuilderState {
+    /// Generates a UUID-shaped local id, unique within the document being built.
+    pub fn generate_local_id(&mut self) -> String {
+        self.generated_local_ids += 1;
+        format!("00000000-0000-4000-8000-{:012x}", self.generated_local_ids)
+    }
+
+    /// Records an error, keeping the first one since later errors tend to follow from it.
+    pub fn fail(&mut self, error: AdfConvertError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// The open blocks, outermost first, for error reports.
+    pub fn stack_summary(&self) -> Vec<String> {
+        self.stack.iter().map(BlockContext::describe).collect()
+    }
+
+    /// An error for a `child` that can't go in `parent`, described with
+    /// [`BlockContext::describe`].
+    pub fn unexpected_parent(&self, child: &str, parent: String) -> AdfConvertError {
+        AdfConvertError::UnexpectedParentFor {
+            child: child.to_string(),
+            parent,
+            stack: self.stack_summary(),
+        }
+    }
+
+    pub fn unexpected_close(&self, child: &BlockContext) -> AdfConvertError {
+        AdfConvertError::UnexpectedClose {
+            child: child.describe(),
+            stack: self.stack_summary(),
+        }
+    }
+
+    pub fn mismatched_close(&self, tag: &str) -> AdfConvertError {
+        AdfConvertError::MismatchedClose {
+            tag: tag.to_string(),
+            stack: self.stack_summary(),
+        }
+    }
+
+    /// Fails on an end tag that doesn't match the block it popped, putting the block back so the
+    /// error shows the stack as the tag found it.
+    pub fn fail_mismatched_close(&mut self, tag: &str, popped: Option<BlockContext>) {
+        self.stack.extend(popped);
+        let error = self.mismatched_close(tag);
+        self.fail(error);
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -62,23 +133,44 @@ pub enum ListItemType {
     TaskItem(TaskItem),
 }
 
-#[derive(Debug)]
+impl ListItemType {
+    /// Converts the item to a plain list item, keeping its content as a paragraph.
+    pub fn into_list_item(self) -> ListItem {
+        match self {
+            ListItemType::ListItem(item) => item,
+            ListItemType::TaskItem(item) => {
+                ListItem::new(vec![AdfBlockNode::paragraph_from(item.unwrap().0)])
+            }
+            ListItemType::DecisionItem(item) => {
+                ListItem::new(vec![AdfBlockNode::paragraph_from(item.unwrap().0)])
+            }
+        }
+    }
+}
+
+#[derive(Debug, IntoStaticStr)]
 pub enum BlockContext {
     Document(Vec<AdfBlockNode>),
     Blockquote(Vec<AdfBlockNode>),
-    CodeBlock(Vec<String>),
+    /// Lines of code and the language, if any
+    CodeBlock(Vec<String>, Option<String>),
     CustomBlock(CustomBlockType, Vec<AdfBlockNode>, HashMap<String, String>),
     MediaBlock(MediaBlockType, Vec<MediaNode>, HashMap<String, String>),
-    TableBlock(Vec<TableRow>),
+    /// A block card, filled in from its data source and view children
+    BlockCard(BlockCardAttrs),
+    /// A table's rows, whether its `<thead>` is open, and its attributes
+    TableBlock(Vec<TableRow>, bool, Option<TableAttrs>),
     TableRowBlock(Vec<TableRowEntry>),
-    TableBlockCell(Vec<AdfBlockNode>),
-    TableBlockHeader(Vec<AdfBlockNode>),
+    TableBlockCell(Vec<AdfBlockNode>, Option<TableCellAttrs>),
+    TableBlockHeader(Vec<AdfBlockNode>, Option<TableCellAttrs>),
     Heading(u8, Vec<AdfNode>),
     Summary(Vec<AdfNode>),
     Paragraph(Vec<AdfNode>),
     PendingList {
         nodes: Vec<ListItemType>,
         ordered: bool,
+        /// The number an ordered list starts at, from its `start` attribute
+        start: Option<u32>,
         local_id: Option<String>,
         local_tag: Option<String>,
     },
@@ -86,3 +178,15 @@ pub enum BlockContext {
     TaskItem(Vec<AdfNode>, TaskItemState, String),
     DecisionItem(Vec<AdfNode>, String),
 }
+
+impl BlockContext {
+    /// The kind of block, without its content, e.g. `CustomBlock(Panel)`.
+    pub fn describe(&self) -> String {
+        let name: &'static str = self.into();
+        match self {
+            BlockContext::CustomBlock(block_ty, ..) => format!("{name}({block_ty:?})"),
+            BlockContext::MediaBlock(block_ty, ..) => format!("{name}({block_ty:?})"),
+            _ => name.to_string(),
+        }
+    }
+}
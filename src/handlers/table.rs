@@ -1,65 +1,168 @@
 use crate::{
-    adf::adf_types::{AdfBlockNode, TableRow, TableRowEntry},
-    html_to_adf::{ADFBuilder, HandlerFn},
+    adf::adf_types::{AdfBlockNode, TableAttrs, TableCellAttrs, TableRow, TableRowEntry},
+    html_to_adf::{ADFBuilder, HandlerFn, extract_style},
 };
 
-use super::{ADFBuilderState, BlockContext};
+use super::{ADFBuilderState, BlockContext, Element, find_attr};
+
+/// Reads the cell attributes carried by a `<td>`/`<th>`: its spans, column widths and
+/// background color. A cell without any gives `None`.
+fn table_cell_attrs(element: &Element) -> Option<TableCellAttrs> {
+    // A span of 1 is the default, so it isn't kept
+    let span = |name: &str| {
+        find_attr(element, name)
+            .and_then(|span| span.trim().parse::<u32>().ok())
+            .filter(|&span| span > 1)
+    };
+    let attrs = TableCellAttrs {
+        background: find_attr(element, "style")
+            .and_then(|style| extract_style(&style.to_ascii_lowercase(), "background-color"))
+            .map(|color| normalize_background(&color)),
+        colspan: span("colspan"),
+        colwidth: find_attr(element, "data-colwidth").and_then(|widths| {
+            widths
+                .split(',')
+                .map(|width| width.trim().parse().ok())
+                .collect()
+        }),
+        rowspan: span("rowspan"),
+    };
+    (attrs != TableCellAttrs::default()).then_some(attrs)
+}
+
+/// ADF stores cell backgrounds as lowercase `#rrggbb`, so shorthand hex and `rgb()` colors are
+/// expanded to match. Anything else, such as a named color, is kept as-is.
+fn normalize_background(color: &str) -> String {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#')
+        && hex.len() == 3
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return hex.chars().fold("#".to_string(), |mut expanded, c| {
+            expanded.push(c);
+            expanded.push(c);
+            expanded
+        });
+    }
+    if let Some(channels) = color
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<u8> = channels
+            .split(',')
+            .filter_map(|channel| channel.trim().parse().ok())
+            .collect();
+        if let [r, g, b] = channels[..] {
+            return format!("#{r:02x}{g:02x}{b:02x}");
+        }
+    }
+    color.to_string()
+}
+
+/// Reads the table attributes carried by a `<table>`. A table without any gives `None`.
+fn table_attrs(element: &Element) -> Option<TableAttrs> {
+    let attrs = TableAttrs {
+        is_number_column_enabled: find_attr(element, "data-number-column")
+            .and_then(|enabled| enabled.parse().ok()),
+        layout: find_attr(element, "data-layout").map(str::to_string),
+        width: find_attr(element, "data-width").and_then(|width| width.trim().parse().ok()),
+        display_mode: find_attr(element, "data-display-mode").map(str::to_string),
+    };
+    (attrs != TableAttrs::default()).then_some(attrs)
+}
 
 pub(crate) fn table_start_handler() -> HandlerFn {
-    Box::new(|state, _element| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
-        state.stack.push(BlockContext::TableBlock(vec![]));
+        let attrs = table_attrs(&element);
+        state
+            .stack
+            .push(BlockContext::TableBlock(vec![], false, attrs));
         true
     })
 }
 
-pub(crate) fn table_section_start_handler() -> HandlerFn {
-    Box::new(|_state, _element| true)
+/// Tracks whether rows belong to the table head, where every cell is a header.
+pub(crate) fn table_section_start_handler(in_head: bool) -> HandlerFn {
+    Box::new(move |state, _element| {
+        ADFBuilder::set_table_head(state, in_head);
+        true
+    })
 }
 
 pub(crate) fn table_section_end_handler() -> HandlerFn {
-    Box::new(|_state, _element| true)
+    Box::new(|state, _element| {
+        ADFBuilder::set_table_head(state, false);
+        true
+    })
 }
 
 pub(crate) fn table_row_start_handler() -> HandlerFn {
     Box::new(|state, _element| {
         ADFBuilder::flush_text(state);
+        // A row that was never closed, such as an implied one, ends where the next row starts
+        if let Some(BlockContext::TableRowBlock(_)) = state.stack.last() {
+            ADFBuilder::close_current_table_row(state);
+        }
         state.stack.push(BlockContext::TableRowBlock(vec![]));
         true
     })
 }
 
 pub(crate) fn table_cell_start_handler() -> HandlerFn {
-    Box::new(|state, _element| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
-        state.stack.push(BlockContext::TableBlockCell(vec![]));
+        ADFBuilder::ensure_table_row(state);
+        let attrs = table_cell_attrs(&element);
+        let in_head = state.stack.iter().rev().find_map(|ctx| match ctx {
+            BlockContext::TableBlock(_, in_head, _) => Some(*in_head),
+            _ => None,
+        });
+        // Cells in the table head are headers even when written as `<td>`
+        if in_head == Some(true) {
+            state
+                .stack
+                .push(BlockContext::TableBlockHeader(vec![], attrs));
+        } else {
+            state
+                .stack
+                .push(BlockContext::TableBlockCell(vec![], attrs));
+        }
         true
     })
 }
 
 pub(crate) fn table_header_start_handler() -> HandlerFn {
-    Box::new(|state, _element| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
-        state.stack.push(BlockContext::TableBlockHeader(vec![]));
+        ADFBuilder::ensure_table_row(state);
+        let attrs = table_cell_attrs(&element);
+        state
+            .stack
+            .push(BlockContext::TableBlockHeader(vec![], attrs));
         true
     })
 }
 
 pub(crate) fn table_end_handler() -> HandlerFn {
-    Box::new(|state, _element| {
+    Box::new(|state, element| {
         ADFBuilder::flush_text(state);
-        if let Some(BlockContext::TableBlock(rows)) = state.stack.pop() {
+        if let Some(BlockContext::TableRowBlock(_)) = state.stack.last() {
+            ADFBuilder::close_current_table_row(state);
+        }
+        let frame = state.stack.pop();
+        if let Some(BlockContext::TableBlock(rows, _, attrs)) = frame {
             ADFBuilder::push_node_block_to_parent(
                 state,
                 AdfBlockNode::Table {
-                    attrs: None,
+                    attrs,
                     content: rows,
                 },
             );
-            true
         } else {
-            false
+            state.fail_mismatched_close(&element.tag, frame);
         }
+        true
     })
 }
 
@@ -74,7 +177,11 @@ pub(crate) fn table_row_end_handler() -> HandlerFn {
 pub(crate) fn table_cell_end_handler() -> HandlerFn {
     Box::new(|state, _element| {
         ADFBuilder::flush_text(state);
-        ADFBuilder::close_current_table_cell(state);
+        if let Some(BlockContext::TableBlockHeader(..)) = state.stack.last() {
+            ADFBuilder::close_current_table_header(state);
+        } else {
+            ADFBuilder::close_current_table_cell(state);
+        }
         true
     })
 }
@@ -88,51 +195,92 @@ pub(crate) fn table_header_end_handler() -> HandlerFn {
 }
 
 impl ADFBuilder {
+    fn set_table_head(state: &mut ADFBuilderState, in_head: bool) {
+        let table = state.stack.iter_mut().rev().find_map(|ctx| match ctx {
+            BlockContext::TableBlock(_, in_table_head, _) => Some(in_table_head),
+            _ => None,
+        });
+        if let Some(in_table_head) = table {
+            *in_table_head = in_head;
+        }
+    }
+
+    /// Opens an implied row for a cell that appears directly inside a table, as browsers do.
+    fn ensure_table_row(state: &mut ADFBuilderState) {
+        if let Some(BlockContext::TableBlock(..)) = state.stack.last() {
+            state.stack.push(BlockContext::TableRowBlock(vec![]));
+        }
+    }
+
     fn push_row_to_table(state: &mut ADFBuilderState, row: TableRow) {
-        if let Some(BlockContext::TableBlock(rows)) = state.stack.last_mut() {
-            rows.push(row);
-        } else {
-            panic!("No table block found in stack");
+        match state.stack.last_mut() {
+            Some(BlockContext::TableBlock(rows, ..)) => rows.push(row),
+            parent => {
+                let parent = parent.map_or("nothing".to_string(), |parent| parent.describe());
+                let error = state.unexpected_parent("TableRowBlock", parent);
+                state.fail(error);
+            }
         }
     }
 
-    fn push_cell_to_row(state: &mut ADFBuilderState, cell_nodes: Vec<AdfBlockNode>) {
-        if let Some(BlockContext::TableRowBlock(cells)) = state.stack.last_mut() {
-            cells.push(TableRowEntry::new_table_cell(cell_nodes, None));
-        } else {
-            panic!("No table row block found in stack");
+    fn push_cell_to_row(
+        state: &mut ADFBuilderState,
+        cell_nodes: Vec<AdfBlockNode>,
+        attrs: Option<TableCellAttrs>,
+    ) {
+        match state.stack.last_mut() {
+            Some(BlockContext::TableRowBlock(cells)) => {
+                cells.push(TableRowEntry::new_table_cell(cell_nodes, attrs));
+            }
+            parent => {
+                let parent = parent.map_or("nothing".to_string(), |parent| parent.describe());
+                let error = state.unexpected_parent("TableBlockCell", parent);
+                state.fail(error);
+            }
         }
     }
 
-    fn push_header_to_row(state: &mut ADFBuilderState, cell_nodes: Vec<AdfBlockNode>) {
-        if let Some(BlockContext::TableRowBlock(cells)) = state.stack.last_mut() {
-            cells.push(TableRowEntry::new_table_header(cell_nodes, None));
-        } else {
-            panic!("No table row block found in stack");
+    fn push_header_to_row(
+        state: &mut ADFBuilderState,
+        cell_nodes: Vec<AdfBlockNode>,
+        attrs: Option<TableCellAttrs>,
+    ) {
+        match state.stack.last_mut() {
+            Some(BlockContext::TableRowBlock(cells)) => {
+                cells.push(TableRowEntry::new_table_header(cell_nodes, attrs));
+            }
+            parent => {
+                let parent = parent.map_or("nothing".to_string(), |parent| parent.describe());
+                let error = state.unexpected_parent("TableBlockHeader", parent);
+                state.fail(error);
+            }
         }
     }
 
     fn close_current_table_row(state: &mut ADFBuilderState) {
-        if let Some(BlockContext::TableRowBlock(cells)) = state.stack.pop() {
-            Self::push_row_to_table(state, TableRow::new(cells));
-        } else {
-            panic!("No table row block found in stack");
+        match state.stack.pop() {
+            Some(BlockContext::TableRowBlock(cells)) => {
+                Self::push_row_to_table(state, TableRow::new(cells));
+            }
+            frame => state.fail_mismatched_close("tr", frame),
         }
     }
 
     fn close_current_table_cell(state: &mut ADFBuilderState) {
-        if let Some(BlockContext::TableBlockCell(nodes)) = state.stack.pop() {
-            Self::push_cell_to_row(state, nodes);
-        } else {
-            panic!("No table cell block found in stack");
+        match state.stack.pop() {
+            Some(BlockContext::TableBlockCell(nodes, attrs)) => {
+                Self::push_cell_to_row(state, nodes, attrs);
+            }
+            frame => state.fail_mismatched_close("td", frame),
         }
     }
 
     fn close_current_table_header(state: &mut ADFBuilderState) {
-        if let Some(BlockContext::TableBlockHeader(nodes)) = state.stack.pop() {
-            Self::push_header_to_row(state, nodes);
-        } else {
-            panic!("No table header block found in stack");
+        match state.stack.pop() {
+            Some(BlockContext::TableBlockHeader(nodes, attrs)) => {
+                Self::push_header_to_row(state, nodes, attrs);
+            }
+            frame => state.fail_mismatched_close("th", frame),
         }
     }
 }
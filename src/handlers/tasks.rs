@@ -1,83 +1,127 @@
 use super::{ADFBuilderState, BlockContext, Element};
 use crate::{
     adf::adf_types::{AdfBlockNode, TaskItemState},
-    html_to_adf::HandlerFn,
+    html_to_adf::{AdfConvertError, HandlerFn},
 };
 
 pub(crate) fn task_item_start_handler() -> HandlerFn {
-    Box::new(|state: &mut ADFBuilderState, element: Element| {
-        let has_list_item = state
-            .stack
-            .iter()
-            .any(|item| matches!(item, BlockContext::ListItem(_)));
+    Box::new(start_task_item)
+}
 
-        if !has_list_item {
+/// Starts a task item from a checkbox at the start of a list item, as Markdown task lists
+/// render them. A checkbox anywhere else is kept as `[x]` or `[ ]` text.
+pub(crate) fn checkbox_start_handler() -> HandlerFn {
+    Box::new(|state: &mut ADFBuilderState, element: Element| {
+        let is_checkbox = element.attrs.iter().any(|attr| {
+            attr.name.local.as_ref() == "type" && attr.value.eq_ignore_ascii_case("checkbox")
+        });
+        if !is_checkbox {
             return false;
         }
+        if starts_list_item(state) {
+            return start_task_item(state, element);
+        }
+        let checked = element
+            .attrs
+            .iter()
+            .any(|attr| attr.name.local.as_ref() == "checked");
+        state
+            .current_text
+            .push_str(if checked { "[x]" } else { "[ ]" });
+        false
+    })
+}
+
+/// Whether nothing has been written to the innermost list item yet, either directly or in a
+/// paragraph opened inside it, as loose Markdown lists render it.
+fn starts_list_item(state: &ADFBuilderState) -> bool {
+    if !state.current_text.trim().is_empty() {
+        return false;
+    }
+    match state.stack.as_slice() {
+        [.., BlockContext::ListItem(nodes)] => nodes.is_empty(),
+        [
+            ..,
+            BlockContext::ListItem(nodes),
+            BlockContext::Paragraph(inline),
+        ] => nodes.is_empty() && inline.is_empty(),
+        _ => false,
+    }
+}
 
-        let inner = loop {
-            let item = state.stack.pop();
-            match item {
-                Some(BlockContext::ListItem(inner)) => {
-                    break inner;
-                }
-                None => {
-                    panic!("No list item found in stack");
-                }
-                _ => {
-                    // continue
-                }
+fn start_task_item(state: &mut ADFBuilderState, element: Element) -> bool {
+    let has_list_item = state
+        .stack
+        .iter()
+        .any(|item| matches!(item, BlockContext::ListItem(_)));
+
+    if !has_list_item {
+        return false;
+    }
+
+    let inner = loop {
+        let item = state.stack.pop();
+        match item {
+            Some(BlockContext::ListItem(inner)) => {
+                break inner;
+            }
+            None => {
+                unreachable!("A list item was found in the stack");
             }
+            _ => {
+                // continue
+            }
+        }
+    };
+
+    let mut nodes = vec![];
+    for node in inner {
+        if let AdfBlockNode::Paragraph {
+            content: Some(para_nodes),
+        } = node
+        {
+            nodes.extend(para_nodes);
         };
+    }
 
-        let mut nodes = vec![];
-        for node in inner {
-            match node {
-                AdfBlockNode::Paragraph {
-                    content: Some(para_nodes),
-                } => {
-                    nodes.extend(para_nodes);
-                }
-                _ => {}
+    if let Some(input_type) = element
+        .attrs
+        .iter()
+        .find(|attr| attr.name.local.as_ref() == "type")
+    {
+        if input_type.value.eq_ignore_ascii_case("checkbox") {
+            let checked = element
+                .attrs
+                .iter()
+                .any(|attr| attr.name.local.as_ref() == "checked");
+            let item_state = if checked {
+                TaskItemState::Done
+            } else {
+                TaskItemState::Todo
             };
-        }
 
-        if let Some(input_type) = element
-            .attrs
-            .iter()
-            .find(|attr| attr.name.local.as_ref() == "type")
-        {
-            if input_type.value.eq_ignore_ascii_case("checkbox") {
-                let checked = element
+            let task_item = BlockContext::TaskItem(
+                nodes,
+                item_state,
+                element
                     .attrs
                     .iter()
-                    .any(|attr| attr.name.local.as_ref() == "checked");
-                let item_state = if checked {
-                    TaskItemState::Done
-                } else {
-                    TaskItemState::Todo
-                };
-
-                let task_item = BlockContext::TaskItem(
-                    nodes,
-                    item_state,
-                    element
-                        .attrs
-                        .iter()
-                        .find(|attr| attr.name.local.as_ref() == "id")
-                        .map(|id| id.value.to_string())
-                        .unwrap_or_default(),
-                );
-                state.stack.push(task_item);
-            } else {
-                panic!(
-                    "Unsupported type attribute for task item: {}",
-                    input_type.value
-                );
-            }
+                    .find(|attr| attr.name.local.as_ref() == "id")
+                    .map(|id| id.value.to_string())
+                    .unwrap_or_else(|| state.generate_local_id()),
+            );
+            state.stack.push(task_item);
         } else {
-            panic!("No type attribute found for task item");
+            state.fail(AdfConvertError::InvalidElement {
+                tag: element.tag,
+                reason: format!("unsupported task item type {}", input_type.value),
+            });
         }
-        true
-    })
+    } else {
+        state.fail(AdfConvertError::InvalidElement {
+            tag: element.tag,
+            reason: "task item without a type".to_string(),
+        });
+    }
+    true
 }
@@ -117,7 +117,7 @@ pub use html::*;
 
 use std::borrow::Cow;
 use std::fmt::Write;
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
 
 /// A buffer for writing HTML into.
 pub struct Buffer {
@@ -185,8 +185,11 @@ impl Buffer {
 
     /// Closes all open tags and returns the buffer's contents.
     pub fn finish(self) -> String {
-        let mutex = Arc::try_unwrap(self.ctx).ok().unwrap();
-        let mut ctx = mutex.into_inner().unwrap();
+        // Nodes only hold weak references, so the buffer normally owns the sole strong one
+        let mut ctx = match Arc::try_unwrap(self.ctx) {
+            Ok(mutex) => mutex.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(ctx) => std::mem::take(&mut *ctx.lock().unwrap_or_else(PoisonError::into_inner)),
+        };
         ctx.close_deeper_than(0);
         ctx.wtr
     }
@@ -218,10 +221,22 @@ impl std::ops::DerefMut for Buffer {
     }
 }
 
+/// Runs `f` on the buffer context behind a node.
+///
+/// Nodes borrow from their [`Buffer`], so the context is alive for as long as any node is; `None`
+/// is only returned if that invariant is broken. A poisoned lock means an earlier write panicked
+/// partway, which leaves the text buffer itself intact, so it is used as is.
+fn with_ctx<R>(ctx: &Weak<Mutex<Ctx>>, f: impl FnOnce(&mut Ctx) -> R) -> Option<R> {
+    let ctx = ctx.upgrade()?;
+    let mut ctx = ctx.lock().unwrap_or_else(PoisonError::into_inner);
+    Some(f(&mut ctx))
+}
+
+// Writing to a `String` can't fail, so the results of `write!` into `wtr` are ignored.
 impl Ctx {
     fn close_unclosed(&mut self) {
         if let Some(closer) = self.tag_open.take() {
-            self.wtr.write_str(closer).unwrap();
+            self.wtr.write_str(closer).ok();
         }
     }
 
@@ -229,17 +244,17 @@ impl Ctx {
         self.close_unclosed();
         let to_pop = self.stack.len() - depth;
         for _ in 0..to_pop {
-            if let Some((tag, is_self_closing)) = self.stack.pop() {
-                if !is_self_closing {
-                    write!(self.wtr, "</{}>", tag).unwrap();
-                }
+            if let Some((tag, is_self_closing)) = self.stack.pop()
+                && !is_self_closing
+            {
+                write!(self.wtr, "</{}>", tag).ok();
             }
         }
     }
 
     fn open(&mut self, tag: Cow<'static, str>, depth: usize, is_self_closing: bool) {
         self.close_deeper_than(depth);
-        write!(self.wtr, "<{}", &tag).unwrap();
+        write!(self.wtr, "<{}", &tag).ok();
         if is_self_closing {
             self.tag_open = Some(" />");
         } else {
@@ -250,7 +265,7 @@ impl Ctx {
 
     fn open_comment(&mut self, depth: usize) {
         self.close_deeper_than(depth);
-        write!(self.wtr, "<!-- ").unwrap();
+        write!(self.wtr, "<!-- ").ok();
         self.tag_open = Some(" -->");
     }
 }
@@ -258,9 +273,7 @@ impl Ctx {
 impl<'a> Node<'a> {
     /// Create a new node, inheriting from the parent node.
     pub fn child<'b>(&'b mut self, tag: Cow<'static, str>) -> Node<'b> {
-        let ctx = self.ctx.upgrade().unwrap();
-        let mut ctx = ctx.lock().unwrap();
-        ctx.open(tag, self.depth, false);
+        with_ctx(&self.ctx, |ctx| ctx.open(tag, self.depth, false));
         Node {
             depth: self.depth + 1,
             ctx: self.ctx.clone(),
@@ -271,9 +284,7 @@ impl<'a> Node<'a> {
 
     /// Create a void child node, which self-closes.
     pub fn void_child<'b>(&'b mut self, tag: Cow<'static, str>) -> Void<'b> {
-        let ctx = self.ctx.upgrade().unwrap();
-        let mut ctx = ctx.lock().unwrap();
-        ctx.open(tag, self.depth, true);
+        with_ctx(&self.ctx, |ctx| ctx.open(tag, self.depth, true));
         Void {
             ctx: self.ctx.clone(),
             _phantom: std::marker::PhantomData,
@@ -281,9 +292,7 @@ impl<'a> Node<'a> {
     }
 
     pub fn comment<'b>(&'b mut self) -> Comment<'b> {
-        let ctx = self.ctx.upgrade().unwrap();
-        let mut ctx = ctx.lock().unwrap();
-        ctx.open_comment(self.depth);
+        with_ctx(&self.ctx, |ctx| ctx.open_comment(self.depth));
         Comment {
             ctx: self.ctx.clone(),
             _phantom: std::marker::PhantomData,
@@ -291,11 +300,11 @@ impl<'a> Node<'a> {
     }
 
     pub fn attr(self, attr: &str) -> Node<'a> {
-        let ctx = self.ctx.upgrade().unwrap();
-        let mut ctx = ctx.lock().unwrap();
-        if ctx.tag_open.is_some() {
-            write!(ctx.wtr, " {}", attr).unwrap();
-        }
+        with_ctx(&self.ctx, |ctx| {
+            if ctx.tag_open.is_some() {
+                write!(ctx.wtr, " {}", attr).ok();
+            }
+        });
         self
     }
 
@@ -319,43 +328,38 @@ impl<'a> Node<'a> {
 
 impl<'a> Write for Node<'a> {
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let mutex = self.ctx.upgrade().unwrap();
-        let mut ctx = mutex.lock().unwrap();
-        ctx.close_deeper_than(self.depth);
         let s = match self.escaping {
             Escaping::Raw => s.into(),
             Escaping::Normal => html_escape::encode_text(s),
             Escaping::Safe => html_escape::encode_safe(s),
         };
-        ctx.wtr.write_str(&s)
+        with_ctx(&self.ctx, |ctx| {
+            ctx.close_deeper_than(self.depth);
+            ctx.wtr.write_str(&s)
+        })
+        .unwrap_or(Err(std::fmt::Error))
     }
 }
 
 impl<'a> Void<'a> {
     pub fn attr(self, attr: &str) -> Void<'a> {
-        let ctx = self.ctx.upgrade().unwrap();
-        let mut ctx = ctx.lock().unwrap();
-        if ctx.tag_open.is_some() {
-            write!(ctx.wtr, " {}", attr).unwrap();
-        }
+        with_ctx(&self.ctx, |ctx| {
+            if ctx.tag_open.is_some() {
+                write!(ctx.wtr, " {}", attr).ok();
+            }
+        });
         self
     }
 }
 
 impl<'a> Write for Comment<'a> {
     fn write_char(&mut self, c: char) -> std::fmt::Result {
-        let mutex = self.ctx.upgrade().unwrap();
-        let mut ctx = mutex.lock().unwrap();
-        ctx.wtr.write_char(c)
+        with_ctx(&self.ctx, |ctx| ctx.wtr.write_char(c)).unwrap_or(Err(std::fmt::Error))
     }
     fn write_fmt(&mut self, args: std::fmt::Arguments<'_>) -> std::fmt::Result {
-        let mutex = self.ctx.upgrade().unwrap();
-        let mut ctx = mutex.lock().unwrap();
-        ctx.wtr.write_fmt(args)
+        with_ctx(&self.ctx, |ctx| ctx.wtr.write_fmt(args)).unwrap_or(Err(std::fmt::Error))
     }
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let mutex = self.ctx.upgrade().unwrap();
-        let mut ctx = mutex.lock().unwrap();
-        ctx.wtr.write_str(s)
+        with_ctx(&self.ctx, |ctx| ctx.wtr.write_str(s)).unwrap_or(Err(std::fmt::Error))
     }
 }
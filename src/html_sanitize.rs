@@ -10,7 +10,7 @@ pub fn sanitize_html_structure(input: &str) -> String {
     let dom = parse_document(RcDom::default(), Default::default())
         .from_utf8()
         .read_from(&mut input.as_bytes())
-        .unwrap();
+        .expect("reading from a byte slice can't fail");
 
     sanitize_node(&dom.document);
 
@@ -20,9 +20,9 @@ pub fn sanitize_html_structure(input: &str) -> String {
         &SerializableHandle::from(dom.document.clone()),
         SerializeOpts::default(),
     )
-    .unwrap();
+    .expect("writing to a Vec can't fail");
 
-    String::from_utf8(output).unwrap()
+    String::from_utf8(output).expect("html5ever serializes to UTF-8")
 }
 
 fn sanitize_node(handle: &Handle) {
@@ -41,26 +41,24 @@ fn sanitize_node(handle: &Handle) {
                     }
                 }
 
-                if has_block {
-                    if let Some(parent_weak) = node.parent.take() {
-                        if let Some(parent) = parent_weak.upgrade() {
-                            let mut parent_children = parent.children.borrow_mut();
-
-                            if let Some(index) =
-                                parent_children.iter().position(|n| Rc::ptr_eq(n, node))
-                            {
-                                parent_children.remove(index);
-                                for child in node.children.borrow().iter() {
-                                    parent_children.insert(index, child.clone());
-                                }
-                            }
+                if has_block && let Some(parent_weak) = node.parent.take() {
+                    if let Some(parent) = parent_weak.upgrade() {
+                        let mut parent_children = parent.children.borrow_mut();
 
-                            // Restore parent back after mutation
-                            node.parent.set(Some(Rc::downgrade(&parent)));
-                        } else {
-                            // Parent weak reference was dangling; restore as None
-                            node.parent.set(None);
+                        if let Some(index) =
+                            parent_children.iter().position(|n| Rc::ptr_eq(n, node))
+                        {
+                            parent_children.remove(index);
+                            for child in node.children.borrow().iter() {
+                                parent_children.insert(index, child.clone());
+                            }
                         }
+
+                        // Restore parent back after mutation
+                        node.parent.set(Some(Rc::downgrade(&parent)));
+                    } else {
+                        // Parent weak reference was dangling; restore as None
+                        node.parent.set(None);
                     }
                 }
             }
@@ -89,28 +87,28 @@ fn unwrap_nested_anchors(node: &Handle) {
     let mut children = node.children.borrow_mut();
     let mut i = 0;
     while i < children.len() {
-        if let NodeData::Element { name, .. } = &children[i].data {
-            if name.local.as_ref() == "a" {
-                // Extract text nodes from nested <a>
-                let text_nodes: Vec<_> = children[i]
-                    .children
-                    .borrow()
-                    .iter()
-                    .filter_map(|child| match &child.data {
-                        NodeData::Text { contents } => Some(contents.borrow().clone()),
-                        _ => None,
-                    })
-                    .collect();
-
-                let inserts: Vec<_> = text_nodes
-                    .into_iter()
-                    .map(|t| make_text_node(t.as_ref()))
-                    .collect();
-
-                children.remove(i);
-                children.splice(i..i, inserts);
-                continue; // stay at the same index
-            }
+        if let NodeData::Element { name, .. } = &children[i].data
+            && name.local.as_ref() == "a"
+        {
+            // Extract text nodes from nested <a>
+            let text_nodes: Vec<_> = children[i]
+                .children
+                .borrow()
+                .iter()
+                .filter_map(|child| match &child.data {
+                    NodeData::Text { contents } => Some(contents.borrow().clone()),
+                    _ => None,
+                })
+                .collect();
+
+            let inserts: Vec<_> = text_nodes
+                .into_iter()
+                .map(|t| make_text_node(t.as_ref()))
+                .collect();
+
+            children.remove(i);
+            children.splice(i..i, inserts);
+            continue; // stay at the same index
         }
         i += 1;
     }
@@ -118,10 +116,10 @@ fn unwrap_nested_anchors(node: &Handle) {
 
 fn is_known_block_element(node: &Handle) -> bool {
     if let NodeData::Element { ref name, .. } = node.data {
-        match name.local.as_ref() {
-            "details" | "summary" | "adf-media-group" | "table" => true,
-            _ => false,
-        }
+        matches!(
+            name.local.as_ref(),
+            "details" | "summary" | "adf-media-group" | "table"
+        )
     } else {
         false
     }
@@ -131,7 +129,7 @@ pub fn normalize_html(input: &str) -> String {
     let dom = parse_document(RcDom::default(), Default::default())
         .from_utf8()
         .read_from(&mut input.as_bytes())
-        .unwrap();
+        .expect("reading from a byte slice can't fail");
 
     let mut output = Vec::new();
     serialize(
@@ -139,12 +137,12 @@ pub fn normalize_html(input: &str) -> String {
         &markup5ever_rcdom::SerializableHandle::from(dom.document.clone()),
         SerializeOpts::default(),
     )
-    .unwrap();
+    .expect("writing to a Vec can't fail");
 
-    let post_sanitized = String::from_utf8(output).unwrap();
+    let post_sanitized = String::from_utf8(output).expect("html5ever serializes to UTF-8");
     // Fix for broken <br> tags
     let sanitized = post_sanitized.replace("<br>", "<br />");
-    tracing::debug!(sanitized_html = %sanitized, "Sanitized HTML");
+    tracing::trace!(sanitized_html = %sanitized, "Sanitized HTML");
     sanitized
 }
 
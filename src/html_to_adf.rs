@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
@@ -7,10 +8,70 @@ use html5ever::tokenizer::{
 };
 
 use crate::adf::adf_types::{
-    AdfBlockNode, AdfMark, AdfNode, DecisionItem, DecisionItemAttrs, DecisionItemState,
-    ExpandAttrs, ListItem, LocalId, TaskItem, TaskItemAttrs,
+    AdfBlockNode, AdfMark, AdfNode, CodeBlockAttrs, DecisionItem, DecisionItemAttrs,
+    DecisionItemState, ExpandAttrs, ListItem, LocalId, OrderedListAttrs, TaskItem, TaskItemAttrs,
+    normalize_language, order_code_link,
 };
 use crate::handlers::*;
+use crate::markdown::try_markdown_to_adf_with_options;
+use crate::options::{ConvertOptions, RawHtmlPolicy};
+
+/// Elements with no ADF equivalent, handled according to [`RawHtmlPolicy`].
+const RAW_HTML_TAGS: [&str; 10] = [
+    "iframe", "object", "embed", "video", "audio", "canvas", "svg", "math", "form", "template",
+];
+
+/// Trims whitespace, reusing the allocation when there is nothing to trim.
+fn trim_owned(text: String) -> String {
+    if text.trim().len() == text.len() {
+        text
+    } else {
+        text.trim().to_string()
+    }
+}
+
+/// Trims the text of a task or decision item, keeping its leading whitespace if asked to.
+fn trim_item_text(text: String, preserve_leading: bool) -> String {
+    if preserve_leading {
+        text.trim_end().to_string()
+    } else {
+        trim_owned(text)
+    }
+}
+
+/// The text of a paragraph that looks like Markdown pasted as plain text: unmarked text whose
+/// first line starts like a heading (`## `) or list item (`- `, `1. `). Hard breaks become line
+/// breaks, and `&` and `<` are escaped so that text which only looks like HTML stays text.
+fn pasted_markdown(node: &AdfBlockNode) -> Option<String> {
+    let AdfBlockNode::Paragraph {
+        content: Some(nodes),
+    } = node
+    else {
+        return None;
+    };
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            AdfNode::Text {
+                text: part,
+                marks: None,
+            } => text.push_str(part),
+            AdfNode::HardBreak => text.push('\n'),
+            _ => return None,
+        }
+    }
+    let first_line = text.lines().next()?;
+    let heading = first_line.trim_start_matches('#');
+    let hashes = first_line.len() - heading.len();
+    let ordered = first_line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let digits = first_line.len() - ordered.len();
+    let looks_like_markdown = ((1..=6).contains(&hashes) && heading.starts_with(' '))
+        || ["- ", "* ", "+ "]
+            .iter()
+            .any(|marker| first_line.starts_with(marker))
+        || ((1..=9).contains(&digits) && (ordered.starts_with(". ") || ordered.starts_with(") ")));
+    looks_like_markdown.then(|| text.replace('&', "&amp;").replace('<', "&lt;"))
+}
 
 /// Cleans surrounding text by removing leading and trailing whitespace before and after newlines
 fn clean_surrounding_text(text: &str) -> &str {
@@ -46,16 +107,75 @@ fn clean_surrounding_text(text: &str) -> &str {
 
 pub type HandlerFn = Box<dyn Fn(&mut ADFBuilderState, Element) -> bool>;
 
+/// Why HTML couldn't be converted to ADF. Each block error carries the blocks that were open
+/// at the time, outermost first, as described by their kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdfConvertError {
+    /// A block ended inside a parent that can't hold it.
+    UnexpectedParentFor {
+        child: String,
+        parent: String,
+        stack: Vec<String>,
+    },
+    /// A block was closed where it can't be, such as a list item outside a list.
+    UnexpectedClose { child: String, stack: Vec<String> },
+    /// An end tag didn't match the block open at the top of the stack.
+    MismatchedClose { tag: String, stack: Vec<String> },
+    /// An element was missing something needed to convert it.
+    InvalidElement { tag: String, reason: String },
+}
+
+impl std::fmt::Display for AdfConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdfConvertError::UnexpectedParentFor {
+                child,
+                parent,
+                stack,
+            } => write!(f, "{child} can't be placed in {parent}: {stack:?}"),
+            AdfConvertError::UnexpectedClose { child, stack } => {
+                write!(f, "{child} closed unexpectedly: {stack:?}")
+            }
+            AdfConvertError::MismatchedClose { tag, stack } => {
+                write!(f, "Mismatched </{tag}>: {stack:?}")
+            }
+            AdfConvertError::InvalidElement { tag, reason } => {
+                write!(f, "Invalid <{tag}>: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AdfConvertError {}
+
+/// An element with no ADF equivalent, collected until its end tag.
+struct RawHtmlCapture {
+    tag: String,
+    depth: usize,
+    html: String,
+}
+
 pub struct ADFBuilder {
     state: RefCell<ADFBuilderState>,
+    raw_html: RefCell<Option<RawHtmlCapture>>,
     custom_start_handlers: HashMap<String, HandlerFn>,
     start_handlers: HashMap<String, HandlerFn>,
     custom_end_handlers: HashMap<String, HandlerFn>,
     end_handlers: HashMap<String, HandlerFn>,
 }
 
+impl Default for ADFBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ADFBuilder {
     pub fn new() -> Self {
+        Self::with_options(ConvertOptions::default())
+    }
+
+    pub fn with_options(options: ConvertOptions) -> Self {
         let mut this = Self {
             state: RefCell::new(ADFBuilderState {
                 stack: vec![BlockContext::Document(vec![])],
@@ -63,7 +183,12 @@ impl ADFBuilder {
                 current_text: String::new(),
                 custom_block_id: None,
                 custom_block_tag: None,
+                options,
+                generated_local_ids: 0,
+                error: None,
+                trim_text_seam: false,
             }),
+            raw_html: RefCell::new(None),
             start_handlers: HashMap::new(),
             custom_start_handlers: HashMap::new(),
             end_handlers: HashMap::new(),
@@ -93,11 +218,12 @@ impl ADFBuilder {
         this.insert_start_handler("del", del_start_handler());
         this.insert_start_handler("a", a_start_handler());
         this.insert_start_handler("u", u_start_handler());
+        this.insert_start_handler("ins", u_start_handler());
         this.insert_start_handler("sub", sub_start_handler());
         this.insert_start_handler("sup", sup_start_handler());
 
         // For all mark tags use same generic mark handler
-        for tag in &["em", "strong", "del", "a", "u", "sub", "sup"] {
+        for tag in &["em", "strong", "del", "a", "u", "ins", "sub", "sup"] {
             this.insert_end_handler(tag, mark_end_handler());
         }
 
@@ -110,10 +236,10 @@ impl ADFBuilder {
         this.insert_start_handler("table", table_start_handler());
         this.insert_end_handler("table", table_end_handler());
 
-        // Noops for handling the section tags
-        this.insert_start_handler("thead", table_section_start_handler());
+        // Rows in the head are header rows, whatever their cells are written as
+        this.insert_start_handler("thead", table_section_start_handler(true));
         this.insert_end_handler("thead", table_section_end_handler());
-        this.insert_start_handler("tbody", table_section_start_handler());
+        this.insert_start_handler("tbody", table_section_start_handler(false));
         this.insert_end_handler("tbody", table_section_end_handler());
 
         this.insert_start_handler("tr", table_row_start_handler());
@@ -147,6 +273,7 @@ impl ADFBuilder {
         this.insert_end_handler("summary", summary_end_handler());
 
         this.insert_start_handler("adf-task-item", task_item_start_handler());
+        this.insert_start_handler("input", checkbox_start_handler());
         this.insert_start_handler("adf-decision-item", decision_start_handler());
         this.insert_start_handler("adf-local-data", local_data_start_handler());
 
@@ -162,6 +289,14 @@ impl ADFBuilder {
         this.insert_start_handler("adf-media-single", media_single_start_handler());
         this.insert_end_handler("adf-media-single", media_single_end_handler());
 
+        this.insert_start_handler("adf-block-card", block_card_start_handler());
+        this.insert_end_handler("adf-block-card", block_card_end_handler());
+        this.insert_start_handler(
+            "adf-block-card-data-source",
+            block_card_data_source_start_handler(),
+        );
+        this.insert_start_handler("adf-block-card-view", block_card_view_start_handler());
+
         this.insert_start_handler("adf-media-group", media_group_start_handler());
         this.insert_end_handler("adf-media-group", media_group_end_handler());
 
@@ -190,6 +325,8 @@ impl ADFBuilder {
         self.end_handlers.insert(tag.to_string(), Box::new(handler));
     }
 
+    /// Registers a handler for a start tag, run before the built-in one. The built-in handler
+    /// only runs when this one returns false. A handler already added for the tag is replaced.
     pub fn add_start_handler(
         &mut self,
         tag: &str,
@@ -199,6 +336,8 @@ impl ADFBuilder {
             .insert(tag.to_string(), Box::new(handler));
     }
 
+    /// Registers a handler for an end tag, as [`add_start_handler`](Self::add_start_handler)
+    /// does for start tags.
     pub fn add_end_handler(
         &mut self,
         tag: &str,
@@ -227,6 +366,13 @@ impl ADFBuilder {
     }
 
     pub fn flush_text(state: &mut ADFBuilderState) {
+        // A status holds plain text, so text under marks inside it is collected as is
+        if matches!(
+            state.stack.last(),
+            Some(BlockContext::CustomBlock(CustomBlockType::Status, _, _))
+        ) {
+            return;
+        }
         if !state.current_text.is_empty() {
             let mut text = std::mem::take(&mut state.current_text);
 
@@ -236,61 +382,80 @@ impl ADFBuilder {
                 Some(
                     BlockContext::Heading(_, _)
                         | BlockContext::Paragraph(_)
-                        | BlockContext::TableBlockCell(_)
-                        | BlockContext::TableBlockHeader(_)
+                        | BlockContext::TableBlockCell(..)
+                        | BlockContext::TableBlockHeader(..)
                         | BlockContext::Blockquote(_)
                         | BlockContext::ListItem(_)
+                        | BlockContext::CustomBlock(
+                            CustomBlockType::Div
+                                | CustomBlockType::Expand
+                                | CustomBlockType::NestedExpand
+                                | CustomBlockType::Panel,
+                            _,
+                            _
+                        )
                 )
             );
 
+            let preserve_leading = state.options.preserve_leading_whitespace;
             if trim_for_blocks {
-                text = clean_surrounding_text(&text).to_string();
+                // Newlines left inside the text are soft line breaks, which render as spaces
+                let cleaned = clean_surrounding_text(&text);
+                if cleaned.len() != text.len() || cleaned.contains('\n') {
+                    text = cleaned.replace('\n', " ");
+                }
             }
 
             if text.trim().is_empty() {
                 return;
             }
 
-            let marks = if state.mark_stack.is_empty() {
-                None
-            } else {
-                Some(state.mark_stack.clone())
-            };
+            // Nesting the same element twice, as in `<b><b>x</b></b>`, still gives one mark
+            let mut marks: Vec<AdfMark> = Vec::with_capacity(state.mark_stack.len());
+            for mark in &state.mark_stack {
+                if !marks.contains(mark) {
+                    marks.push(mark.clone());
+                }
+            }
+            order_code_link(&mut marks);
+            let marks = if marks.is_empty() { None } else { Some(marks) };
 
             if let Some(frame) = state.stack.last_mut() {
                 match frame {
                     BlockContext::Paragraph(nodes) | BlockContext::Heading(_, nodes) => {
-                        let node = AdfNode::Text {
-                            text: text.clone(),
-                            marks,
-                        };
+                        let node = AdfNode::Text { text, marks };
                         nodes.push(node);
                     }
                     BlockContext::ListItem(nodes)
                     | BlockContext::Blockquote(nodes)
-                    | BlockContext::TableBlockHeader(nodes)
-                    | BlockContext::TableBlockCell(nodes) => {
-                        let node = AdfNode::Text {
-                            text: text.clone(),
-                            marks,
-                        };
+                    | BlockContext::TableBlockHeader(nodes, _)
+                    | BlockContext::TableBlockCell(nodes, _)
+                    | BlockContext::CustomBlock(
+                        CustomBlockType::Div
+                        | CustomBlockType::Expand
+                        | CustomBlockType::NestedExpand
+                        | CustomBlockType::Panel,
+                        nodes,
+                        _,
+                    ) => {
+                        let node = AdfNode::Text { text, marks };
                         Self::push_into_last_paragraph(nodes, node);
                     }
                     BlockContext::TaskItem(nodes, _, _) => {
                         let node = AdfNode::Text {
-                            text: text.trim().to_string(),
+                            text: trim_item_text(text, preserve_leading),
                             marks,
                         };
                         nodes.push(node);
                     }
                     BlockContext::DecisionItem(nodes, _) => {
                         let node = AdfNode::Text {
-                            text: text.trim().to_string(),
+                            text: trim_item_text(text, preserve_leading),
                             marks,
                         };
                         nodes.push(node);
                     }
-                    BlockContext::CodeBlock(lines) => {
+                    BlockContext::CodeBlock(lines, _) => {
                         lines.push(text);
                     }
                     _ => {}
@@ -306,7 +471,22 @@ impl ADFBuilder {
     }
 
     pub fn close_current_block(state: &mut ADFBuilderState) {
+        if let Err(error) = Self::try_close_current_block(state) {
+            state.fail(error);
+        }
+    }
+
+    fn try_close_current_block(state: &mut ADFBuilderState) -> Result<(), AdfConvertError> {
+        // The document is only closed by `emit`
+        if state.stack.len() < 2 {
+            let document = state
+                .stack
+                .last()
+                .expect("Document should always be present");
+            return Err(state.unexpected_close(document));
+        }
         let frame = state.stack.pop().expect("Expected a block context");
+        let frame = Self::infer_list_kind(state, frame);
         let mut parent = state
             .stack
             .last_mut()
@@ -314,12 +494,12 @@ impl ADFBuilder {
         match frame {
             BlockContext::Paragraph(nodes) => match &mut parent {
                 BlockContext::Document(parent_nodes)
-                | BlockContext::TableBlockCell(parent_nodes)
-                | BlockContext::TableBlockHeader(parent_nodes)
+                | BlockContext::TableBlockCell(parent_nodes, _)
+                | BlockContext::TableBlockHeader(parent_nodes, _)
                 | BlockContext::Blockquote(parent_nodes)
                 | BlockContext::ListItem(parent_nodes) => {
                     if nodes.is_empty() {
-                        return;
+                        return Ok(());
                     }
                     parent_nodes.push(AdfBlockNode::Paragraph {
                         content: Some(nodes),
@@ -330,24 +510,29 @@ impl ADFBuilder {
                     | CustomBlockType::Expand
                     | CustomBlockType::NestedExpand
                     | CustomBlockType::Panel => {
-                        parent_nodes.push(AdfBlockNode::Paragraph {
-                            content: Some(nodes),
-                        });
+                        let content = if nodes.is_empty() { None } else { Some(nodes) };
+                        parent_nodes.push(AdfBlockNode::Paragraph { content });
                     }
-                    parent => {
-                        panic!("Invalid parent for Paragraph: {parent:?}");
+                    block_ty => {
+                        let parent = format!("CustomBlock({block_ty:?})");
+                        return Err(state.unexpected_parent("Paragraph", parent));
                     }
                 },
-                parent => panic!("Invalid parent for Paragraph: {parent:?}"),
+                BlockContext::Heading(_, parent_nodes) => flatten_into_heading(parent_nodes, nodes),
+                parent => {
+                    let parent = parent.describe();
+                    return Err(state.unexpected_parent("Paragraph", parent));
+                }
             },
             BlockContext::CustomBlock(CustomBlockType::Expand, nodes, attrs) => match parent {
                 BlockContext::Document(parent_nodes)
-                | BlockContext::TableBlockCell(parent_nodes)
-                | BlockContext::TableBlockHeader(parent_nodes)
+                | BlockContext::TableBlockCell(parent_nodes, _)
+                | BlockContext::TableBlockHeader(parent_nodes, _)
                 | BlockContext::ListItem(parent_nodes)
                 | BlockContext::Blockquote(parent_nodes) => {
                     let title = attrs.get("title").cloned();
-                    let expand_attrs = ExpandAttrs { title };
+                    let expanded = attrs.contains_key("open");
+                    let expand_attrs = ExpandAttrs { title, expanded };
 
                     parent_nodes.push(AdfBlockNode::Expand {
                         content: nodes,
@@ -360,57 +545,81 @@ impl ADFBuilder {
                     | CustomBlockType::NestedExpand
                     | CustomBlockType::Panel => {
                         let title = attrs.get("title").cloned();
-                        let expand_attrs = ExpandAttrs { title };
+                        let expanded = attrs.contains_key("open");
+                        let expand_attrs = ExpandAttrs { title, expanded };
                         parent_nodes.push(AdfBlockNode::Expand {
                             content: nodes,
                             attrs: expand_attrs,
                         });
                     }
-                    parent => {
-                        panic!("Invalid parent for Paragraph: {parent:?}");
+                    block_ty => {
+                        let parent = format!("CustomBlock({block_ty:?})");
+                        return Err(state.unexpected_parent("CustomBlock(Expand)", parent));
                     }
                 },
-                _ => panic!("Invalid parent for CustomBlock"),
+                parent => {
+                    let parent = parent.describe();
+                    return Err(state.unexpected_parent("CustomBlock(Expand)", parent));
+                }
             },
-            BlockContext::CodeBlock(lines) => match parent {
+            BlockContext::CodeBlock(lines, language) => match parent {
                 BlockContext::Document(parent_nodes)
-                | BlockContext::TableBlockCell(parent_nodes)
-                | BlockContext::TableBlockHeader(parent_nodes)
+                | BlockContext::TableBlockCell(parent_nodes, _)
+                | BlockContext::TableBlockHeader(parent_nodes, _)
                 | BlockContext::ListItem(parent_nodes)
                 | BlockContext::Blockquote(parent_nodes)
                 | BlockContext::CustomBlock(CustomBlockType::Div, parent_nodes, _) => {
                     let text = lines.join("");
+                    let language = match language {
+                        Some(language) if state.options.normalize_code_language => {
+                            Some(normalize_language(&language))
+                        }
+                        language => language,
+                    };
+                    // ADF rejects empty text nodes, so an empty code block has no content
+                    let content =
+                        (!text.is_empty()).then(|| vec![AdfNode::Text { text, marks: None }]);
                     parent_nodes.push(AdfBlockNode::CodeBlock {
-                        content: Some(vec![AdfNode::Text {
-                            text: text.into(),
-                            marks: None,
-                        }]),
-                        attrs: None,
+                        content,
+                        attrs: language.map(|language| CodeBlockAttrs {
+                            language: Some(language),
+                        }),
                     });
                 }
-                _ => panic!("Invalid parent for CodeBlock"),
+                parent => {
+                    let parent = parent.describe();
+                    return Err(state.unexpected_parent("CodeBlock", parent));
+                }
             },
             BlockContext::Blockquote(nodes) => match parent {
                 BlockContext::Document(parent_nodes)
-                | BlockContext::TableBlockCell(parent_nodes)
-                | BlockContext::TableBlockHeader(parent_nodes)
+                | BlockContext::TableBlockCell(parent_nodes, _)
+                | BlockContext::TableBlockHeader(parent_nodes, _)
                 | BlockContext::ListItem(parent_nodes)
                 | BlockContext::CustomBlock(CustomBlockType::Div, parent_nodes, _) => {
-                    parent_nodes.push(AdfBlockNode::Blockquote { content: nodes })
+                    let content = Self::trim_empty_paragraphs(nodes);
+                    parent_nodes.push(AdfBlockNode::Blockquote { content })
+                }
+                parent => {
+                    let parent = parent.describe();
+                    return Err(state.unexpected_parent("Blockquote", parent));
                 }
-                _ => panic!("Invalid parent for Blockquote"),
             },
             BlockContext::PendingList {
                 nodes,
                 ordered,
+                start,
                 local_id,
                 local_tag,
             } => match parent {
                 BlockContext::Document(parent_nodes)
                 | BlockContext::CustomBlock(CustomBlockType::Div, parent_nodes, _)
+                | BlockContext::CustomBlock(CustomBlockType::Panel, parent_nodes, _)
+                | BlockContext::CustomBlock(CustomBlockType::Expand, parent_nodes, _)
+                | BlockContext::CustomBlock(CustomBlockType::NestedExpand, parent_nodes, _)
                 | BlockContext::Blockquote(parent_nodes)
-                | BlockContext::TableBlockCell(parent_nodes)
-                | BlockContext::TableBlockHeader(parent_nodes)
+                | BlockContext::TableBlockCell(parent_nodes, _)
+                | BlockContext::TableBlockHeader(parent_nodes, _)
                 | BlockContext::ListItem(parent_nodes) => {
                     let is_task_list = local_tag
                         .as_ref()
@@ -420,6 +629,7 @@ impl ADFBuilder {
                         .as_ref()
                         .map(|tag| tag == "decision-list")
                         .unwrap_or(false);
+                    // Jira rejects lists without items, so lists that end up empty are dropped
                     if is_task_list {
                         let task_list_items = nodes
                             .into_iter()
@@ -431,6 +641,9 @@ impl ADFBuilder {
                                 }
                             })
                             .collect::<Vec<_>>();
+                        if task_list_items.is_empty() {
+                            return Ok(());
+                        }
 
                         parent_nodes.push(AdfBlockNode::TaskList {
                             attrs: LocalId {
@@ -449,6 +662,9 @@ impl ADFBuilder {
                                 }
                             })
                             .collect::<Vec<_>>();
+                        if decision_list_items.is_empty() {
+                            return Ok(());
+                        }
 
                         parent_nodes.push(AdfBlockNode::DecisionList {
                             content: decision_list_items,
@@ -459,42 +675,76 @@ impl ADFBuilder {
                     } else if ordered {
                         let ordered_list_items = nodes
                             .into_iter()
-                            .filter_map(|item| {
-                                if let ListItemType::ListItem(list_item) = item {
-                                    Some(list_item)
-                                } else {
-                                    None
-                                }
-                            })
+                            .map(ListItemType::into_list_item)
                             .collect::<Vec<_>>();
+                        if ordered_list_items.is_empty() {
+                            return Ok(());
+                        }
                         parent_nodes.push(AdfBlockNode::OrderedList {
                             content: ordered_list_items,
-                            attrs: None,
+                            attrs: start.map(|order| OrderedListAttrs { order: Some(order) }),
                         });
                     } else {
                         let bullet_list_items = nodes
                             .into_iter()
-                            .filter_map(|item| {
-                                if let ListItemType::ListItem(list_item) = item {
-                                    Some(list_item)
-                                } else {
-                                    None
-                                }
-                            })
+                            .map(ListItemType::into_list_item)
                             .collect::<Vec<_>>();
+                        if bullet_list_items.is_empty() {
+                            return Ok(());
+                        }
                         parent_nodes.push(AdfBlockNode::BulletList {
                             content: bullet_list_items,
                         });
                     }
                 }
-                parent => panic!("Invalid parent for PendingList: {parent:?}"),
+                parent => {
+                    let parent = parent.describe();
+                    return Err(state.unexpected_parent("PendingList", parent));
+                }
             },
-            block => {
-                let stack = &state.stack;
-                panic!(
-                    "{block:?} closed incorrectly; must use block-specific close method: {stack:?}"
-                );
+            // Blocks such as table cells and list items have their own close methods
+            block => return Err(state.unexpected_close(&block)),
+        }
+        Ok(())
+    }
+
+    /// Promotes an unmarked bullet list to a task or decision list when every item is one,
+    /// as with Markdown checkboxes or hand-written HTML without `adf-local-data`.
+    fn infer_list_kind(state: &mut ADFBuilderState, frame: BlockContext) -> BlockContext {
+        match frame {
+            BlockContext::PendingList {
+                nodes,
+                ordered: false,
+                start,
+                local_id,
+                local_tag: None,
+            } if !nodes.is_empty() => {
+                let local_tag = if nodes
+                    .iter()
+                    .all(|item| matches!(item, ListItemType::TaskItem(_)))
+                {
+                    Some("task-list".to_string())
+                } else if nodes
+                    .iter()
+                    .all(|item| matches!(item, ListItemType::DecisionItem(_)))
+                {
+                    Some("decision-list".to_string())
+                } else {
+                    None
+                };
+                let local_id = match local_tag {
+                    Some(_) => Some(local_id.unwrap_or_else(|| state.generate_local_id())),
+                    None => local_id,
+                };
+                BlockContext::PendingList {
+                    nodes,
+                    ordered: false,
+                    start,
+                    local_id,
+                    local_tag,
+                }
             }
+            frame => frame,
         }
     }
 
@@ -504,10 +754,13 @@ impl ADFBuilder {
         if let Some(BlockContext::ListItem(nodes)) = stack_item {
             match state.stack.last_mut() {
                 Some(BlockContext::PendingList { nodes: list, .. }) => {
+                    let nodes = Self::trim_empty_paragraphs(nodes);
                     list.push(ListItemType::ListItem(ListItem::new(nodes)));
                 }
-                _ => {
-                    panic!("ListItem closed without PendingList parent");
+                parent => {
+                    let parent = parent.map_or("nothing".to_string(), |parent| parent.describe());
+                    let error = state.unexpected_parent("ListItem", parent);
+                    state.fail(error);
                 }
             }
         } else if let Some(BlockContext::TaskItem(nodes, item_state, local_id)) = stack_item {
@@ -521,7 +774,12 @@ impl ADFBuilder {
                 );
                 list.push(ListItemType::TaskItem(task_item));
             } else {
-                panic!("TaskItem closed without PendingList parent");
+                let parent = state
+                    .stack
+                    .last()
+                    .map_or("nothing".to_string(), BlockContext::describe);
+                let error = state.unexpected_parent("TaskItem", parent);
+                state.fail(error);
             }
         } else if let Some(BlockContext::DecisionItem(nodes, local_id)) = stack_item {
             if let Some(BlockContext::PendingList { nodes: list, .. }) = state.stack.last_mut() {
@@ -534,24 +792,87 @@ impl ADFBuilder {
                 );
                 list.push(ListItemType::DecisionItem(decision_item));
             } else {
-                panic!("DecisionItem closed without PendingList parent");
+                let parent = state
+                    .stack
+                    .last()
+                    .map_or("nothing".to_string(), BlockContext::describe);
+                let error = state.unexpected_parent("DecisionItem", parent);
+                state.fail(error);
             }
-        } else {
-            panic!("Invalid context for ListItem close {:?}", stack_item);
+        } else if let Some(frame) = stack_item {
+            // Not a list item, so it goes back for the error to describe the stack as it was
+            state.stack.push(frame);
+            let error = state.unexpected_close(state.stack.last().expect("Just pushed"));
+            state.fail(error);
+        }
+    }
+
+    /// Parses HTML with this builder's handlers, including any added with
+    /// [`add_start_handler`](Self::add_start_handler) and
+    /// [`add_end_handler`](Self::add_end_handler), so bespoke tags can be supported:
+    ///
+    /// ```
+    /// use subseq_adf_convert::{adf::adf_types::AdfMark, html_to_adf::ADFBuilder};
+    ///
+    /// let mut builder = ADFBuilder::new();
+    /// builder.add_start_handler("adf-math", |state, _| {
+    ///     ADFBuilder::flush_text(state);
+    ///     state.mark_stack.push(AdfMark::Code);
+    ///     true
+    /// });
+    /// builder.add_end_handler("adf-math", |state, _| {
+    ///     ADFBuilder::flush_text(state);
+    ///     ADFBuilder::pop_mark(state, |mark| *mark == AdfMark::Code);
+    ///     true
+    /// });
+    /// let adf = builder.parse("<p>Area: <adf-math>pi r^2</adf-math></p>").unwrap();
+    /// ```
+    pub fn parse(self, input: &str) -> Result<AdfBlockNode, AdfConvertError> {
+        let input = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+        let queue: BufferQueue = Default::default();
+        queue.push_back(Tendril::from_slice(input));
+
+        let tok = Tokenizer::new(self, TokenizerOpts::default());
+        while !queue.is_empty() {
+            let _ = tok.feed(&queue);
         }
+        tok.end();
+        tok.sink.emit()
     }
 
-    pub fn emit(self) -> AdfBlockNode {
+    /// Closes the blocks left open and returns the document, or the first error met while
+    /// building it.
+    pub fn emit(self) -> Result<AdfBlockNode, AdfConvertError> {
         let mut state = self.state.into_inner();
         Self::flush_text(&mut state);
-        while state.stack.len() > 1 {
+        // An element left open at the end of the input still keeps what was collected
+        if let Some(capture) = self.raw_html.into_inner()
+            && state.options.raw_html_policy == RawHtmlPolicy::AsCodeBlock
+        {
+            Self::push_raw_html(&mut state, capture.html);
+        }
+        while state.stack.len() > 1 && state.error.is_none() {
             Self::close_current_block(&mut state);
         }
-        if let BlockContext::Document(content) = state.stack.pop().unwrap() {
-            AdfBlockNode::Doc {
+        if let Some(error) = state.error {
+            return Err(error);
+        }
+        if let Some(BlockContext::Document(mut content)) = state.stack.pop() {
+            if state.options.trim_document_paragraphs {
+                Self::trim_edge_paragraphs(&mut content);
+            }
+            if state.options.interpret_markdown_in_text {
+                Self::interpret_pasted_markdown(&mut content, &state.options);
+            }
+            let mut doc = AdfBlockNode::Doc {
                 content,
                 version: 1,
+            };
+            doc.normalize_empty_paragraphs();
+            if state.options.promote_standalone_cards {
+                doc.promote_standalone_cards();
             }
+            Ok(doc)
         } else {
             panic!("Expected Document at the base of stack");
         }
@@ -560,7 +881,7 @@ impl ADFBuilder {
     fn push_inline(state: &mut ADFBuilderState, node: AdfNode) {
         if let Some(frame) = state.stack.last_mut() {
             match frame {
-                BlockContext::CodeBlock(lines) => lines.push("\n".into()),
+                BlockContext::CodeBlock(lines, _) => lines.push("\n".into()),
                 BlockContext::Paragraph(nodes)
                 | BlockContext::Heading(_, nodes)
                 | BlockContext::DecisionItem(nodes, _)
@@ -596,6 +917,45 @@ impl ADFBuilder {
             .collect()
     }
 
+    /// Replaces each paragraph that [looks like pasted Markdown](pasted_markdown) with the
+    /// blocks its text parses to as Markdown. A paragraph that fails to parse is kept as it was.
+    fn interpret_pasted_markdown(nodes: &mut Vec<AdfBlockNode>, options: &ConvertOptions) {
+        *nodes = std::mem::take(nodes)
+            .into_iter()
+            .flat_map(|node| {
+                let Some(text) = pasted_markdown(&node) else {
+                    return vec![node];
+                };
+                match try_markdown_to_adf_with_options(&text, options) {
+                    Ok(AdfBlockNode::Doc { content, .. }) => content,
+                    Ok(_) => vec![node],
+                    Err(error) => {
+                        tracing::warn!(%error, "Failed to interpret pasted Markdown");
+                        vec![node]
+                    }
+                }
+            })
+            .collect();
+    }
+
+    /// Removes empty paragraphs from the start and end of `nodes`, keeping those in between.
+    /// Editors write an empty paragraph as `<p><br></p>`, so a paragraph holding nothing but hard
+    /// breaks counts as empty.
+    fn trim_edge_paragraphs(nodes: &mut Vec<AdfBlockNode>) {
+        let is_empty = |node: &AdfBlockNode| match node {
+            AdfBlockNode::Paragraph { content } => content
+                .iter()
+                .flatten()
+                .all(|node| matches!(node, AdfNode::HardBreak)),
+            _ => false,
+        };
+        while nodes.last().is_some_and(is_empty) {
+            nodes.pop();
+        }
+        let leading = nodes.iter().take_while(|node| is_empty(node)).count();
+        nodes.drain(..leading);
+    }
+
     pub fn push_node_block_to_parent(state: &mut ADFBuilderState, node: AdfBlockNode) {
         let frame = state
             .stack
@@ -609,10 +969,10 @@ impl ADFBuilder {
             | BlockContext::CustomBlock(CustomBlockType::NestedExpand, nodes, _)
             | BlockContext::CustomBlock(CustomBlockType::Div, nodes, _)
             | BlockContext::ListItem(nodes)
-            | BlockContext::TableBlockCell(nodes)
-            | BlockContext::TableBlockHeader(nodes) => {
-                match &node {
-                    AdfBlockNode::Paragraph { content } => match content {
+            | BlockContext::TableBlockCell(nodes, _)
+            | BlockContext::TableBlockHeader(nodes, _) => {
+                if let AdfBlockNode::Paragraph { content } = &node {
+                    match content {
                         Some(content) => {
                             if content.is_empty() {
                                 return;
@@ -621,22 +981,35 @@ impl ADFBuilder {
                         None => {
                             return;
                         }
-                    },
-                    _ => {}
+                    }
                 }
                 nodes.push(node);
                 return;
             }
+            BlockContext::Heading(_, nodes) => {
+                if let AdfBlockNode::Paragraph { content } = node {
+                    flatten_into_heading(nodes, content.unwrap_or_default());
+                    return;
+                }
+                let error = state.unexpected_parent(&node.to_string(), "Heading".to_string());
+                state.fail(error);
+                return;
+            }
             BlockContext::Paragraph(nodes) => {
                 // Invalid paragraph context for block node
                 // We need to drop the paragraph context
                 // and push the block node to the grandparent
                 if !nodes.is_empty() {
-                    panic!("Invalid paragraph context for block node: {frame:?} <-- {node:?}");
+                    let error = state.unexpected_parent(&node.to_string(), "Paragraph".to_string());
+                    state.fail(error);
+                    return;
                 }
             }
-            _ => {
-                panic!("Invalid block context for block node: {frame:?} <-- {node:?}");
+            frame => {
+                let parent = frame.describe();
+                let error = state.unexpected_parent(&node.to_string(), parent);
+                state.fail(error);
+                return;
             }
         }
 
@@ -644,6 +1017,126 @@ impl ADFBuilder {
         Self::push_node_block_to_parent(state, node);
     }
 
+    /// Applies the [`RawHtmlPolicy`] to a token, returning true if the token was consumed.
+    fn capture_raw_html(&self, state: &mut ADFBuilderState, token: &Token) -> bool {
+        let policy = state.options.raw_html_policy;
+        let mut capture = self.raw_html.borrow_mut();
+        let Some(current) = capture.as_mut() else {
+            let Token::TagToken(tag) = token else {
+                return false;
+            };
+            if tag.kind != TagKind::StartTag
+                || policy == RawHtmlPolicy::AsUnknownPassthrough
+                || !RAW_HTML_TAGS.contains(&tag.name.as_ref())
+            {
+                return false;
+            }
+            let html = serialize_tag(tag);
+            if tag.self_closing || tag.name.as_ref() == "embed" {
+                if policy == RawHtmlPolicy::AsCodeBlock {
+                    Self::push_raw_html(state, html);
+                } else {
+                    Self::drop_raw_html(state);
+                }
+            } else {
+                *capture = Some(RawHtmlCapture {
+                    tag: tag.name.to_string(),
+                    depth: 1,
+                    html,
+                });
+            }
+            return true;
+        };
+
+        match token {
+            Token::TagToken(tag) => {
+                if tag.name.as_ref() == current.tag && !tag.self_closing {
+                    match tag.kind {
+                        TagKind::StartTag => current.depth += 1,
+                        TagKind::EndTag => current.depth -= 1,
+                    }
+                }
+                current.html.push_str(&serialize_tag(tag));
+            }
+            Token::CharacterTokens(text) => {
+                current.html.push_str(&html_escape::encode_text(text));
+            }
+            _ => {}
+        }
+        if current.depth == 0
+            && let Some(finished) = capture.take()
+        {
+            if policy == RawHtmlPolicy::AsCodeBlock {
+                Self::push_raw_html(state, finished.html);
+            } else {
+                Self::drop_raw_html(state);
+            }
+        }
+        true
+    }
+
+    /// Joins the text around a dropped element with a single space rather than two.
+    fn drop_raw_html(state: &mut ADFBuilderState) {
+        if state.current_text.ends_with(char::is_whitespace) {
+            state.trim_text_seam = true;
+        }
+    }
+
+    /// Adds raw HTML as an `html` code block, splitting the paragraph it appears in. Contexts
+    /// that only hold inline content get it as inline code instead.
+    fn push_raw_html(state: &mut ADFBuilderState, html: String) {
+        let split_paragraph = matches!(state.stack.last(), Some(BlockContext::Paragraph(_)));
+        if split_paragraph {
+            // The whitespace before the element would be left at the end of the paragraph
+            let trimmed = state.current_text.trim_end().len();
+            state.current_text.truncate(trimmed);
+        }
+        Self::flush_text(state);
+        if split_paragraph {
+            Self::close_current_block(state);
+        }
+        match state.stack.last() {
+            Some(
+                BlockContext::Document(_)
+                | BlockContext::Blockquote(_)
+                | BlockContext::ListItem(_)
+                | BlockContext::TableBlockCell(..)
+                | BlockContext::TableBlockHeader(..)
+                | BlockContext::CustomBlock(
+                    CustomBlockType::Div
+                    | CustomBlockType::Expand
+                    | CustomBlockType::NestedExpand
+                    | CustomBlockType::Panel,
+                    _,
+                    _,
+                ),
+            ) => Self::push_node_block_to_parent(
+                state,
+                AdfBlockNode::CodeBlock {
+                    attrs: Some(CodeBlockAttrs {
+                        language: Some("html".to_string()),
+                    }),
+                    content: Some(vec![AdfNode::Text {
+                        text: html,
+                        marks: None,
+                    }]),
+                },
+            ),
+            _ => {
+                state.mark_stack.push(AdfMark::Code);
+                state.current_text = html;
+                Self::flush_text(state);
+                state.mark_stack.pop();
+            }
+        }
+        if split_paragraph {
+            // The rest of the paragraph continues after the code block, without the whitespace
+            // that followed the element
+            state.stack.push(BlockContext::Paragraph(vec![]));
+            state.trim_text_seam = true;
+        }
+    }
+
     pub fn push_node_to_parent(state: &mut ADFBuilderState, node: AdfNode) {
         let frame = state
             .stack
@@ -654,34 +1147,52 @@ impl ADFBuilder {
             BlockContext::Blockquote(nodes)
             | BlockContext::ListItem(nodes)
             | BlockContext::Document(nodes)
-            | BlockContext::TableBlockCell(nodes)
-            | BlockContext::TableBlockHeader(nodes) => {
+            | BlockContext::TableBlockCell(nodes, _)
+            | BlockContext::TableBlockHeader(nodes, _) => {
                 Self::push_into_last_paragraph(nodes, node);
             }
             BlockContext::CustomBlock(block_ty, nodes, _) => match block_ty {
-                CustomBlockType::Div | CustomBlockType::Expand | CustomBlockType::Panel => {
+                CustomBlockType::Div
+                | CustomBlockType::Expand
+                | CustomBlockType::NestedExpand
+                | CustomBlockType::Panel => {
                     Self::push_into_last_paragraph(nodes, node);
                 }
-                _ => panic!("Invalid block context for custom block: {block_ty:?} {node:?}"),
+                block_ty => {
+                    let parent = format!("CustomBlock({block_ty:?})");
+                    let error = state.unexpected_parent(&node.to_string(), parent);
+                    state.fail(error);
+                }
             },
             frame => {
-                panic!("Invalid block context for node: {frame:?} <-- {node:?}");
+                let parent = frame.describe();
+                let error = state.unexpected_parent(&node.to_string(), parent);
+                state.fail(error);
             }
         }
     }
 
-    pub fn extract_text(paragraph: &AdfBlockNode) -> String {
-        match paragraph {
-            AdfBlockNode::Paragraph {
-                content: Some(nodes),
-            } => nodes
-                .iter()
-                .filter_map(|n| match n {
-                    AdfNode::Text { text, .. } => Some(text.clone()),
-                    _ => None,
-                })
-                .collect::<String>(),
-            _ => String::new(),
+    /// Concatenates the text nodes of a paragraph, borrowing when there is only one.
+    pub fn extract_text(paragraph: &AdfBlockNode) -> Cow<'_, str> {
+        let AdfBlockNode::Paragraph {
+            content: Some(nodes),
+        } = paragraph
+        else {
+            return Cow::Borrowed("");
+        };
+        let mut texts = nodes.iter().filter_map(|n| match n {
+            AdfNode::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        });
+        match (texts.next(), texts.next()) {
+            (None, _) => Cow::Borrowed(""),
+            (Some(text), None) => Cow::Borrowed(text),
+            (Some(first), Some(second)) => {
+                let mut text = String::from(first);
+                text.push_str(second);
+                text.extend(texts);
+                Cow::Owned(text)
+            }
         }
     }
 }
@@ -702,11 +1213,24 @@ pub fn extract_style(style: &str, property: &str) -> Option<String> {
         .next()
 }
 
+/// Headings are inline-only, so a paragraph nested in one is flattened into it. A hard break
+/// keeps it apart from the text before it.
+fn flatten_into_heading(heading: &mut Vec<AdfNode>, paragraph: Vec<AdfNode>) {
+    if !heading.is_empty() && !paragraph.is_empty() {
+        heading.push(AdfNode::HardBreak);
+    }
+    heading.extend(paragraph);
+}
+
 impl TokenSink for ADFBuilder {
     type Handle = ();
 
     fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<Self::Handle> {
         let mut state = self.state.borrow_mut();
+        // Once the input is known to be malformed, the rest of it is only tokenized
+        if state.error.is_some() || self.capture_raw_html(&mut state, &token) {
+            return TokenSinkResult::Continue;
+        }
         match token {
             Token::TagToken(Tag {
                 kind: TagKind::StartTag,
@@ -763,7 +1287,12 @@ impl TokenSink for ADFBuilder {
                 }
             }
             Token::CharacterTokens(t) => {
-                state.current_text.push_str(&t);
+                let mut text: &str = &t;
+                if state.trim_text_seam {
+                    text = text.trim_start();
+                    state.trim_text_seam = text.is_empty();
+                }
+                state.current_text.push_str(text);
             }
             _ => {}
         }
@@ -771,18 +1300,50 @@ impl TokenSink for ADFBuilder {
     }
 }
 
+/// Serializes a tag back to HTML, for keeping elements that have no ADF equivalent.
+fn serialize_tag(tag: &Tag) -> String {
+    let mut html = String::from("<");
+    if tag.kind == TagKind::EndTag {
+        html.push('/');
+    }
+    html.push_str(&tag.name);
+    for attr in &tag.attrs {
+        html.push_str(&format!(
+            " {}=\"{}\"",
+            attr.name.local,
+            html_escape::encode_double_quoted_attribute(&attr.value)
+        ));
+    }
+    html.push_str(if tag.self_closing { " />" } else { ">" });
+    html
+}
+
 pub fn html_to_adf(input: &str) -> AdfBlockNode {
-    let mut queue: BufferQueue = Default::default();
-    queue.push_back(Tendril::from_slice(input));
+    html_to_adf_with_options(input, &ConvertOptions::default())
+}
+
+/// Converts HTML to ADF.
+///
+/// # Panics
+///
+/// Panics if the HTML nests elements in a way ADF can't represent; use
+/// [`try_html_to_adf_with_options`] to handle that instead.
+pub fn html_to_adf_with_options(input: &str, options: &ConvertOptions) -> AdfBlockNode {
+    try_html_to_adf_with_options(input, options).unwrap_or_else(|error| panic!("{error}"))
+}
 
-    let builder = ADFBuilder::new();
-    let tok = Tokenizer::new(builder, TokenizerOpts::default());
+/// Converts HTML to ADF with the default options, returning an error instead of panicking on
+/// malformed nesting.
+pub fn try_html_to_adf(input: &str) -> Result<AdfBlockNode, AdfConvertError> {
+    try_html_to_adf_with_options(input, &ConvertOptions::default())
+}
 
-    while !queue.is_empty() {
-        let _ = tok.feed(&mut queue);
-    }
-    tok.end();
-    tok.sink.emit()
+/// Converts HTML to ADF, returning an error instead of panicking on malformed nesting.
+pub fn try_html_to_adf_with_options(
+    input: &str,
+    options: &ConvertOptions,
+) -> Result<AdfBlockNode, AdfConvertError> {
+    ADFBuilder::with_options(options.clone()).parse(input)
 }
 
 #[cfg(test)]
@@ -790,8 +1351,10 @@ mod tests {
     use super::*;
 
     use crate::adf::adf_types::{
-        AdfNode, DecisionItem, DecisionItemAttrs, HeadingAttrs, LinkMark, ListItem, MediaAttrs,
-        MediaDataType, MediaNode, MediaSingleAttrs, MediaType, Subsup, TableRow, TableRowEntry,
+        AdfNode, BlockCardAttrs, DateAttrs, DecisionItem, DecisionItemAttrs, EmojiAttrs,
+        HeadingAttrs, InlineCardAttrs, LinkMark, ListItem, MediaAttrs, MediaDataType, MediaMark,
+        MediaNode, MediaSingleAttrs, MediaType, NestedAttrs, StatusAttrs, Subsup, TableRow,
+        TableRowEntry, TaskItemState,
     };
 
     fn assert_content_eq(adf: AdfBlockNode, expected: Vec<AdfBlockNode>) {
@@ -824,6 +1387,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clean_surrounding_text_multibyte_whitespace() {
+        // Non-breaking and ideographic spaces are multibyte; slicing must stay on char boundaries
+        assert_eq!(
+            clean_surrounding_text("\u{00A0}\nHeading 1\n\u{3000}"),
+            "Heading 1"
+        );
+        assert_eq!(
+            clean_surrounding_text("\n\u{3000}Heading 1\u{00A0}\n"),
+            "\u{3000}Heading 1\u{00A0}"
+        );
+        assert_eq!(
+            clean_surrounding_text("\u{3000}Heading 1\u{3000}"),
+            "\u{3000}Heading 1\u{3000}"
+        );
+        assert_eq!(clean_surrounding_text("\u{00A0}\n\u{3000}"), "");
+        assert_eq!(clean_surrounding_text("\u{3000}\n"), "");
+        assert_eq!(clean_surrounding_text("\n\u{00A0}"), "");
+        assert_eq!(clean_surrounding_text("é\n\u{3000}"), "é");
+    }
+
     #[test]
     fn test_blockquote() {
         let adf = html_to_adf(r#"<blockquote>Quoted text.</blockquote>"#);
@@ -890,52 +1474,273 @@ mod tests {
     }
 
     #[test]
-    fn test_combined_marks_splitting() {
+    fn test_code_block_after_loose_text_in_list_item() {
         let adf = html_to_adf(
-            r#"<p>Some text <a href="https://www.example.com">examples</a> are <strong><em>complicated</em></strong></p>"#,
+            r#"<ul><li>Run this:<pre><code>cargo test
+</code></pre>then check</li></ul>"#,
+        );
+        let text = |text: &str| {
+            Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }])
+        };
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![
+                    AdfBlockNode::Paragraph {
+                        content: text("Run this:"),
+                    },
+                    AdfBlockNode::CodeBlock {
+                        attrs: None,
+                        content: text("cargo test\n"),
+                    },
+                    AdfBlockNode::Paragraph {
+                        content: text("then check"),
+                    },
+                ])],
+            }],
+        );
+    }
+
+    #[test]
+    fn test_empty_lists_are_dropped() {
+        let adf = html_to_adf(r#"<ul></ul><ol></ol><p>After lists</p>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "After lists".into(),
+                    marks: None,
+                }]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_empty_paragraphs_are_normalized() {
+        // Handlers may push `Some(vec![])`; emit serializes it as a bare paragraph
+        let mut builder = ADFBuilder::new();
+        builder.add_start_handler("adf-blank", |state, _| {
+            if let Some(BlockContext::TableBlockCell(nodes, _)) = state.stack.last_mut() {
+                nodes.push(AdfBlockNode::Paragraph {
+                    content: Some(vec![]),
+                });
+            }
+            true
+        });
+        let adf = builder
+            .parse("<table><tr><td><adf-blank></adf-blank></td></tr></table>")
+            .unwrap();
+        let empty_cell = serde_json::json!({
+            "type": "tableCell",
+            "content": [{ "type": "paragraph" }],
+        });
+        assert_eq!(
+            serde_json::to_value(&adf).unwrap()["content"][0]["content"][0]["content"],
+            serde_json::json!([empty_cell])
+        );
+    }
+
+    #[test]
+    fn test_double_break_splits_paragraph() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+
+        let adf = html_to_adf(r#"<p>First<br><br>Second</p>"#);
+        assert_content_eq(adf, vec![paragraph("First"), paragraph("Second")]);
+
+        let adf = html_to_adf("<blockquote>First<br>\n<br>Second</blockquote>");
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Blockquote {
+                content: vec![paragraph("First"), paragraph("Second")],
+            }],
+        );
+
+        // Breaks beyond the pair, and whitespace after them, don't carry into the next paragraph
+        let adf = html_to_adf("<p>First<br><br><br> Second<br><br><br></p>");
+        assert_content_eq(adf, vec![paragraph("First"), paragraph("Second")]);
+
+        let adf = html_to_adf("<blockquote>First<br><br><br>Second</blockquote>");
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Blockquote {
+                content: vec![paragraph("First"), paragraph("Second")],
+            }],
+        );
+
+        let adf = html_to_adf(r#"<ul><li>Only<br><br></li></ul>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![paragraph("Only")])],
+            }],
         );
+
+        let options = ConvertOptions {
+            split_on_double_break: false,
+            ..Default::default()
+        };
+        let adf = html_to_adf_with_options(r#"<p>First<br><br>Second</p>"#, &options);
         assert_content_eq(
             adf,
             vec![AdfBlockNode::Paragraph {
                 content: Some(vec![
                     AdfNode::Text {
-                        text: "Some text ".into(),
+                        text: "First".into(),
                         marks: None,
                     },
+                    AdfNode::HardBreak,
+                    AdfNode::HardBreak,
                     AdfNode::Text {
-                        text: "examples".into(),
-                        marks: Some(vec![AdfMark::Link(LinkMark {
-                            href: "https://www.example.com".into(),
-                            ..Default::default()
-                        })]),
-                    },
-                    AdfNode::Text {
-                        text: " are ".into(),
+                        text: "Second".into(),
                         marks: None,
                     },
-                    AdfNode::Text {
-                        text: "complicated".into(),
-                        marks: Some(vec![AdfMark::Strong, AdfMark::Em]),
-                    },
                 ]),
             }],
         );
     }
 
     #[test]
-    fn test_subsup_underline() {
+    fn test_styled_div_with_emoji_is_paragraph() {
         let adf = html_to_adf(
-            r#"<p>This is <u>underlined</u> and <sub>subscript</sub> and <sup>superscript</sup>.</p>"#,
+            r#"<div style="color: red">Hello <adf-emoji aria-label=":smile:">😄</adf-emoji> world</div>"#,
         );
         assert_content_eq(
             adf,
             vec![AdfBlockNode::Paragraph {
                 content: Some(vec![
                     AdfNode::Text {
-                        text: "This is ".into(),
+                        text: "Hello ".into(),
                         marks: None,
                     },
-                    AdfNode::Text {
+                    AdfNode::Emoji {
+                        attrs: EmojiAttrs {
+                            id: None,
+                            short_name: ":smile:".into(),
+                            text: Some("😄".into()),
+                        },
+                    },
+                    AdfNode::Text {
+                        text: " world".into(),
+                        marks: None,
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_table_cell_background_is_normalized() {
+        let adf = html_to_adf(
+            r#"<table><tr><td style="background-color: #ABC">a</td><td style="background-color: rgb(227, 252, 239)">b</td></tr></table>"#,
+        );
+        let AdfBlockNode::Doc { content, .. } = adf else {
+            panic!("Expected a document");
+        };
+        let AdfBlockNode::Table { content: rows, .. } = &content[0] else {
+            panic!("Expected a table");
+        };
+        let backgrounds: Vec<_> = rows[0]
+            .content()
+            .iter()
+            .map(|entry| match entry {
+                TableRowEntry::TableCell(cell) => cell.attrs().clone().and_then(|a| a.background),
+                TableRowEntry::TableHeader(header) => {
+                    header.attrs().clone().and_then(|a| a.background)
+                }
+            })
+            .collect();
+        assert_eq!(
+            backgrounds,
+            vec![Some("#aabbcc".to_string()), Some("#e3fcef".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_paragraph_nested_in_heading_is_flattened() {
+        let expected = vec![AdfBlockNode::Heading {
+            attrs: HeadingAttrs {
+                level: 2,
+                ..Default::default()
+            },
+            content: Some(vec![AdfNode::Text {
+                text: "Nested".into(),
+                marks: None,
+            }]),
+        }];
+        assert_content_eq(html_to_adf("<h2><p>Nested</p></h2>"), expected.clone());
+        assert_content_eq(html_to_adf("<h2><div>Nested</div></h2>"), expected);
+
+        // Sibling paragraphs stay apart
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let expected = vec![AdfBlockNode::Heading {
+            attrs: HeadingAttrs {
+                level: 2,
+                ..Default::default()
+            },
+            content: Some(vec![text("a"), AdfNode::HardBreak, text("b")]),
+        }];
+        assert_content_eq(html_to_adf("<h2><p>a</p><p>b</p></h2>"), expected.clone());
+        assert_content_eq(html_to_adf("<h2><div>a</div><div>b</div></h2>"), expected);
+    }
+
+    #[test]
+    fn test_combined_marks_splitting() {
+        let adf = html_to_adf(
+            r#"<p>Some text <a href="https://www.example.com">examples</a> are <strong><em>complicated</em></strong></p>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Some text ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "examples".into(),
+                        marks: Some(vec![AdfMark::Link(LinkMark {
+                            href: "https://www.example.com".into(),
+                            ..Default::default()
+                        })]),
+                    },
+                    AdfNode::Text {
+                        text: " are ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "complicated".into(),
+                        marks: Some(vec![AdfMark::Strong, AdfMark::Em]),
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_subsup_underline() {
+        let adf = html_to_adf(
+            r#"<p>This is <u>underlined</u> and <sub>subscript</sub> and <sup>superscript</sup>.</p>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "This is ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
                         text: "underlined".into(),
                         marks: Some(vec![AdfMark::Underline]),
                     },
@@ -964,6 +1769,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ins_as_underline() {
+        let adf = html_to_adf(r#"<p>This was <ins>inserted</ins> later.</p>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "This was ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "inserted".into(),
+                        marks: Some(vec![AdfMark::Underline]),
+                    },
+                    AdfNode::Text {
+                        text: " later.".into(),
+                        marks: None,
+                    },
+                ]),
+            }],
+        );
+    }
+
     #[test]
     fn test_span_styles() {
         let adf = html_to_adf(
@@ -998,6 +1827,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_code_block_data_language() {
+        let expected = vec![AdfBlockNode::CodeBlock {
+            attrs: Some(CodeBlockAttrs {
+                language: Some("rust".into()),
+            }),
+            content: Some(vec![AdfNode::Text {
+                text: "let x = 42;".into(),
+                marks: None,
+            }]),
+        }];
+        for html in [
+            r#"<pre data-language="rust"><code>let x = 42;</code></pre>"#,
+            r#"<pre><code data-language="rust">let x = 42;</code></pre>"#,
+            r#"<pre><code class="hljs language-rust">let x = 42;</code></pre>"#,
+        ] {
+            assert_content_eq(html_to_adf(html), expected.clone());
+        }
+    }
+
+    #[test]
+    fn test_pre_without_code() {
+        let adf = html_to_adf("<pre>  indented\n  code\n\tand a tab</pre>");
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::CodeBlock {
+                attrs: None,
+                content: Some(vec![AdfNode::Text {
+                    text: "  indented\n  code\n\tand a tab".into(),
+                    marks: None,
+                }]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_code_block_empty_language_class() {
+        let expected = vec![AdfBlockNode::CodeBlock {
+            attrs: None,
+            content: Some(vec![AdfNode::Text {
+                text: "plain".into(),
+                marks: None,
+            }]),
+        }];
+        for html in [
+            r#"<pre><code class="language-">plain</code></pre>"#,
+            r#"<pre><code class="hljs language-" data-language=" ">plain</code></pre>"#,
+        ] {
+            assert_content_eq(html_to_adf(html), expected.clone());
+        }
+    }
+
+    #[test]
+    fn test_code_block_language_normalization() {
+        let options = ConvertOptions {
+            normalize_code_language: true,
+            ..Default::default()
+        };
+        for (class, expected) in [
+            ("js", "javascript"),
+            ("py", "python"),
+            ("sh", "bash"),
+            ("TS", "typescript"),
+            ("rust", "rust"),
+            ("Haskell", "haskell"),
+        ] {
+            let html = format!(r#"<pre><code class="language-{class}">x</code></pre>"#);
+            let code_block = |language: &str| AdfBlockNode::CodeBlock {
+                attrs: Some(CodeBlockAttrs {
+                    language: Some(language.into()),
+                }),
+                content: Some(vec![AdfNode::Text {
+                    text: "x".into(),
+                    marks: None,
+                }]),
+            };
+            assert_content_eq(
+                html_to_adf_with_options(&html, &options),
+                vec![code_block(expected)],
+            );
+            // Languages are kept as written unless normalization is enabled
+            assert_content_eq(html_to_adf(&html), vec![code_block(class)]);
+        }
+    }
+
+    #[test]
+    fn test_nested_code_inside_pre_is_verbatim() {
+        let adf = html_to_adf(r#"<pre><code>a <code>b</code> c</code></pre>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::CodeBlock {
+                attrs: None,
+                content: Some(vec![AdfNode::Text {
+                    text: "a b c".into(),
+                    marks: None,
+                }]),
+            }],
+        );
+    }
+
     #[test]
     fn test_code_inside_pre_and_outside_pre() {
         let adf = html_to_adf(
@@ -1132,6 +2061,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_table_cells_without_row() {
+        let adf = html_to_adf(
+            r#"<table><th>Name</th><th>Value</th><tr><td>a</td><td>1</td></tr><td>b</td></table>"#,
+        );
+        let cell = |text: &str| {
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: text.into(),
+                    marks: None,
+                }]),
+            }]
+        };
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(cell("Name"), None),
+                        TableRowEntry::new_table_header(cell("Value"), None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_cell(cell("a"), None),
+                        TableRowEntry::new_table_cell(cell("1"), None),
+                    ]),
+                    TableRow::new(vec![TableRowEntry::new_table_cell(cell("b"), None)]),
+                ],
+            }],
+        );
+    }
+
+    #[test]
+    fn test_table_head_data_cells_are_headers() {
+        let adf = html_to_adf(
+            r#"<table>
+                <thead><tr><td>Name</td><th>Value</th></tr></thead>
+                <tbody><tr><td>a</td><td>1</td></tr></tbody>
+            </table>"#,
+        );
+        let cell = |text: &str| {
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: text.into(),
+                    marks: None,
+                }]),
+            }]
+        };
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Table {
+                attrs: None,
+                content: vec![
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_header(cell("Name"), None),
+                        TableRowEntry::new_table_header(cell("Value"), None),
+                    ]),
+                    TableRow::new(vec![
+                        TableRowEntry::new_table_cell(cell("a"), None),
+                        TableRowEntry::new_table_cell(cell("1"), None),
+                    ]),
+                ],
+            }],
+        );
+    }
+
     #[test]
     fn test_media_parsing() {
         let adf = html_to_adf(
@@ -1163,7 +2158,66 @@ mod tests {
                 }],
                 attrs: MediaSingleAttrs {
                     layout: "align-start".to_string(),
+                    ..Default::default()
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn test_media_single_missing_attributes() {
+        let adf = html_to_adf(
+            r#"<adf-media-single><a>no target</a><img data-media-id="m1"></adf-media-single>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::MediaSingle {
+                attrs: MediaSingleAttrs {
+                    layout: "center".into(),
+                    ..Default::default()
                 },
+                content: vec![MediaNode {
+                    media_type: MediaType::Media,
+                    attrs: MediaAttrs {
+                        id: "m1".into(),
+                        type_: MediaDataType::File,
+                        ..Default::default()
+                    },
+                    marks: None,
+                }],
+            }],
+        );
+    }
+
+    #[test]
+    fn test_inline_image_is_media_inline() {
+        let adf = html_to_adf(
+            r#"<p>text <img src="https://example.com/a.png" alt="diagram" style="width: 16px"> more</p>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "text ".into(),
+                        marks: None,
+                    },
+                    AdfNode::MediaInline {
+                        attrs: MediaAttrs {
+                            alt: Some("diagram".into()),
+                            width: Some(16),
+                            ..Default::default()
+                        },
+                        marks: Some(vec![MediaMark::Link(LinkMark {
+                            href: "https://example.com/a.png".into(),
+                            ..Default::default()
+                        })]),
+                    },
+                    AdfNode::Text {
+                        text: " more".into(),
+                        marks: None,
+                    },
+                ]),
             }],
         );
     }
@@ -1216,46 +2270,275 @@ mod tests {
     }
 
     #[test]
-    fn test_br_inside_paragraph() {
-        let adf = html_to_adf(r#"<p>First line<br/>Second line</p>"#);
+    fn test_lists_promoted_without_local_data() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let task = |text_: &str, local_id: &str, state: TaskItemState| {
+            TaskItem::new(
+                vec![text(text_)],
+                TaskItemAttrs {
+                    local_id: local_id.to_string(),
+                    state,
+                },
+            )
+        };
+
+        // Marker present: the list keeps its local id
+        let adf = html_to_adf(
+            r#"<adf-local-data data-tag="task-list" id="list"></adf-local-data>
+            <ul><li><input type="checkbox" id="a"> Ship</li></ul>"#,
+        );
         assert_content_eq(
             adf,
-            vec![AdfBlockNode::Paragraph {
-                content: Some(vec![
-                    AdfNode::Text {
-                        text: "First line".into(),
-                        marks: None,
-                    },
-                    AdfNode::HardBreak,
-                    AdfNode::Text {
-                        text: "Second line".into(),
-                        marks: None,
-                    },
-                ]),
+            vec![AdfBlockNode::TaskList {
+                attrs: LocalId {
+                    local_id: "list".into(),
+                },
+                content: vec![task("Ship", "a", TaskItemState::Todo)],
             }],
         );
-    }
 
-    #[test]
-    fn test_hr_between_paragraphs() {
-        let adf = html_to_adf(r#"<p>Before rule</p><hr/><p>After rule</p>"#);
+        // Marker absent: Markdown checkboxes become a task list with generated ids
+        let adf = crate::markdown::markdown_to_adf(
+            "- [ ] Ship
+- [x] Announce",
+        )
+        .unwrap();
         assert_content_eq(
             adf,
-            vec![
-                AdfBlockNode::Paragraph {
-                    content: Some(vec![AdfNode::Text {
-                        text: "Before rule".into(),
-                        marks: None,
-                    }]),
-                },
-                AdfBlockNode::Rule,
-                AdfBlockNode::Paragraph {
-                    content: Some(vec![AdfNode::Text {
-                        text: "After rule".into(),
-                        marks: None,
-                    }]),
+            vec![AdfBlockNode::TaskList {
+                attrs: LocalId {
+                    local_id: "00000000-0000-4000-8000-000000000003".into(),
                 },
-            ],
+                content: vec![
+                    task(
+                        "Ship",
+                        "00000000-0000-4000-8000-000000000001",
+                        TaskItemState::Todo,
+                    ),
+                    task(
+                        "Announce",
+                        "00000000-0000-4000-8000-000000000002",
+                        TaskItemState::Done,
+                    ),
+                ],
+            }],
+        );
+
+        // Marker absent: hand-written decision items become a decision list
+        let adf =
+            html_to_adf(r#"<ul><li><adf-decision-item id="d">Do it</adf-decision-item></li></ul>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::DecisionList {
+                content: vec![DecisionItem::new(
+                    vec![text("Do it")],
+                    DecisionItemAttrs {
+                        local_id: "d".into(),
+                        state: DecisionItemState,
+                    },
+                )],
+                attrs: LocalId {
+                    local_id: "00000000-0000-4000-8000-000000000001".into(),
+                },
+            }],
+        );
+
+        // Only some items are tasks, so the list stays a bullet list and keeps their text
+        let adf =
+            html_to_adf(r#"<ul><li><input type="checkbox" checked> Done</li><li>Plain</li></ul>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![
+                    ListItem::new(vec![AdfBlockNode::paragraph_from(vec![text("Done")])]),
+                    ListItem::new(vec![AdfBlockNode::paragraph_from(vec![text("Plain")])]),
+                ],
+            }],
+        );
+    }
+
+    #[test]
+    fn test_checkbox_outside_list_item_start() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+
+        // Only a checkbox opening the innermost list item starts a task
+        let adf = html_to_adf(
+            r#"<ul><li><p>Intro</p><table><tr><td><input type="checkbox"> x</td></tr></table></li></ul>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![
+                    AdfBlockNode::paragraph_from(vec![text("Intro")]),
+                    AdfBlockNode::Table {
+                        attrs: None,
+                        content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                            vec![AdfBlockNode::paragraph_from(vec![text("[ ] x")])],
+                            None,
+                        )])],
+                    },
+                ])],
+            }],
+        );
+
+        let adf = html_to_adf(
+            r#"<ul><li><blockquote><p>q <input type="checkbox"></p></blockquote></li></ul>"#,
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![AdfBlockNode::Blockquote {
+                    content: vec![AdfBlockNode::paragraph_from(vec![text("q [ ]")])],
+                }])],
+            }],
+        );
+
+        // Elsewhere the checkbox keeps its state as text
+        let adf = html_to_adf(r#"<p>before <input type="checkbox" checked> after</p>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::paragraph_from(vec![text("before [x] after")])],
+        );
+    }
+
+    #[test]
+    fn test_raw_html_policy() {
+        let markdown = "Intro\n\nWatch <iframe src=\"https://example.com/embed?a=1&amp;b=2\" width=\"560\">No frames</iframe> now";
+        let parse = |policy: RawHtmlPolicy| {
+            let options = ConvertOptions {
+                raw_html_policy: policy,
+                ..Default::default()
+            };
+            crate::markdown::markdown_to_adf_with_options(markdown, &options).unwrap()
+        };
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+
+        assert_content_eq(
+            parse(RawHtmlPolicy::Drop),
+            vec![paragraph("Intro"), paragraph("Watch now")],
+        );
+        assert_content_eq(
+            parse(RawHtmlPolicy::AsUnknownPassthrough),
+            vec![paragraph("Intro"), paragraph("Watch No frames now")],
+        );
+        assert_content_eq(
+            parse(RawHtmlPolicy::AsCodeBlock),
+            vec![
+                paragraph("Intro"),
+                paragraph("Watch"),
+                AdfBlockNode::CodeBlock {
+                    attrs: Some(CodeBlockAttrs {
+                        language: Some("html".into()),
+                    }),
+                    content: Some(vec![AdfNode::Text {
+                        text: r#"<iframe src="https://example.com/embed?a=1&amp;b=2" width="560">No frames</iframe>"#.into(),
+                        marks: None,
+                    }]),
+                },
+                paragraph("now"),
+            ],
+        );
+
+        // Where a block can't go, the HTML is kept as inline code
+        let options = ConvertOptions {
+            raw_html_policy: RawHtmlPolicy::AsCodeBlock,
+            ..Default::default()
+        };
+        assert_content_eq(
+            html_to_adf_with_options(r#"<h2>Demo <embed src="demo.swf"></h2>"#, &options),
+            vec![AdfBlockNode::Heading {
+                attrs: HeadingAttrs {
+                    level: 2,
+                    ..Default::default()
+                },
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Demo ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: r#"<embed src="demo.swf">"#.into(),
+                        marks: Some(vec![AdfMark::Code]),
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_date_display_text_is_dropped() {
+        let adf =
+            html_to_adf(r#"<p>Due <time datetime="2024-03-05T00:00:00+00:00">March 5</time></p>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Due ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Date {
+                        attrs: DateAttrs {
+                            timestamp: "1709596800000".into(),
+                        },
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_br_inside_paragraph() {
+        let adf = html_to_adf(r#"<p>First line<br/>Second line</p>"#);
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "First line".into(),
+                        marks: None,
+                    },
+                    AdfNode::HardBreak,
+                    AdfNode::Text {
+                        text: "Second line".into(),
+                        marks: None,
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_hr_between_paragraphs() {
+        let adf = html_to_adf(r#"<p>Before rule</p><hr/><p>After rule</p>"#);
+        assert_content_eq(
+            adf,
+            vec![
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "Before rule".into(),
+                        marks: None,
+                    }]),
+                },
+                AdfBlockNode::Rule,
+                AdfBlockNode::Paragraph {
+                    content: Some(vec![AdfNode::Text {
+                        text: "After rule".into(),
+                        marks: None,
+                    }]),
+                },
+            ],
         );
     }
 
@@ -1273,21 +2556,30 @@ mod tests {
             adf,
             vec![
                 AdfBlockNode::Heading {
-                    attrs: HeadingAttrs { level: 1 },
+                    attrs: HeadingAttrs {
+                        level: 1,
+                        ..Default::default()
+                    },
                     content: Some(vec![AdfNode::Text {
                         text: "Main Heading".into(),
                         marks: None,
                     }]),
                 },
                 AdfBlockNode::Heading {
-                    attrs: HeadingAttrs { level: 2 },
+                    attrs: HeadingAttrs {
+                        level: 2,
+                        ..Default::default()
+                    },
                     content: Some(vec![AdfNode::Text {
                         text: "Sub Heading".into(),
                         marks: None,
                     }]),
                 },
                 AdfBlockNode::Heading {
-                    attrs: HeadingAttrs { level: 3 },
+                    attrs: HeadingAttrs {
+                        level: 3,
+                        ..Default::default()
+                    },
                     content: Some(vec![
                         AdfNode::Text {
                             text: "Marked".into(),
@@ -1302,4 +2594,519 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_trim_document_edge_paragraphs() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let html = r#"<p><br></p><p>First</p><p><br></p><p>Second</p><p><br></p><p><br></p>"#;
+        let empty = AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::HardBreak]),
+        };
+
+        assert_content_eq(
+            html_to_adf(html),
+            vec![paragraph("First"), empty.clone(), paragraph("Second")],
+        );
+
+        let options = ConvertOptions {
+            trim_document_paragraphs: false,
+            ..Default::default()
+        };
+        assert_content_eq(
+            html_to_adf_with_options(html, &options),
+            vec![
+                empty.clone(),
+                paragraph("First"),
+                empty.clone(),
+                paragraph("Second"),
+                empty.clone(),
+                empty,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_leading_byte_order_mark() {
+        let html = "<h1>Title</h1><p>Body</p>";
+        assert_eq!(html_to_adf(&format!("\u{FEFF}{html}")), html_to_adf(html));
+
+        let markdown = "# Title\n\nBody";
+        let expected = crate::markdown::markdown_to_adf(markdown).unwrap();
+        assert_eq!(
+            crate::markdown::markdown_to_adf(&format!("\u{FEFF}{markdown}")).unwrap(),
+            expected
+        );
+        assert!(matches!(
+            &expected,
+            AdfBlockNode::Doc { content, .. } if matches!(content[0], AdfBlockNode::Heading { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extract_text() {
+        let single = AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: "Only".to_string(),
+                marks: None,
+            }]),
+        };
+        assert!(matches!(
+            ADFBuilder::extract_text(&single),
+            Cow::Borrowed("Only")
+        ));
+
+        let several = AdfBlockNode::Paragraph {
+            content: Some(vec![
+                AdfNode::Text {
+                    text: "One".to_string(),
+                    marks: None,
+                },
+                AdfNode::HardBreak,
+                AdfNode::Text {
+                    text: " two".to_string(),
+                    marks: Some(vec![AdfMark::Strong]),
+                },
+                AdfNode::Text {
+                    text: " three".to_string(),
+                    marks: None,
+                },
+            ]),
+        };
+        assert_eq!(ADFBuilder::extract_text(&several), "One two three");
+        assert_eq!(ADFBuilder::extract_text(&AdfBlockNode::Rule), "");
+    }
+
+    #[test]
+    fn test_promote_standalone_cards() {
+        let card =
+            r#"<a href="https://example.com" data-inline-card="true">https://example.com</a>"#;
+        let inline_card = AdfNode::InlineCard {
+            attrs: InlineCardAttrs {
+                url: Some("https://example.com".into()),
+            },
+        };
+        let block_card = AdfBlockNode::BlockCard {
+            attrs: BlockCardAttrs {
+                datasource: None,
+                url: "https://example.com".into(),
+            },
+        };
+        let options = ConvertOptions {
+            promote_standalone_cards: true,
+            ..Default::default()
+        };
+
+        // Off by default, so a paragraph holding only an inline card still round-trips
+        assert_content_eq(
+            html_to_adf(&format!("<p>{card}</p>")),
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![inline_card.clone()]),
+            }],
+        );
+        assert_content_eq(
+            html_to_adf_with_options(&format!("<p>{card}</p><p>{card}</p>"), &options),
+            vec![block_card.clone(), block_card.clone()],
+        );
+
+        // A card in running text stays inline
+        assert_content_eq(
+            html_to_adf_with_options(&format!("<p>See {card}</p>"), &options),
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "See ".into(),
+                        marks: None,
+                    },
+                    inline_card.clone(),
+                ]),
+            }],
+        );
+
+        // Table cells take block cards, but list items don't
+        assert_content_eq(
+            html_to_adf_with_options(
+                &format!("<table><tr><td>{card}</td></tr></table><ul><li>{card}</li></ul>"),
+                &options,
+            ),
+            vec![
+                AdfBlockNode::Table {
+                    attrs: None,
+                    content: vec![TableRow::new(vec![TableRowEntry::new_table_cell(
+                        vec![block_card],
+                        None,
+                    )])],
+                },
+                AdfBlockNode::BulletList {
+                    content: vec![ListItem::new(vec![AdfBlockNode::Paragraph {
+                        content: Some(vec![inline_card]),
+                    }])],
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_nested_identical_marks() {
+        let adf = html_to_adf(
+            r#"<p>Very <strong><strong>bold</strong></strong> and <em>so <strong><em>on</em></strong></em></p>"#,
+        );
+        let text = |text: &str, marks: Option<Vec<AdfMark>>| AdfNode::Text {
+            text: text.into(),
+            marks,
+        };
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    text("Very ", None),
+                    text("bold", Some(vec![AdfMark::Strong])),
+                    text(" and ", None),
+                    text("so ", Some(vec![AdfMark::Em])),
+                    text("on", Some(vec![AdfMark::Em, AdfMark::Strong])),
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_block_card_text_is_dropped() {
+        let adf = html_to_adf(
+            "<ul><li><adf-block-card data-block-card=\"https://example.com\">https://example.com\
+             </adf-block-card>After</li></ul>",
+        );
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::BulletList {
+                content: vec![ListItem::new(vec![
+                    AdfBlockNode::BlockCard {
+                        attrs: BlockCardAttrs {
+                            url: "https://example.com".into(),
+                            datasource: None,
+                        },
+                    },
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![AdfNode::Text {
+                            text: "After".into(),
+                            marks: None,
+                        }]),
+                    },
+                ])],
+            }],
+        );
+    }
+
+    #[test]
+    fn test_try_html_to_adf_errors() {
+        assert_eq!(
+            try_html_to_adf("<h1>Title</h2>"),
+            Err(AdfConvertError::MismatchedClose {
+                tag: "h2".into(),
+                stack: vec!["Document".into(), "Heading".into()],
+            })
+        );
+        assert_eq!(
+            try_html_to_adf("<td>Cell</td>"),
+            Err(AdfConvertError::UnexpectedParentFor {
+                child: "TableBlockCell".into(),
+                parent: "Document".into(),
+                stack: vec!["Document".into()],
+            })
+        );
+        assert_eq!(
+            try_html_to_adf("<p><a data-inline-card>Card</a></p>"),
+            Err(AdfConvertError::InvalidElement {
+                tag: "a".into(),
+                reason: "inline card without an href".into(),
+            })
+        );
+        assert!(matches!(
+            try_html_to_adf("<p>Text</li>"),
+            Err(AdfConvertError::UnexpectedClose { .. })
+        ));
+        // Stray closing tags of containers fail instead of popping the document
+        for tag in ["table", "details", "adf-media-single", "adf-media-group"] {
+            assert_eq!(
+                try_html_to_adf(&format!("<p>Text</p></{tag}>")),
+                Err(AdfConvertError::MismatchedClose {
+                    tag: tag.into(),
+                    stack: vec!["Document".into()],
+                }),
+                "</{tag}>"
+            );
+        }
+        assert_eq!(
+            try_html_to_adf("<details><p>Text</table></details>"),
+            Err(AdfConvertError::MismatchedClose {
+                tag: "table".into(),
+                stack: vec![
+                    "Document".into(),
+                    "CustomBlock(Expand)".into(),
+                    "Paragraph".into()
+                ],
+            })
+        );
+        assert_eq!(
+            try_html_to_adf("<p>Text</p>"),
+            Ok(html_to_adf("<p>Text</p>"))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched </h2>")]
+    fn test_html_to_adf_panics_on_error() {
+        html_to_adf("<h1>Title</h2>");
+    }
+
+    #[test]
+    fn test_preserve_leading_whitespace() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let html = "<p>\n  indented\n</p><p>  first</p>";
+        let options = ConvertOptions {
+            preserve_leading_whitespace: true,
+            ..Default::default()
+        };
+
+        // The line break opening the run goes either way, leaving the indentation after it
+        for adf in [html_to_adf(html), html_to_adf_with_options(html, &options)] {
+            assert_content_eq(
+                adf,
+                vec![
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![text("  indented")]),
+                    },
+                    AdfBlockNode::Paragraph {
+                        content: Some(vec![text("  first")]),
+                    },
+                ],
+            );
+        }
+
+        let html = r#"<ul><li><input type="checkbox" id="a">  Ship  </li></ul>"#;
+        let task_text = |adf: AdfBlockNode| {
+            let AdfBlockNode::Doc { content, .. } = adf else {
+                panic!("Expected a document");
+            };
+            let AdfBlockNode::TaskList { content, .. } = &content[0] else {
+                panic!("Expected a task list");
+            };
+            content[0].content().to_vec()
+        };
+        assert_eq!(task_text(html_to_adf(html)), vec![text("Ship")]);
+        assert_eq!(
+            task_text(html_to_adf_with_options(html, &options)),
+            vec![text("  Ship")]
+        );
+    }
+
+    #[test]
+    fn test_builder_custom_handlers() {
+        let mut builder = ADFBuilder::new();
+        builder.add_start_handler("adf-math", |state, _| {
+            ADFBuilder::flush_text(state);
+            state.mark_stack.push(AdfMark::Code);
+            true
+        });
+        builder.add_end_handler("adf-math", |state, _| {
+            ADFBuilder::flush_text(state);
+            ADFBuilder::pop_mark(state, |mark| *mark == AdfMark::Code);
+            true
+        });
+        let html = "<p>Area: <adf-math>pi r^2</adf-math></p>";
+        assert_content_eq(
+            builder.parse(html).unwrap(),
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    AdfNode::Text {
+                        text: "Area: ".into(),
+                        marks: None,
+                    },
+                    AdfNode::Text {
+                        text: "pi r^2".into(),
+                        marks: Some(vec![AdfMark::Code]),
+                    },
+                ]),
+            }],
+        );
+
+        // Without the handlers the tag is ignored and its text kept
+        assert_content_eq(
+            html_to_adf(html),
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![AdfNode::Text {
+                    text: "Area: pi r^2".into(),
+                    marks: None,
+                }]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_interpret_markdown_in_text() {
+        let text = |text: &str| AdfNode::Text {
+            text: text.into(),
+            marks: None,
+        };
+        let paragraph = |nodes: Vec<AdfNode>| AdfBlockNode::Paragraph {
+            content: Some(nodes),
+        };
+        let html = "<p>## Heading</p><p>- One<br>- Two</p><p>3. Three</p><p>#hashtag and 2024. Plain</p>\
+            <p><strong># Bold</strong></p>";
+        let options = ConvertOptions {
+            interpret_markdown_in_text: true,
+            ..Default::default()
+        };
+
+        // Off by default, so the text stays as written
+        let AdfBlockNode::Doc { content, .. } = html_to_adf(html) else {
+            panic!("Expected a document");
+        };
+        assert_eq!(content.len(), 5);
+        assert_eq!(content[0], paragraph(vec![text("## Heading")]));
+
+        assert_content_eq(
+            html_to_adf_with_options(html, &options),
+            vec![
+                AdfBlockNode::Heading {
+                    attrs: HeadingAttrs {
+                        level: 2,
+                        ..Default::default()
+                    },
+                    content: Some(vec![text("Heading")]),
+                },
+                AdfBlockNode::BulletList {
+                    content: vec![
+                        ListItem::new(vec![paragraph(vec![text("One")])]),
+                        ListItem::new(vec![paragraph(vec![text("Two")])]),
+                    ],
+                },
+                AdfBlockNode::OrderedList {
+                    content: vec![ListItem::new(vec![paragraph(vec![text("Three")])])],
+                    attrs: Some(OrderedListAttrs { order: Some(3) }),
+                },
+                paragraph(vec![text("#hashtag and 2024. Plain")]),
+                paragraph(vec![AdfNode::Text {
+                    text: "# Bold".into(),
+                    marks: Some(vec![AdfMark::Strong]),
+                }]),
+            ],
+        );
+
+        // Escaped markup in the text stays text rather than becoming elements
+        let heading = |content: &str| AdfBlockNode::Heading {
+            attrs: HeadingAttrs {
+                level: 1,
+                ..Default::default()
+            },
+            content: Some(vec![text(content)]),
+        };
+        let bullet = |content: &str| AdfBlockNode::BulletList {
+            content: vec![ListItem::new(vec![paragraph(vec![text(content)])])],
+        };
+        let html = "<p># a &lt;b&gt;x&lt;/b&gt; &amp;amp; &lt;/li&gt;</p>\
+            <p>- item &lt;table&gt;&lt;tr&gt;&lt;td&gt;c&lt;/td&gt;&lt;/tr&gt;&lt;/table&gt;</p>";
+        assert_content_eq(
+            html_to_adf_with_options(html, &options),
+            vec![
+                heading("a <b>x</b> &amp; </li>"),
+                bullet("item <table><tr><td>c</td></tr></table>"),
+            ],
+        );
+
+        // Tags in the text are never reparsed as raw HTML, whatever the policy
+        let drop_html = ConvertOptions {
+            raw_html_policy: RawHtmlPolicy::Drop,
+            ..options.clone()
+        };
+        assert_content_eq(
+            html_to_adf_with_options("<p>- &lt;video&gt;v&lt;/video&gt;</p>", &drop_html),
+            vec![bullet("<video>v</video>")],
+        );
+    }
+
+    #[test]
+    fn test_status_with_nested_inline() {
+        let status = |text: &str| AdfNode::Status {
+            attrs: StatusAttrs {
+                color: "neutral".into(),
+                local_id: None,
+                text: text.into(),
+            },
+        };
+
+        let adf = html_to_adf("<p><adf-status>Done <adf-emoji>😄</adf-emoji></adf-status></p>");
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![status("Done 😄")]),
+            }],
+        );
+
+        let adf = html_to_adf("<p><adf-status>Done <strong>now</strong></adf-status> after</p>");
+        assert_content_eq(
+            adf,
+            vec![AdfBlockNode::Paragraph {
+                content: Some(vec![
+                    status("Done now"),
+                    AdfNode::Text {
+                        text: " after".into(),
+                        marks: None,
+                    },
+                ]),
+            }],
+        );
+    }
+
+    #[test]
+    fn test_details_without_summary() {
+        let paragraph = |text: &str| AdfBlockNode::Paragraph {
+            content: Some(vec![AdfNode::Text {
+                text: text.into(),
+                marks: None,
+            }]),
+        };
+        let expand = |title: Option<&str>, content| AdfBlockNode::Expand {
+            attrs: ExpandAttrs {
+                title: title.map(Into::into),
+                expanded: false,
+            },
+            content,
+        };
+
+        let adf = html_to_adf("<details><p>body</p></details>");
+        assert_content_eq(adf, vec![expand(None, vec![paragraph("body")])]);
+
+        // A summary only titles its own expand, not the next one or a nested one
+        let adf = html_to_adf(
+            "<details><summary>One</summary><p>a</p>\
+             <details data-nested=\"true\"><p>b</p></details></details>\
+             <details><p>c</p></details>",
+        );
+        assert_content_eq(
+            adf,
+            vec![
+                expand(
+                    Some("One"),
+                    vec![
+                        paragraph("a"),
+                        AdfBlockNode::NestedExpand {
+                            attrs: NestedAttrs {
+                                title: "".into(),
+                                expanded: false,
+                            },
+                            content: vec![paragraph("b")],
+                        },
+                    ],
+                ),
+                expand(None, vec![paragraph("c")]),
+            ],
+        );
+    }
 }
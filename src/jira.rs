@@ -0,0 +1,101 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::adf::adf_types::AdfBlockNode;
+
+#[derive(Debug)]
+pub enum JiraAdfError {
+    /// Nothing was found at this path, which ends at the first missing segment.
+    MissingField(String),
+    /// The value at the path isn't valid ADF.
+    InvalidAdf(serde_json::Error),
+}
+
+impl std::fmt::Display for JiraAdfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JiraAdfError::MissingField(path) => write!(f, "Missing field '{path}'"),
+            JiraAdfError::InvalidAdf(err) => write!(f, "Invalid ADF: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for JiraAdfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JiraAdfError::MissingField(_) => None,
+            JiraAdfError::InvalidAdf(err) => Some(err),
+        }
+    }
+}
+
+/// Parses the ADF found at a dotted path in a Jira API response, such as `fields.description`
+/// for an issue or `body` for a comment.
+///
+/// Numeric segments index into arrays, so `fields.comment.comments.0.body` reads the first
+/// comment embedded in an issue. An empty path parses the value itself as ADF. A
+/// [`JiraAdfError::MissingField`] names the path up to and including the first segment that
+/// wasn't found.
+pub fn extract_adf_from_jira(value: &Value, path: &str) -> Result<AdfBlockNode, JiraAdfError> {
+    let mut current = value;
+    for (i, segment) in path.split('.').filter(|_| !path.is_empty()).enumerate() {
+        let next = match current {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => current.get(segment),
+        };
+        current = next.ok_or_else(|| {
+            let walked: Vec<_> = path.split('.').take(i + 1).collect();
+            JiraAdfError::MissingField(walked.join("."))
+        })?;
+    }
+    AdfBlockNode::deserialize(current).map_err(JiraAdfError::InvalidAdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_adf_from_jira() {
+        let doc = json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{
+                "type": "paragraph",
+                "content": [{ "type": "text", "text": "Hi" }],
+            }],
+        });
+        let issue = json!({
+            "fields": {
+                "description": doc,
+                "comment": { "comments": [{ "id": "10000", "body": doc }] },
+            },
+        });
+        let expected: AdfBlockNode = serde_json::from_value(doc.clone()).unwrap();
+
+        assert_eq!(
+            extract_adf_from_jira(&issue, "fields.description").unwrap(),
+            expected
+        );
+        assert_eq!(
+            extract_adf_from_jira(&issue, "fields.comment.comments.0.body").unwrap(),
+            expected
+        );
+        assert_eq!(
+            extract_adf_from_jira(&json!({ "body": doc }), "body").unwrap(),
+            expected
+        );
+        assert_eq!(extract_adf_from_jira(&doc, "").unwrap(), expected);
+
+        let err = extract_adf_from_jira(&issue, "fields.comment.comments.1.body").unwrap_err();
+        assert!(
+            matches!(err, JiraAdfError::MissingField(path) if path == "fields.comment.comments.1")
+        );
+        assert!(matches!(
+            extract_adf_from_jira(&issue, "fields.comment"),
+            Err(JiraAdfError::InvalidAdf(_))
+        ));
+    }
+}
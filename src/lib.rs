@@ -1,7 +1,13 @@
 pub mod adf;
 pub mod adf_to_html;
+pub mod adf_to_rst;
+pub mod adf_to_simple;
+pub mod emoji;
 pub mod handlers;
 pub mod html_builder;
 pub mod html_sanitize;
 pub mod html_to_adf;
+pub mod jira;
 pub mod markdown;
+pub mod options;
+pub mod render;
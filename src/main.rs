@@ -1,59 +1,110 @@
-use serde_json::{Value, from_value};
+use serde_json::Value;
 use std::env;
 use std::fs;
+use std::io;
+use strum_macros::EnumString;
+use subseq_adf_convert::adf::adf_types::{to_json, to_json_pretty};
 use subseq_adf_convert::adf_to_html::adf_to_html;
+use subseq_adf_convert::jira::extract_adf_from_jira;
 use subseq_adf_convert::markdown::html_to_markdown;
 use subseq_adf_convert::markdown::markdown_to_adf;
 
-use subseq_adf_convert::adf::adf_types::AdfBlockNode;
+/// What the converted document is written to stdout as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+enum Output {
+    #[default]
+    Html,
+    Markdown,
+    /// The document parsed back from the Markdown, to show what the round trip loses
+    Adf,
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {program} [--to html|markdown|adf] [--pretty] [--field <field.path>] <input_file.json | ->"
+    );
+    std::process::exit(2);
+}
+
+/// Reports a conversion error and exits.
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {message}");
+    std::process::exit(1);
+}
 
 fn main() {
-    // Get the first argument (after the program name)
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        tracing::debug!(program = %args[0], "Usage: <program> <input_file.json>");
-        std::process::exit(1);
+    // Get the input file (`-` for stdin), the optional ADF field path, the output format and
+    // whether to indent the ADF JSON
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+    let mut output = Output::default();
+    let mut pretty = false;
+    let mut path = None;
+    let mut filename = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => {
+                output = args
+                    .next()
+                    .and_then(|to| to.parse().ok())
+                    .unwrap_or_else(|| usage(&program))
+            }
+            "--pretty" => pretty = true,
+            "--field" => path = Some(args.next().unwrap_or_else(|| usage(&program))),
+            flag if flag.starts_with('-') && flag != "-" => {
+                eprintln!("error: Unknown option {flag}");
+                usage(&program)
+            }
+            _ if filename.is_some() => usage(&program),
+            _ => filename = Some(arg),
+        }
     }
-
-    let filename = &args[1];
+    let Some(filename) = filename else {
+        usage(&program)
+    };
 
     // Read file contents
-    let contents = fs::read_to_string(filename).unwrap_or_else(|err| {
-        tracing::debug!(filename = %filename, error = %err, "Failed to read input file");
-        std::process::exit(1);
-    });
+    let contents = if filename == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(&filename)
+    }
+    .unwrap_or_else(|err| fail(format!("Failed to read {filename}: {err}")));
 
     // Parse as JSON
-    let json: Value = serde_json::from_str(&contents).unwrap_or_else(|err| {
-        tracing::debug!(error = %err, "Invalid JSON");
-        std::process::exit(1);
-    });
-
-    // Extract "description" field
-    let fields = json.get("fields").unwrap_or_else(|| {
-        tracing::debug!("Missing 'fields' field in JSON");
-        std::process::exit(1);
-    });
-
-    let description = fields.get("description").cloned().unwrap_or_else(|| {
-        tracing::debug!("Missing 'description' field in fields");
-        std::process::exit(1);
-    });
+    let json: Value =
+        serde_json::from_str(&contents).unwrap_or_else(|err| fail(format!("Invalid JSON: {err}")));
 
-    // Parse as AdfNode
-    let adf: AdfBlockNode = from_value(description).unwrap_or_else(|err| {
-        tracing::debug!(error = %err, "Failed to parse 'description' as AdfNode");
-        std::process::exit(1);
+    // Extract and parse the ADF field. Without a path, an issue's description is read, and
+    // anything else is taken to be a bare document.
+    let path = path.unwrap_or_else(|| {
+        let path = if json.get("fields").is_some() {
+            "fields.description"
+        } else {
+            ""
+        };
+        path.to_string()
     });
+    let adf = extract_adf_from_jira(&json, &path)
+        .unwrap_or_else(|err| fail(format!("Failed to extract ADF at `{path}`: {err}")));
     let html = adf_to_html(vec![adf], &contents);
-    println!("\n--- HTML ---\n{}\n---      ---\n", html);
+    if output == Output::Html {
+        println!("{html}");
+        return;
+    }
     let markdown = html_to_markdown(html);
-    println!("\n--- MARKDOWN ---\n{}\n---          ---\n", markdown);
+    if output == Output::Markdown {
+        println!("{markdown}");
+        return;
+    }
 
-    let adf = markdown_to_adf(&markdown);
-    let adf = adf.unwrap_or_else(|| {
-        tracing::debug!("Failed to convert markdown to AdfNode");
-        std::process::exit(1);
-    });
-    tracing::debug!(adf_json = %serde_json::to_string(&adf).unwrap(), "Converted markdown to ADF");
+    let adf = markdown_to_adf(&markdown)
+        .unwrap_or_else(|| fail("Failed to convert the Markdown back to ADF"));
+    let adf_json = if pretty {
+        to_json_pretty(&adf)
+    } else {
+        to_json(&adf)
+    };
+    println!("{}", adf_json.unwrap_or_else(|err| fail(err)));
 }
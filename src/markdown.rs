@@ -1,17 +1,32 @@
-use htmd::{Element, HtmlToMarkdown};
+use std::rc::Rc;
+
+use htmd::{Element, HtmlToMarkdown, HtmlToMarkdownBuilder};
 use html5ever::serialize::{SerializeOpts, serialize};
 use markdown::{CompileOptions, Options, ParseOptions, to_html_with_options as markdown_to_html};
 use markup5ever_rcdom::{Handle, NodeData, SerializableHandle};
 
 use crate::{
-    adf::adf_types::AdfBlockNode, adf_to_html::adf_to_html, html_sanitize::normalize_html,
-    html_to_adf::html_to_adf,
+    adf::adf_types::AdfBlockNode,
+    adf_to_html::adf_to_html,
+    html_sanitize::normalize_html,
+    html_to_adf::{AdfConvertError, extract_style, try_html_to_adf_with_options},
+    options::ConvertOptions,
 };
 
+const STATUS_PREFIX: &str = "{status:";
+
+/// Panel types that have a GitHub alert equivalent, paired with the alert kind.
+const PANEL_ALERTS: [(&str, &str); 4] = [
+    ("info", "NOTE"),
+    ("warning", "WARNING"),
+    ("error", "CAUTION"),
+    ("success", "TIP"),
+];
+
 pub(crate) fn table_handler(element: Element) -> Option<String> {
     let mut headers = vec![];
     let mut rows = vec![];
-    let internal_converter = create_converter();
+    let internal_converter = create_cell_converter();
 
     for child in element.node.children.borrow().iter() {
         if let NodeData::Element { ref name, .. } = child.data {
@@ -112,13 +127,9 @@ fn extract_table_row(node: &Handle, converter: &HtmlToMarkdown) -> Vec<String> {
                     )
                     .ok()?;
                     let html_string = String::from_utf8(buf).ok()?;
-                    Some(trim_newlines(
-                        &converter
-                            .convert(&html_string)
-                            .unwrap_or_default()
-                            .trim()
-                            .to_string(),
-                    ))
+                    Some(escape_table_cell(&trim_newlines(
+                        converter.convert(&html_string).unwrap_or_default().trim(),
+                    )))
                 } else {
                     None
                 }
@@ -129,6 +140,11 @@ fn extract_table_row(node: &Handle, converter: &HtmlToMarkdown) -> Vec<String> {
         .collect()
 }
 
+/// Escapes literal pipes so cell content can't split the GFM table row.
+fn escape_table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
 fn trim_newlines(text: &str) -> String {
     text.lines()
         .map(str::trim)
@@ -137,23 +153,558 @@ fn trim_newlines(text: &str) -> String {
         .join(" ")
 }
 
+/// Renders a status as `{status:Text|color|localId}` so it stays readable in Markdown.
+/// The local id segment is omitted when the status doesn't have one.
+fn status_handler(element: Element) -> Option<String> {
+    let mut color = None;
+    let mut local_id = None;
+    for attr in element.attrs {
+        match attr.name.local.as_ref() {
+            "style" => color = extract_style(&attr.value, "background-color"),
+            "data-local-id" if !attr.value.is_empty() => local_id = Some(attr.value.to_string()),
+            _ => {}
+        }
+    }
+
+    let text = element.content.trim();
+    let color = color.unwrap_or_else(|| "neutral".to_string());
+    // The syntax can't hold its own delimiters, so such statuses stay as raw HTML
+    if [text, &color, local_id.as_deref().unwrap_or_default()]
+        .iter()
+        .any(|value| value.contains(['|', '{', '}', '<', '\n']))
+    {
+        return raw_html_handler(element);
+    }
+    match local_id {
+        Some(local_id) => Some(format!("{STATUS_PREFIX}{text}|{color}|{local_id}}}")),
+        None => Some(format!("{STATUS_PREFIX}{text}|{color}}}")),
+    }
+}
+
+/// Renders rules as `---` on their own line. The surrounding blank lines keep it from being read
+/// as a setext heading underline.
+fn rule_handler(_element: Element) -> Option<String> {
+    Some("\n\n---\n\n".to_string())
+}
+
+/// Renders a paragraph without its surrounding blank lines when it sits in a tight list, so
+/// simple list items come out as `* item` lines with no blank lines between them.
+fn paragraph_handler(element: Element) -> Option<String> {
+    let in_tight_list = parent_node(element.node)
+        .filter(|li| element_name(li) == Some("li"))
+        .and_then(|li| parent_node(&li))
+        .is_some_and(|list| is_tight_list(&list));
+    if in_tight_list {
+        Some(element.content.trim_matches('\n').to_string())
+    } else {
+        Some(format!("\n\n{}\n\n", element.content))
+    }
+}
+
+/// A list is tight when each of its items holds a single paragraph, optionally followed by
+/// nested lists. Items with several paragraphs or other blocks make the whole list loose.
+fn is_tight_list(list: &Handle) -> bool {
+    list.children
+        .borrow()
+        .iter()
+        .filter(|item| element_name(item) == Some("li"))
+        .all(|item| {
+            let children = item.children.borrow();
+            let mut paragraphs = 0;
+            children.iter().filter_map(element_name).all(|name| {
+                paragraphs += usize::from(name == "p");
+                paragraphs <= 1 && (name == "p" || !is_block_tag(name))
+            })
+        })
+}
+
+fn is_block_tag(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "pre"
+            | "div"
+            | "blockquote"
+            | "table"
+            | "figure"
+            | "details"
+            | "hr"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+    ) || name.starts_with("adf-media")
+}
+
+fn element_name(node: &Handle) -> Option<&str> {
+    match node.data {
+        NodeData::Element { ref name, .. } => Some(name.local.as_ref()),
+        _ => None,
+    }
+}
+
+fn parent_node(node: &Handle) -> Option<Handle> {
+    let parent = node.parent.take();
+    let upgraded = parent.as_ref().and_then(|weak| weak.upgrade());
+    node.parent.set(parent);
+    upgraded
+}
+
+/// Renders bold and italic text with `marker`, falling back to `<strong>`/`<em>` when the
+/// delimiters couldn't open or close there.
+///
+/// Markdown only treats a delimiter next to punctuation or a symbol (including emoji) as
+/// emphasis when the character on its other side is whitespace or punctuation. Text such as
+/// `**👩‍💻**` followed by a letter or a direction mark (U+200E) would otherwise come back as
+/// literal asterisks. `_` also can't open or close inside a word.
+fn emphasis_handler(element: Element, marker: &str) -> Option<String> {
+    let trimmed = element.content.trim_start();
+    let leading = &element.content[..element.content.len() - trimmed.len()];
+    let content = trimmed.trim_end();
+    let trailing = &trimmed[content.len()..];
+    if content.is_empty() {
+        return None;
+    }
+
+    let (before, after) = sibling_chars(element.node);
+    let before = if leading.is_empty() {
+        before
+    } else {
+        Some(' ')
+    };
+    let after = if trailing.is_empty() {
+        after
+    } else {
+        Some(' ')
+    };
+    let delimits = |edge: Option<char>, outside: Option<char>| {
+        let outside_ok = outside.is_none_or(|c| c.is_whitespace() || c.is_ascii_punctuation());
+        let intraword = marker.starts_with('_') && !outside_ok;
+        outside_ok || (edge.is_some_and(char::is_alphanumeric) && !intraword)
+    };
+
+    if delimits(content.chars().next(), before) && delimits(content.chars().last(), after) {
+        Some(format!("{leading}{marker}{content}{marker}{trailing}"))
+    } else {
+        let tag = if marker == "**" { "strong" } else { "em" };
+        Some(format!("{leading}<{tag}>{content}</{tag}>{trailing}"))
+    }
+}
+
+/// The characters of the text immediately before and after a node, if its siblings are text.
+fn sibling_chars(node: &Handle) -> (Option<char>, Option<char>) {
+    let Some(parent) = parent_node(node) else {
+        return (None, None);
+    };
+    let children = parent.children.borrow();
+    let Some(index) = children.iter().position(|child| Rc::ptr_eq(child, node)) else {
+        return (None, None);
+    };
+    let text = |sibling: Option<&Handle>| match sibling.map(|sibling| &sibling.data) {
+        Some(NodeData::Text { contents }) => Some(contents.borrow().to_string()),
+        _ => None,
+    };
+    let before = index
+        .checked_sub(1)
+        .and_then(|i| text(children.get(i)))
+        .and_then(|text| text.chars().last());
+    let after = text(children.get(index + 1)).and_then(|text| text.chars().next());
+    (before, after)
+}
+
+/// Renders a panel as a GitHub alert (`> [!NOTE]`) when its type has an alert equivalent.
+/// Other panels, and figures that aren't panels, are kept as raw HTML.
+fn panel_handler(element: Element) -> Option<String> {
+    let alert = element
+        .attrs
+        .iter()
+        .find(|attr| attr.name.local.as_ref() == "data-panel-type")
+        .and_then(|attr| {
+            PANEL_ALERTS
+                .iter()
+                .find(|(panel_type, _)| *panel_type == attr.value.as_ref())
+        })
+        .map(|(_, alert)| *alert);
+    let Some(alert) = alert else {
+        return raw_html_handler(element);
+    };
+
+    let mut md = format!("\n\n> [!{alert}]\n");
+    for line in element.content.trim_matches('\n').lines() {
+        if line.is_empty() {
+            md.push_str(">\n");
+        } else {
+            md.push_str(&format!("> {line}\n"));
+        }
+    }
+    md.push('\n');
+    Some(md)
+}
+
+/// Passes a link through as raw HTML, or as an autolink when it shows its own URL.
+fn anchor_handler(element: Element) -> Option<String> {
+    let href = element
+        .attrs
+        .iter()
+        .find(|attr| attr.name.local.as_ref() == "href")
+        .map(|attr| attr.value.as_ref());
+    // Autolinks need a scheme and can't hold spaces or angle brackets
+    if let Some(href) = href
+        && element.attrs.len() == 1
+        && element.content == href
+        && href.split_once(':').is_some_and(|(scheme, _)| {
+            !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        && !href.contains(|c: char| c.is_whitespace() || c.is_control() || c == '<' || c == '>')
+    {
+        return Some(format!("<{href}>"));
+    }
+    // GFM would turn a URL in the text, like an inline card's, into a second link nested in this
+    // one, so the `://` is escaped to keep it plain text. Backslashes are literal in code spans,
+    // which aren't autolinked anyway.
+    if element.content.contains('`') {
+        return raw_html_handler(element);
+    }
+    let content = element.content.replace("://", r"\://");
+    raw_html_handler(Element {
+        content: &content,
+        ..element
+    })
+}
+
+/// Writes an image as `![alt](src)` when that says all there is to it. Images with a size, a
+/// border or a media id, and those inside the `adf-*` elements kept as raw HTML, stay as `<img>`
+/// so nothing is lost.
+fn image_handler(element: Element) -> Option<String> {
+    let mut src = None;
+    let mut alt = "";
+    for attr in element.attrs {
+        match (attr.name.local.as_ref(), attr.value.as_ref()) {
+            ("src", value) => src = Some(value),
+            ("alt", value) => alt = value,
+            ("data-collection" | "data-media-id", "") => {}
+            _ => return raw_html_handler(element),
+        }
+    }
+    let in_adf_element = std::iter::successors(parent_node(element.node), parent_node)
+        .any(|node| element_name(&node).is_some_and(|name| name.starts_with("adf-")));
+    match src {
+        Some(src)
+            if !in_adf_element
+                && !src.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '<'))
+                && !alt.contains(['[', ']', '\\']) =>
+        {
+            Some(format!("![{alt}]({src})"))
+        }
+        _ => raw_html_handler(element),
+    }
+}
+
+/// Passes an element through to the Markdown as raw HTML with its converted content.
+fn raw_html_handler(element: Element) -> Option<String> {
+    let attrs = element
+        .attrs
+        .iter()
+        .map(|attr| {
+            format!(
+                "{}=\"{}\"",
+                attr.name.local.as_ref(),
+                html_escape::encode_double_quoted_attribute(&attr.value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(format!(
+        "<{0} {1}>{2}</{0}>",
+        element.tag, attrs, element.content
+    ))
+}
+
+/// Walks HTML a tag or a character at a time, keeping track of whether the text is inside
+/// `<code>` or `<pre>`.
+struct HtmlScanner<'a> {
+    rest: &'a str,
+    code_depth: usize,
+}
+
+impl<'a> HtmlScanner<'a> {
+    fn new(html: &'a str) -> Self {
+        Self {
+            rest: html,
+            code_depth: 0,
+        }
+    }
+
+    /// The input that hasn't been scanned yet.
+    fn rest(&self) -> &'a str {
+        self.rest
+    }
+
+    fn in_code(&self) -> bool {
+        self.code_depth > 0
+    }
+
+    /// Skips `len` bytes of text.
+    fn advance(&mut self, len: usize) {
+        self.rest = &self.rest[len..];
+    }
+
+    /// Takes the next character of text.
+    fn take_char(&mut self) -> Option<char> {
+        let c = self.rest.chars().next()?;
+        self.advance(c.len_utf8());
+        Some(c)
+    }
+
+    /// Takes the tag or comment the input starts with, if any. A `>` inside a quoted attribute
+    /// value doesn't end the tag.
+    fn take_tag(&mut self) -> Option<&'a str> {
+        let len = tag_len(self.rest)?;
+        let tag = &self.rest[..len];
+        let name = tag[1..]
+            .split(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+            .find(|name| !name.is_empty())
+            .unwrap_or_default();
+        if matches!(name, "code" | "pre") {
+            if tag.starts_with("</") {
+                self.code_depth = self.code_depth.saturating_sub(1);
+            } else {
+                self.code_depth += 1;
+            }
+        }
+        self.advance(len);
+        Some(tag)
+    }
+}
+
+/// The length of the tag or comment `html` starts with, or `None` if it doesn't start with one.
+fn tag_len(html: &str) -> Option<usize> {
+    let after = html.strip_prefix('<')?;
+    if let Some(comment) = after.strip_prefix("!--") {
+        let end = comment.find("-->").map(|i| i + 3).unwrap_or(comment.len());
+        return Some(4 + end);
+    }
+    if !after.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?') {
+        return None;
+    }
+
+    let mut quote = None;
+    let mut after_equals = false;
+    for (i, c) in after.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '>' => return Some(i + 2),
+            None if after_equals && (c == '"' || c == '\'') => quote = Some(c),
+            None => {}
+        }
+        if !c.is_ascii_whitespace() {
+            after_equals = c == '=';
+        }
+    }
+    Some(html.len())
+}
+
+/// Replaces the blockquotes that GitHub alert syntax compiles to with panel figures.
+/// Alerts without a panel equivalent are left as blockquotes.
+fn expand_alert_syntax(html: &str) -> String {
+    const OPEN: &str = "\n<p>[!";
+
+    let mut output = String::with_capacity(html.len());
+    let mut scanner = HtmlScanner::new(html);
+    // For each open blockquote, whether it became a panel
+    let mut open_quotes: Vec<bool> = vec![];
+
+    while !scanner.rest().is_empty() {
+        let Some(tag) = scanner.take_tag() else {
+            output.extend(scanner.take_char());
+            continue;
+        };
+        if tag == "<blockquote>"
+            && let Some(after) = scanner.rest().strip_prefix(OPEN)
+            && let Some(close) = after.find(']')
+            && let Some((panel_type, _)) = PANEL_ALERTS
+                .iter()
+                .find(|(_, alert)| alert.eq_ignore_ascii_case(&after[..close]))
+        {
+            output.push_str(&format!("<figure data-panel-type=\"{panel_type}\">\n"));
+            open_quotes.push(true);
+            let after = &after[close + 1..];
+            let rest = match after.strip_prefix("</p>") {
+                Some(after) => after,
+                None => {
+                    output.push_str("<p>");
+                    after.trim_start_matches(['\n', ' '])
+                }
+            };
+            scanner.advance(scanner.rest().len() - rest.len());
+            continue;
+        }
+        if tag.starts_with("<blockquote") {
+            open_quotes.push(false);
+        } else if tag == "</blockquote>" && open_quotes.pop() == Some(true) {
+            output.push_str("</figure>");
+            continue;
+        }
+        output.push_str(tag);
+    }
+    output
+}
+
+/// Parses the inside of a `{status:...}` token, as it appears in HTML text, into an
+/// `<adf-status>` element.
+fn status_syntax_to_html(body: &str) -> Option<String> {
+    let body = html_escape::decode_html_entities(body);
+    let mut parts = body.split('|');
+    let text = parts.next()?.trim();
+    let color = parts.next()?.trim();
+    let local_id = parts.next().map(str::trim);
+    if parts.next().is_some() || text.is_empty() {
+        return None;
+    }
+
+    let color = if color.is_empty() { "neutral" } else { color };
+    let mut html = format!(
+        "<adf-status style=\"background-color: {}\"",
+        html_escape::encode_double_quoted_attribute(color)
+    );
+    if let Some(local_id) = local_id.filter(|id| !id.is_empty()) {
+        html.push_str(&format!(
+            " data-local-id=\"{}\"",
+            html_escape::encode_double_quoted_attribute(local_id)
+        ));
+    }
+    html.push_str(&format!(">{}</adf-status>", html_escape::encode_text(text)));
+    Some(html)
+}
+
+/// Replaces `{status:...}` tokens in the HTML produced from Markdown with `<adf-status>`
+/// elements. Text inside `<code>` and `<pre>` is left untouched.
+fn expand_status_syntax(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut scanner = HtmlScanner::new(html);
+
+    while !scanner.rest().is_empty() {
+        if let Some(tag) = scanner.take_tag() {
+            output.push_str(tag);
+            continue;
+        }
+
+        if !scanner.in_code()
+            && let Some(body) = scanner.rest().strip_prefix(STATUS_PREFIX)
+            && let Some(close) = body.find(['}', '<'])
+            && body[close..].starts_with('}')
+            && let Some(status) = status_syntax_to_html(&body[..close])
+        {
+            output.push_str(&status);
+            scanner.advance(STATUS_PREFIX.len() + close + 1);
+            continue;
+        }
+
+        output.extend(scanner.take_char());
+    }
+    output
+}
+
+/// Keeps entities in text from being decoded more than once on the way to Markdown.
+///
+/// htmd runs text through a second entity decoder after html5ever has already decoded it, and that
+/// decoder gives up after the first bare `&`, so every `&` is encoded once more to make htmd's
+/// output the actual text. On top of that, the Markdown itself is decoded when parsed: a literal
+/// `<` followed by a tag-like character would become raw HTML and a literal `&name;` would become
+/// a different character, so those are encoded a further time to stay entities in the Markdown.
+/// Text inside `<code>` and `<pre>` is left untouched because neither htmd nor Markdown decode it.
+///
+/// Literal `{status:` text gets an empty comment after the brace, which Markdown passes through
+/// and the HTML parser drops, so it isn't read back as a status.
+fn escape_markdown_sensitive_text(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut scanner = HtmlScanner::new(html);
+
+    while !scanner.rest().is_empty() {
+        if let Some(tag) = scanner.take_tag() {
+            output.push_str(tag);
+            continue;
+        }
+        if scanner.in_code() {
+            output.extend(scanner.take_char());
+            continue;
+        }
+
+        let rest = scanner.rest();
+        if rest.starts_with(STATUS_PREFIX) {
+            output.push_str("{&amp;lt;!----&amp;gt;");
+            scanner.advance(1);
+        } else if let Some(after) = rest.strip_prefix("&lt;")
+            && after.starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c))
+        {
+            output.push_str("&amp;amp;lt;");
+            scanner.advance("&lt;".len());
+        } else if let Some(after) = rest.strip_prefix("&amp;")
+            && looks_like_entity(after)
+        {
+            output.push_str("&amp;amp;amp;");
+            scanner.advance("&amp;".len());
+        } else if rest.starts_with('&') {
+            output.push_str("&amp;");
+            scanner.advance(1);
+        } else {
+            output.extend(scanner.take_char());
+        }
+    }
+    output
+}
+
+fn looks_like_entity(text: &str) -> bool {
+    let name_len = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+        .unwrap_or(text.len());
+    name_len > 0 && text[name_len..].starts_with(';')
+}
+
 fn create_converter() -> HtmlToMarkdown {
-    let converter = HtmlToMarkdown::builder()
+    converter_builder().build()
+}
+
+/// Converter for the content of a table cell, which must fit on a single line of the table.
+/// Panels stay as raw HTML since alert syntax can't span lines there.
+fn create_cell_converter() -> HtmlToMarkdown {
+    converter_builder()
+        .add_handler(vec!["figure"], |element: Element| {
+            raw_html_handler(Element {
+                content: element.content.trim(),
+                ..element
+            })
+        })
+        .build()
+}
+
+fn converter_builder() -> HtmlToMarkdownBuilder {
+    HtmlToMarkdown::builder()
         .add_handler(vec!["table"], table_handler)
+        .add_handler(vec!["adf-status"], status_handler)
+        .add_handler(vec!["figure"], panel_handler)
+        .add_handler(vec!["hr"], rule_handler)
+        .add_handler(vec!["p"], paragraph_handler)
+        .add_handler(vec!["strong", "b"], |element: Element| {
+            emphasis_handler(element, "**")
+        })
+        .add_handler(vec!["em", "i"], |element: Element| {
+            emphasis_handler(element, "_")
+        })
+        .add_handler(vec!["a"], anchor_handler)
+        .add_handler(vec!["img"], image_handler)
         .add_handler(
             vec![
-                "a",
                 "span",
                 "del",
-                "img",
                 "time",
                 "input",
-                "figure",
                 "details",
                 "summary",
                 "adf-emoji",
                 "adf-mention",
-                "adf-status",
                 "adf-media-single",
                 "adf-media-group",
                 "adf-decision-item",
@@ -163,26 +714,15 @@ fn create_converter() -> HtmlToMarkdown {
                 "adf-block-card-data-source",
                 "adf-block-card-view",
             ],
-            |element: Element| {
-                let attrs = element
-                    .attrs
-                    .iter()
-                    .map(|attr| format!("{}=\"{}\"", attr.name.local.as_ref(), attr.value))
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                Some(format!(
-                    "<{0} {1}>{2}</{0}>",
-                    element.tag, attrs, element.content
-                ))
-            },
+            raw_html_handler,
         )
-        .build();
-    converter
 }
 
 pub fn html_to_markdown(html: String) -> String {
     let converter = create_converter();
-    converter.convert(&html).unwrap_or_default()
+    converter
+        .convert(&escape_markdown_sensitive_text(&html))
+        .unwrap_or_default()
 }
 
 pub fn adf_to_markdown(adf: &[AdfBlockNode], buf: &str) -> String {
@@ -190,6 +730,32 @@ pub fn adf_to_markdown(adf: &[AdfBlockNode], buf: &str) -> String {
 }
 
 pub fn markdown_to_adf(markdown: &str) -> Option<AdfBlockNode> {
+    markdown_to_adf_with_options(markdown, &ConvertOptions::default())
+}
+
+/// Converts Markdown to ADF.
+///
+/// # Panics
+///
+/// Panics if the HTML the Markdown compiles to is nested in a way that has no ADF equivalent.
+/// Use [`try_markdown_to_adf_with_options`] to handle that instead.
+pub fn markdown_to_adf_with_options(
+    markdown: &str,
+    convert_options: &ConvertOptions,
+) -> Option<AdfBlockNode> {
+    Some(
+        try_markdown_to_adf_with_options(markdown, convert_options)
+            .unwrap_or_else(|error| panic!("{error}")),
+    )
+}
+
+/// Converts Markdown to ADF, returning an error instead of panicking on malformed nesting.
+pub fn try_markdown_to_adf_with_options(
+    markdown: &str,
+    convert_options: &ConvertOptions,
+) -> Result<AdfBlockNode, AdfConvertError> {
+    // A byte order mark would otherwise stop the first line from being read as a block
+    let markdown = markdown.strip_prefix('\u{FEFF}').unwrap_or(markdown);
     let parse_options = ParseOptions::gfm();
     let options = Options {
         parse: parse_options,
@@ -205,9 +771,15 @@ pub fn markdown_to_adf(markdown: &str) -> Option<AdfBlockNode> {
             tracing::warn!("Failed to convert markdown to HTML: {}", err);
         })
         .unwrap_or_default();
-    tracing::debug!(presanitized_html = %html, "Presanitized HTML");
+    let html = expand_status_syntax(&expand_alert_syntax(&html));
+    tracing::trace!(presanitized_html = %html, "Presanitized HTML");
     let sanitized = normalize_html(&html);
-    Some(html_to_adf(&sanitized))
+    // Paragraphs here are already Markdown that didn't parse as anything else
+    let convert_options = ConvertOptions {
+        interpret_markdown_in_text: false,
+        ..convert_options.clone()
+    };
+    try_html_to_adf_with_options(&sanitized, &convert_options)
 }
 
 #[cfg(feature = "fuzzing")]
@@ -235,7 +807,10 @@ mod tests {
         AdfNode::Doc {
             content: vec![
                 AdfNode::Heading {
-                    attrs: HeadingAttrs { level: 2 },
+                    attrs: HeadingAttrs {
+                        level: 2,
+                        ..Default::default()
+                    },
                     content: Some(vec![AdfNode::Text {
                         text: "User document".into(),
                         marks: None,
@@ -249,6 +824,7 @@ mod tests {
                         },
                         AdfNode::Emoji {
                             attrs: EmojiAttrs {
+                                id: None,
                                 short_name: ":smile:".into(),
                                 text: Some("😄".into()),
                             },
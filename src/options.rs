@@ -0,0 +1,145 @@
+use strum_macros::Display;
+
+/// Options controlling how documents are converted.
+///
+/// The plain entry points (`adf_to_html`, `html_to_adf`, ...) use `ConvertOptions::default()`;
+/// the `*_with_options` variants accept a caller-supplied set.
+#[derive(Clone, Debug)]
+pub struct ConvertOptions {
+    /// The flavour of HTML produced when rendering ADF.
+    pub html_profile: HtmlProfile,
+    /// Render panels, statuses, task lists and decision lists as standard tags with semantic
+    /// class names (e.g. `<div class="panel panel-info">`) instead of `adf-*` custom elements.
+    /// The output is meant for styling with existing CSS and does not round-trip. Wide and
+    /// full-width media also get a class instead of an inline width style.
+    pub use_class_names: bool,
+    /// Render a blockquote whose last paragraph starts with `—` or `--` as
+    /// `<figure><blockquote>...</blockquote><figcaption>...</figcaption></figure>`, treating that
+    /// paragraph as the attribution. ADF has no citation field, so this does not round-trip.
+    pub blockquote_attribution: bool,
+    /// Treat two consecutive `<br>` elements as a paragraph break instead of two hard breaks.
+    pub split_on_double_break: bool,
+    /// Base URL of an emoji image CDN. With the portable and email profiles, emoji that carry an
+    /// id render as `<img src="{base}/{id}.png">`; all other emoji render as their text or
+    /// shortcode.
+    pub emoji_cdn_base: Option<String>,
+    /// Drop empty paragraphs from the start and end of a parsed document. Empty paragraphs
+    /// between other blocks are kept.
+    pub trim_document_paragraphs: bool,
+    /// Normalize parsed code block languages with
+    /// [`normalize_language`](crate::adf::adf_types::normalize_language), e.g. `js` to
+    /// `javascript`.
+    pub normalize_code_language: bool,
+    /// What to do with parsed elements that have no ADF equivalent, such as `<iframe>`.
+    pub raw_html_policy: RawHtmlPolicy,
+    /// URL schemes that rendered links and images may use, compared case-insensitively.
+    /// Links with any other scheme render as their text and such images lose their `src`, so
+    /// `javascript:` and `data:` URLs never reach the output. Relative URLs are always allowed.
+    /// `None` allows every scheme.
+    pub allowed_url_schemes: Option<Vec<String>>,
+    /// Text direction set with a `dir` attribute on the element wrapping a rendered document.
+    /// ADF carries no direction of its own, at the document or on any block, so individual
+    /// blocks get no `dir` and nothing is read back when parsing.
+    pub default_dir: Option<TextDirection>,
+    /// Parse a paragraph holding nothing but an inline card (`<a data-inline-card>`) as a
+    /// block card for the same URL; see
+    /// [`promote_standalone_cards`](crate::adf::adf_types::AdfBlockNode::promote_standalone_cards).
+    /// Such paragraphs then no longer round-trip, so this is off by default.
+    pub promote_standalone_cards: bool,
+    /// Keep whitespace at the start of task and decision items, for content such as a
+    /// deliberately indented first line. By default it is trimmed. Paragraphs and other blocks
+    /// keep their leading whitespace either way, though a line break opening a text run is
+    /// dropped along with the whitespace before it.
+    pub preserve_leading_whitespace: bool,
+    /// Parse a top-level paragraph of plain text whose first line starts like a Markdown heading
+    /// or list item (`## `, `- `, `1. `) as Markdown, for Markdown pasted into an HTML field.
+    /// This is a heuristic: text that merely starts that way, such as `1. ` opening a sentence,
+    /// is converted too, and the rest of the paragraph is read with Markdown's escaping rules.
+    /// Markdown input itself is never re-interpreted.
+    pub interpret_markdown_in_text: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            html_profile: HtmlProfile::default(),
+            use_class_names: false,
+            blockquote_attribution: false,
+            split_on_double_break: true,
+            emoji_cdn_base: None,
+            trim_document_paragraphs: true,
+            normalize_code_language: false,
+            raw_html_policy: RawHtmlPolicy::default(),
+            allowed_url_schemes: Some(vec![
+                "http".to_string(),
+                "https".to_string(),
+                "mailto".to_string(),
+            ]),
+            default_dir: None,
+            promote_standalone_cards: false,
+            preserve_leading_whitespace: false,
+            interpret_markdown_in_text: false,
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Whether a URL may be rendered, per [`allowed_url_schemes`](Self::allowed_url_schemes).
+    pub fn allows_url(&self, url: &str) -> bool {
+        let Some(allowed) = &self.allowed_url_schemes else {
+            return true;
+        };
+        // Browsers ignore whitespace and control characters in schemes, e.g. `java\tscript:`
+        let url: String = url
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+            .collect();
+        let Some((scheme, _)) = url.split_once(':') else {
+            return true;
+        };
+        let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        // A colon after a path, query or fragment starts belongs to a relative URL
+        !is_scheme || allowed.iter().any(|s| s.eq_ignore_ascii_case(scheme))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HtmlProfile {
+    /// Custom `adf-*` elements carrying everything needed to parse the HTML back into ADF.
+    #[default]
+    RoundTrip,
+    /// The round-trip HTML with media groups rendered as a gallery of `<figure>`s and emoji as
+    /// CDN images or their text, which don't parse back. Other nodes still render as `adf-*`
+    /// elements; [`ConvertOptions::use_class_names`] covers panels, statuses, tasks and decisions.
+    Portable,
+    /// HTML for email clients: only widely supported tags, styled inline with no classes or
+    /// custom elements. Expands render as open sections since email has no `<details>`.
+    Email,
+}
+
+/// The direction of a document's text, as in the HTML `dir` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+    /// Let the browser pick the direction from the text itself.
+    Auto,
+}
+
+/// How parsing treats elements with no ADF equivalent, like `<iframe>`, `<video>` or `<svg>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RawHtmlPolicy {
+    /// Drop the element along with everything inside it.
+    Drop,
+    /// Keep the element's HTML as an `html` code block, or as inline code where a block can't
+    /// go, so nothing is lost.
+    AsCodeBlock,
+    /// Ignore the element's tags and parse its content like any other, which keeps fallback
+    /// text but loses the element itself.
+    #[default]
+    AsUnknownPassthrough,
+}
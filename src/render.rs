@@ -0,0 +1,316 @@
+use crate::adf::adf_types::{
+    AdfBlockNode, AdfMark, AdfNode, MediaMark, MediaNode, MediaType, Subsup, TableRowEntry,
+    TaskItemState,
+};
+use crate::adf_to_html::{date_time, mention_fallback_text};
+use crate::options::ConvertOptions;
+
+/// A table cell as rendered by a [`RenderBackend`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedCell {
+    pub header: bool,
+    /// The cell's blocks, already rendered.
+    pub blocks: Vec<String>,
+}
+
+/// A text format that ADF can be rendered to with [`render_adf`].
+///
+/// This serves plain-text formats such as reStructuredText. HTML and Markdown don't go through
+/// it: the HTML renderer in [`adf_to_html`](crate::adf_to_html) builds a node tree and carries the
+/// profiles and `adf-*` attributes that round-tripping needs, and Markdown is derived from that
+/// HTML. Helpers such as the mention fallback and URL filtering are shared with it instead.
+///
+/// The walker renders children first and hands the results to the parent's method, so each
+/// method only has to wrap content that is already in the target format. Inline methods
+/// receive rendered inline content, except for [`text`](Self::text) and
+/// [`code`](Self::code), which receive raw text to escape as needed. Methods with defaults
+/// cover nodes that many formats have no syntax for.
+pub trait RenderBackend {
+    /// Joins the top-level blocks of a document.
+    fn document(&self, blocks: Vec<String>) -> String {
+        let mut output = blocks.join("\n\n");
+        output.push('\n');
+        output
+    }
+    fn paragraph(&self, content: &str) -> String;
+    fn heading(&self, level: u8, content: &str) -> String;
+    fn code_block(&self, language: Option<&str>, code: &str) -> String;
+    fn blockquote(&self, blocks: Vec<String>) -> String;
+    fn bullet_list(&self, items: Vec<Vec<String>>) -> String;
+    fn ordered_list(&self, start: u32, items: Vec<Vec<String>>) -> String;
+    fn table(&self, rows: Vec<Vec<RenderedCell>>) -> String;
+    fn panel(&self, panel_type: &str, blocks: Vec<String>) -> String;
+    fn expand(&self, title: Option<&str>, blocks: Vec<String>) -> String;
+    fn rule(&self) -> String;
+    /// Renders an image or file. `src` is absent for media only known by its id.
+    fn media(&self, src: Option<&str>, alt: Option<&str>) -> String;
+
+    /// Renders a task list as a bullet list of checkboxes.
+    fn task_list(&self, items: Vec<(bool, String)>) -> String {
+        let items = items
+            .into_iter()
+            .map(|(done, content)| {
+                let marker = if done { "[x]" } else { "[ ]" };
+                vec![format!("{marker} {content}")]
+            })
+            .collect();
+        self.bullet_list(items)
+    }
+    fn decision_list(&self, items: Vec<String>) -> String {
+        self.bullet_list(items.into_iter().map(|item| vec![item]).collect())
+    }
+
+    fn text(&self, text: &str) -> String;
+    fn strong(&self, content: &str) -> String;
+    fn em(&self, content: &str) -> String;
+    fn code(&self, code: &str) -> String;
+    fn link(&self, href: &str, content: &str) -> String;
+    fn hard_break(&self) -> String;
+    fn strike(&self, content: &str) -> String {
+        content.to_string()
+    }
+    fn underline(&self, content: &str) -> String {
+        content.to_string()
+    }
+    fn subscript(&self, content: &str) -> String {
+        content.to_string()
+    }
+    fn superscript(&self, content: &str) -> String {
+        content.to_string()
+    }
+    fn colored(&self, _color: &str, content: &str) -> String {
+        content.to_string()
+    }
+    fn mention(&self, text: &str) -> String {
+        self.text(text)
+    }
+    fn emoji(&self, text: &str) -> String {
+        self.text(text)
+    }
+    fn status(&self, text: &str, _color: &str) -> String {
+        self.text(&format!("[{}]", text.to_uppercase()))
+    }
+    /// Renders a date, given as `YYYY-MM-DD`.
+    fn date(&self, date: &str) -> String {
+        self.text(date)
+    }
+}
+
+/// Renders a document with the given backend.
+pub fn render_adf(adf: &[AdfBlockNode], backend: &impl RenderBackend) -> String {
+    render_adf_with_options(adf, backend, &ConvertOptions::default())
+}
+
+/// Renders a document with the given backend. Links, media and cards whose URLs are not
+/// [allowed](ConvertOptions::allows_url) lose their URL, as in the HTML rendering.
+pub fn render_adf_with_options(
+    adf: &[AdfBlockNode],
+    backend: &impl RenderBackend,
+    options: &ConvertOptions,
+) -> String {
+    let blocks = adf
+        .iter()
+        .flat_map(|node| match node {
+            // The document is the outermost container, so its blocks are rendered directly
+            AdfBlockNode::Doc { content, .. } => render_blocks(content, backend, options),
+            node => render_block(node, backend, options).into_iter().collect(),
+        })
+        .collect();
+    backend.document(blocks)
+}
+
+fn render_blocks(
+    nodes: &[AdfBlockNode],
+    backend: &impl RenderBackend,
+    options: &ConvertOptions,
+) -> Vec<String> {
+    nodes
+        .iter()
+        .filter_map(|node| render_block(node, backend, options))
+        .filter(|block| !block.is_empty())
+        .collect()
+}
+
+fn render_block(
+    node: &AdfBlockNode,
+    backend: &impl RenderBackend,
+    options: &ConvertOptions,
+) -> Option<String> {
+    let rendered = match node {
+        AdfBlockNode::Doc { content, .. } => render_blocks(content, backend, options).join("\n\n"),
+        AdfBlockNode::Blockquote { content } => {
+            backend.blockquote(render_blocks(content, backend, options))
+        }
+        AdfBlockNode::BulletList { content } => backend.bullet_list(
+            content
+                .iter()
+                .map(|item| render_blocks(item.content(), backend, options))
+                .collect(),
+        ),
+        AdfBlockNode::OrderedList { content, attrs } => backend.ordered_list(
+            attrs.as_ref().and_then(|attrs| attrs.order).unwrap_or(1),
+            content
+                .iter()
+                .map(|item| render_blocks(item.content(), backend, options))
+                .collect(),
+        ),
+        AdfBlockNode::CodeBlock { attrs, content } => {
+            let code: String = content
+                .iter()
+                .flatten()
+                .filter_map(|node| match node {
+                    AdfNode::Text { text, .. } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let language = attrs.as_ref().and_then(|attrs| attrs.language.as_deref());
+            backend.code_block(language, &code)
+        }
+        AdfBlockNode::Expand { content, attrs } => backend.expand(
+            attrs.title.as_deref(),
+            render_blocks(content, backend, options),
+        ),
+        AdfBlockNode::NestedExpand { content, attrs } => {
+            backend.expand(Some(&attrs.title), render_blocks(content, backend, options))
+        }
+        AdfBlockNode::Paragraph { content } => {
+            backend.paragraph(&render_inline(content.iter().flatten(), backend, options))
+        }
+        AdfBlockNode::Heading { attrs, content } => backend.heading(
+            attrs.level,
+            &render_inline(content.iter().flatten(), backend, options),
+        ),
+        AdfBlockNode::Rule => backend.rule(),
+        AdfBlockNode::Panel { content, attrs } => {
+            backend.panel(&attrs.panel_type, render_blocks(content, backend, options))
+        }
+        AdfBlockNode::MediaGroup { content } | AdfBlockNode::MediaSingle { content, .. } => content
+            .iter()
+            .map(|media| render_media(media, backend, options))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        AdfBlockNode::Table { content, .. } => backend.table(
+            content
+                .iter()
+                .map(|row| {
+                    row.content()
+                        .iter()
+                        .map(|entry| match entry {
+                            TableRowEntry::TableHeader(header) => RenderedCell {
+                                header: true,
+                                blocks: render_blocks(header.content(), backend, options),
+                            },
+                            TableRowEntry::TableCell(cell) => RenderedCell {
+                                header: false,
+                                blocks: render_blocks(cell.content(), backend, options),
+                            },
+                        })
+                        .collect()
+                })
+                .collect(),
+        ),
+        AdfBlockNode::BlockCard { attrs } if options.allows_url(&attrs.url) => {
+            backend.paragraph(&backend.link(&attrs.url, &backend.text(&attrs.url)))
+        }
+        AdfBlockNode::BlockCard { .. } => return None,
+        AdfBlockNode::TaskList { content, .. } => backend.task_list(
+            content
+                .iter()
+                .map(|item| {
+                    (
+                        item.attrs().state == TaskItemState::Done,
+                        render_inline(item.content(), backend, options),
+                    )
+                })
+                .collect(),
+        ),
+        AdfBlockNode::DecisionList { content, .. } => backend.decision_list(
+            content
+                .iter()
+                .map(|item| render_inline(item.content(), backend, options))
+                .collect(),
+        ),
+        AdfBlockNode::Unknown => return None,
+    };
+    Some(rendered)
+}
+
+fn render_media(
+    media: &MediaNode,
+    backend: &impl RenderBackend,
+    options: &ConvertOptions,
+) -> String {
+    let src = media.marks.iter().flatten().find_map(|mark| match mark {
+        MediaMark::Link(link) if options.allows_url(&link.href) => Some(link.href.as_str()),
+        _ => None,
+    });
+    backend.media(src, media.attrs.alt.as_deref())
+}
+
+fn render_inline<'a>(
+    nodes: impl IntoIterator<Item = &'a AdfNode>,
+    backend: &impl RenderBackend,
+    options: &ConvertOptions,
+) -> String {
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            AdfNode::Text { text, marks } => {
+                let marks = marks.as_deref().unwrap_or_default();
+                // Code content is verbatim, so it is escaped as code rather than as text
+                let mut content = if marks.contains(&AdfMark::Code) {
+                    backend.code(text)
+                } else {
+                    backend.text(text)
+                };
+                // The first mark is the outermost, as in the HTML rendering
+                for mark in marks.iter().rev() {
+                    content = match mark {
+                        AdfMark::Strong => backend.strong(&content),
+                        AdfMark::Em => backend.em(&content),
+                        AdfMark::Code => content,
+                        // Links with disallowed schemes keep only their text
+                        AdfMark::Link(link) if !options.allows_url(&link.href) => content,
+                        AdfMark::Link(link) => backend.link(&link.href, &content),
+                        AdfMark::Strike => backend.strike(&content),
+                        AdfMark::Underline => backend.underline(&content),
+                        AdfMark::Subsup { type_ } => match type_ {
+                            Subsup::Sub => backend.subscript(&content),
+                            Subsup::Sup => backend.superscript(&content),
+                        },
+                        AdfMark::TextColor { color } | AdfMark::BackgroundColor { color } => {
+                            backend.colored(color, &content)
+                        }
+                    };
+                }
+                content
+            }
+            AdfNode::HardBreak => backend.hard_break(),
+            AdfNode::Mention { attrs } => match &attrs.text {
+                Some(text) => backend.mention(text),
+                None => backend.mention(&mention_fallback_text(&attrs.id)),
+            },
+            AdfNode::Emoji { attrs } => {
+                backend.emoji(attrs.text.as_deref().unwrap_or(&attrs.short_name))
+            }
+            AdfNode::Status { attrs } => backend.status(&attrs.text, &attrs.color),
+            AdfNode::Date { attrs } => {
+                backend.date(&date_time(attrs).format("%Y-%m-%d").to_string())
+            }
+            AdfNode::InlineCard { attrs } => match &attrs.url {
+                Some(url) if options.allows_url(url) => backend.link(url, &backend.text(url)),
+                _ => String::new(),
+            },
+            AdfNode::MediaInline { attrs, marks } => render_media(
+                &MediaNode {
+                    media_type: MediaType::Media,
+                    attrs: attrs.clone(),
+                    marks: marks.clone(),
+                },
+                backend,
+                options,
+            ),
+            AdfNode::Unknown => String::new(),
+        })
+        .collect()
+}